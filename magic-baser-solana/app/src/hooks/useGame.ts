@@ -31,6 +31,7 @@ import {
   buildDelegateSessionTx,
   buildUndelegateSessionTx,
 } from "@/solana/systems";
+import type { UpdateStats } from "@/solana/systems";
 import { useGuest } from "@/contexts/GuestContext";
 
 // World ID from constants (BN for SDK compatibility)
@@ -73,6 +74,10 @@ export function useGame() {
   const syncIntervalRef = useRef<NodeJS.Timeout | null>(null);
   const gameStartTimeRef = useRef<number>(0);
   const lastKillSyncRef = useRef<number>(0);
+  // Last update_stats tick sent; the session starts at 0 and accepts only tick + 1
+  const statsTickRef = useRef<number>(0);
+  // Stats sent with that tick; the next one only carries the fields that changed since
+  const lastStatsRef = useRef<UpdateStats | null>(null);
   const localStateRef = useRef<LocalGameState>(localState);
 
   // Session keypair for auto-signing ER transactions (no wallet popup)
@@ -332,6 +337,8 @@ export function useGame() {
         // Reset transaction counter
         setTxCount(0);
         lastKillSyncRef.current = 0;
+        statsTickRef.current = 0;
+        lastStatsRef.current = null;
 
         // Create session keypair for auto-signing ER transactions
         sessionKeypairRef.current = Keypair.generate();
@@ -360,22 +367,28 @@ export function useGame() {
     try {
       const sessionKp = sessionKeypairRef.current;
 
+      const stats: UpdateStats = {
+        hp: localState.hp,
+        xp: localState.xp,
+        goldEarned: localState.gold,
+        timeSurvived: localState.timeSurvived,
+        wave: localState.wave,
+        kills: localState.kills,
+        level: localState.level,
+        isDead: localState.isDead,
+        tick: ++statsTickRef.current,
+      };
+      const previous = lastStatsRef.current ?? undefined;
+      lastStatsRef.current = stats;
+
       const tx = await buildUpdateStatsTx(
         worldPda,
         WORLD_ID,
         publicKey,
         sessionKp.publicKey,
-        {
-          hp: localState.hp,
-          xp: localState.xp,
-          goldEarned: localState.gold,
-          timeSurvived: localState.timeSurvived,
-          wave: localState.wave,
-          kills: localState.kills,
-          level: localState.level,
-          isDead: localState.isDead,
-        },
-        erConnection
+        stats,
+        erConnection,
+        previous
       );
 
       tx.recentBlockhash = (await erConnection.getLatestBlockhash()).blockhash;
@@ -405,22 +418,28 @@ export function useGame() {
     try {
       const sessionKp = sessionKeypairRef.current;
 
+      const stats: UpdateStats = {
+        hp: state.hp,
+        xp: state.xp,
+        goldEarned: state.gold,
+        timeSurvived: state.timeSurvived,
+        wave: state.wave,
+        kills: state.kills,
+        level: state.level,
+        isDead: state.isDead,
+        tick: ++statsTickRef.current,
+      };
+      const previous = lastStatsRef.current ?? undefined;
+      lastStatsRef.current = stats;
+
       const tx = await buildUpdateStatsTx(
         worldPda,
         WORLD_ID,
         publicKey,
         sessionKp.publicKey,
-        {
-          hp: state.hp,
-          xp: state.xp,
-          goldEarned: state.gold,
-          timeSurvived: state.timeSurvived,
-          wave: state.wave,
-          kills: state.kills,
-          level: state.level,
-          isDead: state.isDead,
-        },
-        erConnection
+        stats,
+        erConnection,
+        previous
       );
 
       tx.recentBlockhash = (await erConnection.getLatestBlockhash()).blockhash;
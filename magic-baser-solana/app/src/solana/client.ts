@@ -40,6 +40,10 @@ const LEADERBOARD_IDL: Idl = {
           { name: "gamesPlayed", type: "u32" },
           { name: "updatedAt", type: "i64" },
           { name: "characterId", type: "string" },
+          { name: "environmentId", type: "u8" },
+          { name: "bestKills", type: "u32" },
+          { name: "bestScore", type: "u64" },
+          { name: "characterIndex", type: "u8" },
           { name: "boltMetadata", type: { defined: { name: "BoltMetadata" } } },
         ],
       },
@@ -72,6 +76,12 @@ export function getEntitySeed(authority: PublicKey, suffix: string): Uint8Array
   return new TextEncoder().encode(seed);
 }
 
+// Session entity suffix for a save slot: "session" for slot 0 (the original single session),
+// "session-{slot}" otherwise (game_rules::seeds::session_seed_suffix)
+export function sessionSeedSuffix(slot = 0): string {
+  return slot === 0 ? "session" : `session-${slot}`;
+}
+
 // Check if player exists
 export async function checkPlayerExists(
   connection: Connection,
@@ -165,9 +175,10 @@ export async function fetchPlayerData(
 export async function checkSessionDelegated(
   connection: Connection,
   worldId: BN,
-  authority: PublicKey
+  authority: PublicKey,
+  slot = 0
 ): Promise<boolean> {
-  const sessionSeed = getEntitySeed(authority, "session");
+  const sessionSeed = getEntitySeed(authority, sessionSeedSuffix(slot));
   const sessionEntity = FindEntityPda({
     worldId,
     seed: sessionSeed,
@@ -206,6 +217,7 @@ export interface LeaderboardEntryRaw {
   gamesPlayed: number;
   updatedAt: bigint;
   characterId: string;
+  bestScore: bigint;
 }
 
 // Fetch all leaderboard entries using getProgramAccounts with proper Anchor deserialization
@@ -249,6 +261,7 @@ export async function fetchAllLeaderboardEntries(
         gamesPlayed: decoded.gamesPlayed || 0,
         updatedAt: BigInt(decoded.updatedAt?.toString() || "0"),
         characterId: decoded.characterId || "",
+        bestScore: BigInt(decoded.bestScore?.toString() || "0"),
       });
     } catch (err) {
       console.warn("[Leaderboard] Failed to decode entry:", pubkey.toBase58(), err);
@@ -298,6 +311,7 @@ export async function fetchAllLeaderboardEntries(
           gamesPlayed,
           updatedAt,
           characterId,
+          bestScore: BigInt(0),
         });
       } catch (fallbackErr) {
         console.error("[Leaderboard] Fallback parsing also failed:", fallbackErr);
@@ -320,7 +334,18 @@ function isValidDisplayName(str: string): boolean {
   return validChars.test(str) && !hasControlChars && str.length <= 30;
 }
 
-// Rank leaderboard entries by bestWave > bestTime > totalGold
+// Leaderboard order: bestScore > bestWave > bestTime > totalGold, then the entry pubkey (bytes)
+// The pubkey tie-break makes the order total, so paged fetches never shuffle equal entries
+// Mirrors `leaderboard::compare_entries`, where the order is tested
+export function compareLeaderboardEntries(a: LeaderboardEntryRaw, b: LeaderboardEntryRaw): number {
+  if (b.bestScore !== a.bestScore) return b.bestScore > a.bestScore ? 1 : -1;
+  if (b.bestWave !== a.bestWave) return b.bestWave - a.bestWave;
+  if (b.bestTime !== a.bestTime) return b.bestTime - a.bestTime;
+  if (b.totalGold !== a.totalGold) return b.totalGold > a.totalGold ? 1 : -1;
+  return a.publicKey.toBuffer().compare(b.publicKey.toBuffer());
+}
+
+// Rank leaderboard entries with compareLeaderboardEntries
 export function rankLeaderboardEntries(
   entries: LeaderboardEntryRaw[],
   currentPlayerPubkey?: PublicKey,
@@ -343,12 +368,8 @@ export function rankLeaderboardEntries(
     }
   }
 
-  // Sort: higher wave first, then higher time, then higher gold
-  const sorted = [...entries].sort((a, b) => {
-    if (b.bestWave !== a.bestWave) return b.bestWave - a.bestWave;
-    if (b.bestTime !== a.bestTime) return b.bestTime - a.bestTime;
-    return Number(b.totalGold - a.totalGold);
-  });
+  // Sort: higher score first, then higher wave, time and gold; stable on the entry pubkey
+  const sorted = [...entries].sort(compareLeaderboardEntries);
 
   return sorted.map((entry, index) => {
     const walletAddress = entry.player?.toBase58() || "";
@@ -69,6 +69,9 @@ export const WORLD_PDA = WORLD_PDAS[NETWORK];
 export const PLAYER_COMPONENT_ID = new PublicKey("6URqfQrK5GTfc9HuyT43B2CLx38ZR4uu9nAhXdDmoy7M");
 export const GAME_SESSION_COMPONENT_ID = new PublicKey("9zbUFw8u3XzzNRA3TDQsGG2AkEuu2AQBXFYPxAZuWhTo");
 export const LEADERBOARD_COMPONENT_ID = new PublicKey("DsGfKAe1dC62tx3AkwAad2RsvYqNFF69ki73KdemF53P");
+export const CONFIG_COMPONENT_ID = new PublicKey("Ad7EUG4NMhEk8xwjksLYpStGhi61wt3fsdVuHDtrARxk");
+export const WAVE_HISTORY_COMPONENT_ID = new PublicKey("A3Y1nnUGN8L2qnENirtMwhWEF52yY5W5WtdrvwLf7oGA");
+export const PLAYER_EXTRAS_COMPONENT_ID = new PublicKey("7aSvn5uavbTqJjR55dX1aejtDTngUxS3Xr6Tpqcm84mW");
 
 // System Program IDs
 export const INIT_PLAYER_SYSTEM_ID = new PublicKey("GLR24FCjCRLcEJN37gGcZh9KBnKtM4rKRHdAFchNwprj");
@@ -77,6 +80,14 @@ export const UPDATE_STATS_SYSTEM_ID = new PublicKey("7FeyB4hz8LCrBYJusgEzKReT9rb
 export const USE_REVIVE_SYSTEM_ID = new PublicKey("GwmXPNJE1MWXBgWaMyYZiemEdboAYFceanBZUkEmBA7H");
 export const END_GAME_SYSTEM_ID = new PublicKey("9ytUaZtMR4NGUPTdJbmpbX8hhpmME8muwUGXZVSq8reY");
 export const SUBMIT_SCORE_SYSTEM_ID = new PublicKey("6did5KX3mcbi58jUQ85ZtTV5ahCD71pfFSF96cu73g2A");
+export const COLLECT_GOLD_SYSTEM_ID = new PublicKey("8JYA9dkoC37RU2TfDMYiSUt6dUAVQPSiNg5Uc6UHrXDw");
+
+// Session keys program (our fork of gpl_session, patches/session-keys), issuer of the tokens
+// update_stats accepts from session keypairs
+export const SESSION_KEYS_PROGRAM_ID = new PublicKey("2YrfV6Du5kraNbG4WBcHaGu3dMzDDvPaghj4Twnmd73C");
+
+// Name registry (magic_baser_solana program), holder of the NameRecord PDAs init_player checks
+export const NAME_REGISTRY_PROGRAM_ID = new PublicKey("PMCNPTdTaQi8zSayLLAth3Y1wSG3EpGqSRYJNZiSpax");
 
 // Game constants
 export const GAME_SYNC_INTERVAL_MS = 200; // Sync to ER every 200ms
@@ -1,4 +1,12 @@
-import { PublicKey, Transaction, Connection, Keypair, VersionedTransaction } from "@solana/web3.js";
+import {
+  PublicKey,
+  Transaction,
+  TransactionInstruction,
+  Connection,
+  Keypair,
+  VersionedTransaction,
+  SystemProgram,
+} from "@solana/web3.js";
 import { BN } from "@coral-xyz/anchor";
 import {
   ApplySystem,
@@ -18,11 +26,17 @@ import {
   USE_REVIVE_SYSTEM_ID,
   END_GAME_SYSTEM_ID,
   SUBMIT_SCORE_SYSTEM_ID,
+  COLLECT_GOLD_SYSTEM_ID,
   PLAYER_COMPONENT_ID,
   GAME_SESSION_COMPONENT_ID,
   LEADERBOARD_COMPONENT_ID,
+  CONFIG_COMPONENT_ID,
+  WAVE_HISTORY_COMPONENT_ID,
+  PLAYER_EXTRAS_COMPONENT_ID,
+  SESSION_KEYS_PROGRAM_ID,
+  NAME_REGISTRY_PROGRAM_ID,
 } from "./constants";
-import { getEntitySeed } from "./client";
+import { getEntitySeed, sessionSeedSuffix } from "./client";
 
 // Dummy wallet for Anchor provider (we only need to build instructions, not sign)
 class DummyWallet {
@@ -80,6 +94,10 @@ export async function buildInitPlayerTx(
 
   const tx = new Transaction();
 
+  // Claim the name in the registry; init_player checks the signer holds its record
+  const { instruction: claimIx, nameRecord } = buildClaimNameIx(authority, name);
+  tx.add(claimIx);
+
   // Create player entity
   const playerEntityResult = await AddEntity({
     payer: authority,
@@ -107,6 +125,14 @@ export async function buildInitPlayerTx(
   });
   tx.add(playerCompResult.instruction);
 
+  // Initialize PlayerExtras component (fields added after the Player layout was frozen)
+  const extrasCompResult = await InitializeComponent({
+    payer: authority,
+    entity: playerEntityResult.entityPda,
+    componentId: PLAYER_EXTRAS_COMPONENT_ID,
+  });
+  tx.add(extrasCompResult.instruction);
+
   // Initialize LeaderboardEntry component
   const lbCompResult = await InitializeComponent({
     payer: authority,
@@ -133,6 +159,7 @@ export async function buildInitPlayerTx(
         components: [{ componentId: LEADERBOARD_COMPONENT_ID }],
       },
     ],
+    extraAccounts: [{ pubkey: nameRecord, isSigner: false, isWritable: false }],
     args: {
       name: name,
     },
@@ -142,17 +169,53 @@ export async function buildInitPlayerTx(
   return tx;
 }
 
+// Name record PDA: ["name", sha256(lowercase(name))], case-insensitive like the program
+function findNameRecordPda(name: string): { nameHash: Buffer; nameRecord: PublicKey } {
+  const nameHash = Buffer.from(anchor.utils.sha256.hash(name.toLowerCase()), "hex");
+  const [nameRecord] = PublicKey.findProgramAddressSync(
+    [Buffer.from("name"), nameHash],
+    NAME_REGISTRY_PROGRAM_ID
+  );
+  return { nameHash, nameRecord };
+}
+
+// claim_name(name_hash: [u8; 32], name: String), built by hand as the app ships no registry IDL
+const CLAIM_NAME_DISCRIMINATOR = Buffer.from([198, 183, 177, 101, 183, 218, 69, 237]);
+
+function buildClaimNameIx(
+  authority: PublicKey,
+  name: string
+): { instruction: TransactionInstruction; nameRecord: PublicKey } {
+  const { nameHash, nameRecord } = findNameRecordPda(name);
+  const nameBytes = Buffer.from(name, "utf8");
+  const nameLen = Buffer.alloc(4);
+  nameLen.writeUInt32LE(nameBytes.length, 0);
+
+  const instruction = new TransactionInstruction({
+    programId: NAME_REGISTRY_PROGRAM_ID,
+    keys: [
+      { pubkey: nameRecord, isSigner: false, isWritable: true },
+      { pubkey: authority, isSigner: true, isWritable: true },
+      { pubkey: SystemProgram.programId, isSigner: false, isWritable: false },
+    ],
+    data: Buffer.concat([CLAIM_NAME_DISCRIMINATOR, nameHash, nameLen, nameBytes]),
+  });
+  return { instruction, nameRecord };
+}
+
 // Start game - creates session and calls start_game system
 export async function buildStartGameTx(
   worldPda: PublicKey,
   worldId: BN,
   authority: PublicKey,
   characterId: string,
-  connection: Connection
+  connection: Connection,
+  difficulty = 0,
+  slot = 0
 ): Promise<Transaction> {
   const tx = new Transaction();
 
-  const sessionSeed = getEntitySeed(authority, "session");
+  const sessionSeed = getEntitySeed(authority, sessionSeedSuffix(slot));
   const sessionEntity = FindEntityPda({
     worldId,
     seed: sessionSeed,
@@ -185,17 +248,40 @@ export async function buildStartGameTx(
     tx.add(sessionCompResult.instruction);
   }
 
+  // WaveHistory lives next to GameSession; sessions created before it existed get it here
+  const waveHistoryComponent = FindComponentPda({
+    componentId: WAVE_HISTORY_COMPONENT_ID,
+    entity: sessionEntity,
+  });
+  if (!(await connection.getAccountInfo(waveHistoryComponent))) {
+    const waveHistoryResult = await InitializeComponent({
+      payer: authority,
+      entity: sessionEntity,
+      componentId: WAVE_HISTORY_COMPONENT_ID,
+    });
+    tx.add(waveHistoryResult.instruction);
+  }
+
+  // PlayerExtras lives next to Player; players created before it existed get it here
+  const playerEntity = FindEntityPda({ worldId, seed: getEntitySeed(authority, "player") });
+  tx.add(...(await initPlayerExtrasIfMissing(authority, playerEntity, connection)));
+
   // Setup Anchor provider for BOLT SDK
   setupAnchorProvider(connection);
 
-  // Serialize character_id as args (4 bytes len + string bytes)
+  // Serialize character_id as args (4 bytes len + string bytes + difficulty byte + slot byte)
   const charIdBytes = new TextEncoder().encode(characterId);
-  const args = new Uint8Array(4 + charIdBytes.length);
+  const args = new Uint8Array(4 + charIdBytes.length + 2);
   const view = new DataView(args.buffer);
   view.setUint32(0, charIdBytes.length, true); // little-endian
   args.set(charIdBytes, 4);
+  args[4 + charIdBytes.length] = difficulty;
+  args[4 + charIdBytes.length + 1] = slot;
+
+  // World-wide config entity (death threshold)
+  const configEntity = FindEntityPda({ worldId, seed: new TextEncoder().encode("config") });
 
-  // Call start_game system with character_id (only session, player updated in end_game)
+  // Call start_game system with character_id (player only touched for the starter revives)
   const startResult = await ApplySystem({
     authority,
     systemId: START_GAME_SYSTEM_ID,
@@ -205,7 +291,17 @@ export async function buildStartGameTx(
         entity: sessionEntity,
         components: [{ componentId: GAME_SESSION_COMPONENT_ID }],
       },
+      {
+        entity: configEntity,
+        components: [{ componentId: CONFIG_COMPONENT_ID }],
+      },
+      {
+        entity: playerEntity,
+        components: [{ componentId: PLAYER_COMPONENT_ID }, { componentId: PLAYER_EXTRAS_COMPONENT_ID }],
+      },
     ],
+    // The system checks PlayerExtras sits on the same entity as Player
+    extraAccounts: [{ pubkey: playerEntity, isSigner: false, isWritable: false }],
     args: Buffer.from(args),
   });
   tx.add(startResult.instruction);
@@ -213,6 +309,27 @@ export async function buildStartGameTx(
   return tx;
 }
 
+// InitializeComponent for the player's PlayerExtras, or nothing if it already exists
+async function initPlayerExtrasIfMissing(
+  authority: PublicKey,
+  playerEntity: PublicKey,
+  connection: Connection
+): Promise<TransactionInstruction[]> {
+  const extrasComponent = FindComponentPda({
+    componentId: PLAYER_EXTRAS_COMPONENT_ID,
+    entity: playerEntity,
+  });
+  if (await connection.getAccountInfo(extrasComponent)) {
+    return [];
+  }
+  const extrasResult = await InitializeComponent({
+    payer: authority,
+    entity: playerEntity,
+    componentId: PLAYER_EXTRAS_COMPONENT_ID,
+  });
+  return [extrasResult.instruction];
+}
+
 // Update stats (gasless on ER)
 // entityOwner: the public key used for entity derivation (user's wallet)
 // signer: the public key that will sign the transaction (can be session keypair)
@@ -221,20 +338,14 @@ export async function buildUpdateStatsTx(
   worldId: BN,
   entityOwner: PublicKey,
   signer: PublicKey,
-  stats: {
-    hp: number;
-    xp: number;
-    goldEarned: number;
-    timeSurvived: number;
-    wave: number;
-    kills: number;
-    level: number;
-    isDead: boolean;
-  },
-  connection: Connection
+  stats: UpdateStats,
+  connection: Connection,
+  // Last stats the chain accepted; when given, only the changed fields are sent
+  previous?: UpdateStats,
+  slot = 0
 ): Promise<Transaction> {
   // Entity is derived from the original owner (user's wallet)
-  const sessionSeed = getEntitySeed(entityOwner, "session");
+  const sessionSeed = getEntitySeed(entityOwner, sessionSeedSuffix(slot));
   const sessionEntity = FindEntityPda({
     worldId,
     seed: sessionSeed,
@@ -243,27 +354,161 @@ export async function buildUpdateStatsTx(
   // Setup Anchor provider for BOLT SDK
   setupAnchorProvider(connection);
 
-  // Signer can be different from entity owner (e.g., session keypair)
+  // Signer can be different from entity owner (e.g., session keypair); the system then
+  // checks the session token the owner issued to it for update_stats
+  const extraAccounts = signer.equals(entityOwner)
+    ? []
+    : [{ pubkey: findSessionTokenPda(UPDATE_STATS_SYSTEM_ID, signer, entityOwner), isSigner: false, isWritable: false }];
+
   const result = await ApplySystem({
     authority: signer,
     systemId: UPDATE_STATS_SYSTEM_ID,
     world: worldPda,
+    entities: [
+      {
+        entity: sessionEntity,
+        components: [{ componentId: GAME_SESSION_COMPONENT_ID }, { componentId: WAVE_HISTORY_COMPONENT_ID }],
+      },
+    ],
+    extraAccounts,
+    args: previous ? packUpdateStatsDelta(stats, previous) : packUpdateStatsArgs(stats),
+  });
+
+  return result.transaction;
+}
+
+// Session token PDA: ["session_token", target_program, session_signer, authority]
+function findSessionTokenPda(targetProgram: PublicKey, sessionSigner: PublicKey, authority: PublicKey): PublicKey {
+  return PublicKey.findProgramAddressSync(
+    [Buffer.from("session_token"), targetProgram.toBuffer(), sessionSigner.toBuffer(), authority.toBuffer()],
+    SESSION_KEYS_PROGRAM_ID
+  )[0];
+}
+
+// UpdateStatsArgs v5: version(1) + hp(2) + xp(4) + gold(4) + time(4) + wave(1) + kills(4)
+// + level(1) + is_dead(1) + combo(4) + tick(8) + damage dealt(4) + taken(4) + healed(4),
+// little-endian
+const UPDATE_STATS_VERSION = 5;
+
+export type UpdateStats = {
+  hp: number;
+  xp: number;
+  goldEarned: number;
+  // Seconds since the run started, on-chain paused time included (the chain takes it off)
+  timeSurvived: number;
+  wave: number;
+  kills: number;
+  level: number;
+  isDead: boolean;
+  combo?: number;
+  // Session's last accepted tick + 1; the system rejects anything else
+  tick: number;
+  // Run totals; the chain never lowers them
+  damageDealt?: number;
+  damageTaken?: number;
+  damageHealed?: number;
+};
+
+function packUpdateStatsArgs(stats: UpdateStats): Buffer {
+  const buf = Buffer.alloc(46);
+  buf.writeUInt8(UPDATE_STATS_VERSION, 0);
+  buf.writeUInt16LE(stats.hp, 1);
+  buf.writeUInt32LE(stats.xp, 3);
+  buf.writeUInt32LE(stats.goldEarned, 7);
+  buf.writeUInt32LE(stats.timeSurvived, 11);
+  buf.writeUInt8(stats.wave, 15);
+  buf.writeUInt32LE(stats.kills, 16);
+  buf.writeUInt8(stats.level, 20);
+  buf.writeUInt8(stats.isDead ? 1 : 0, 21);
+  buf.writeUInt32LE(stats.combo ?? 0, 22);
+  buf.writeBigUInt64LE(BigInt(stats.tick), 26);
+  buf.writeUInt32LE(stats.damageDealt ?? 0, 34);
+  buf.writeUInt32LE(stats.damageTaken ?? 0, 38);
+  buf.writeUInt32LE(stats.damageHealed ?? 0, 42);
+  return buf;
+}
+
+// UpdateStatsArgs delta (v3): version(1) + mask(2) + tick(8) + only the fields whose bit is set,
+// in the v5 order; bit i is the i-th field (hp = 0 ... combo = 8 ... damage healed = 11). Combo is
+// sent whenever non-zero
+const UPDATE_STATS_DELTA_VERSION = 3;
+
+function packUpdateStatsDelta(stats: UpdateStats, previous: UpdateStats): Buffer {
+  const fields: [boolean, number, number][] = [
+    [stats.hp !== previous.hp, 2, stats.hp],
+    [stats.xp !== previous.xp, 4, stats.xp],
+    [stats.goldEarned !== previous.goldEarned, 4, stats.goldEarned],
+    [stats.timeSurvived !== previous.timeSurvived, 4, stats.timeSurvived],
+    [stats.wave !== previous.wave, 1, stats.wave],
+    [stats.kills !== previous.kills, 4, stats.kills],
+    [stats.level !== previous.level, 1, stats.level],
+    [stats.isDead !== previous.isDead, 1, stats.isDead ? 1 : 0],
+    [(stats.combo ?? 0) !== 0, 4, stats.combo ?? 0],
+    [(stats.damageDealt ?? 0) !== (previous.damageDealt ?? 0), 4, stats.damageDealt ?? 0],
+    [(stats.damageTaken ?? 0) !== (previous.damageTaken ?? 0), 4, stats.damageTaken ?? 0],
+    [(stats.damageHealed ?? 0) !== (previous.damageHealed ?? 0), 4, stats.damageHealed ?? 0],
+  ];
+
+  let mask = 0;
+  let len = 11;
+  fields.forEach(([changed, size], i) => {
+    if (changed) {
+      mask |= 1 << i;
+      len += size;
+    }
+  });
+
+  const buf = Buffer.alloc(len);
+  buf.writeUInt8(UPDATE_STATS_DELTA_VERSION, 0);
+  buf.writeUInt16LE(mask, 1);
+  buf.writeBigUInt64LE(BigInt(stats.tick), 3);
+  let offset = 11;
+  for (const [changed, size, value] of fields) {
+    if (!changed) continue;
+    buf.writeUIntLE(value, offset, size);
+    offset += size;
+  }
+  return buf;
+}
+
+// Collect gold (gasless on ER), for runs started with the gold pickups feature flag, where
+// update_stats ignores reported gold. amount: gold picked up since the last call, from `pickups`
+// pickups (each worth at least one gold; converted to bonus gold at the config rate at run end)
+export async function buildCollectGoldTx(
+  worldPda: PublicKey,
+  worldId: BN,
+  entityOwner: PublicKey,
+  signer: PublicKey,
+  amount: number,
+  connection: Connection,
+  slot = 0,
+  pickups = 1
+): Promise<Transaction> {
+  const sessionEntity = FindEntityPda({ worldId, seed: getEntitySeed(entityOwner, sessionSeedSuffix(slot)) });
+
+  // Setup Anchor provider for BOLT SDK
+  setupAnchorProvider(connection);
+
+  const extraAccounts = signer.equals(entityOwner)
+    ? []
+    : [{ pubkey: findSessionTokenPda(COLLECT_GOLD_SYSTEM_ID, signer, entityOwner), isSigner: false, isWritable: false }];
+
+  const args = Buffer.alloc(6);
+  args.writeUInt32LE(amount, 0);
+  args.writeUInt16LE(Math.min(pickups, amount), 4);
+
+  const result = await ApplySystem({
+    authority: signer,
+    systemId: COLLECT_GOLD_SYSTEM_ID,
+    world: worldPda,
     entities: [
       {
         entity: sessionEntity,
         components: [{ componentId: GAME_SESSION_COMPONENT_ID }],
       },
     ],
-    args: {
-      hp: stats.hp,
-      xp: stats.xp,
-      gold_earned: stats.goldEarned,
-      time_survived: stats.timeSurvived,
-      wave: stats.wave,
-      kills: stats.kills,
-      level: stats.level,
-      is_dead: stats.isDead,
-    },
+    extraAccounts,
+    args,
   });
 
   return result.transaction;
@@ -274,12 +519,13 @@ export async function buildUseReviveTx(
   worldPda: PublicKey,
   worldId: BN,
   authority: PublicKey,
-  connection: Connection
+  connection: Connection,
+  slot = 0
 ): Promise<Transaction> {
   const playerSeed = getEntitySeed(authority, "player");
   const playerEntity = FindEntityPda({ worldId, seed: playerSeed });
 
-  const sessionSeed = getEntitySeed(authority, "session");
+  const sessionSeed = getEntitySeed(authority, sessionSeedSuffix(slot));
   const sessionEntity = FindEntityPda({ worldId, seed: sessionSeed });
 
   // Setup Anchor provider for BOLT SDK
@@ -309,9 +555,10 @@ export async function buildEndGameTx(
   worldPda: PublicKey,
   worldId: BN,
   authority: PublicKey,
-  connection: Connection
+  connection: Connection,
+  slot = 0
 ): Promise<Transaction> {
-  const sessionSeed = getEntitySeed(authority, "session");
+  const sessionSeed = getEntitySeed(authority, sessionSeedSuffix(slot));
   const sessionEntity = FindEntityPda({ worldId, seed: sessionSeed });
 
   const playerSeed = getEntitySeed(authority, "player");
@@ -320,6 +567,9 @@ export async function buildEndGameTx(
   // Setup Anchor provider for BOLT SDK
   setupAnchorProvider(connection);
 
+  const tx = new Transaction();
+  tx.add(...(await initPlayerExtrasIfMissing(authority, playerEntity, connection)));
+
   const result = await ApplySystem({
     authority,
     systemId: END_GAME_SYSTEM_ID,
@@ -331,36 +581,38 @@ export async function buildEndGameTx(
       },
       {
         entity: playerEntity,
-        components: [{ componentId: PLAYER_COMPONENT_ID }],
+        components: [{ componentId: PLAYER_COMPONENT_ID }, { componentId: PLAYER_EXTRAS_COMPONENT_ID }],
       },
     ],
+    // The system checks PlayerExtras sits on the same entity as Player
+    extraAccounts: [{ pubkey: playerEntity, isSigner: false, isWritable: false }],
   });
+  tx.add(result.instruction);
 
-  return result.transaction;
+  return tx;
 }
 
-// Delegate GameSession to Ephemeral Rollup
+// Delegate GameSession (and its WaveHistory, which update_stats writes) to Ephemeral Rollup
 export async function buildDelegateSessionTx(
   worldPda: PublicKey,
   worldId: BN,
   authority: PublicKey,
-  connection: Connection
+  connection: Connection,
+  slot = 0
 ): Promise<Transaction> {
-  const sessionSeed = getEntitySeed(authority, "session");
+  const sessionSeed = getEntitySeed(authority, sessionSeedSuffix(slot));
   const sessionEntity = FindEntityPda({ worldId, seed: sessionSeed });
-  const sessionComponent = FindComponentPda({
-    componentId: GAME_SESSION_COMPONENT_ID,
-    entity: sessionEntity,
-  });
-
-  const delegateIx = createDelegateInstruction({
-    entity: sessionEntity,
-    account: sessionComponent,
-    ownerProgram: GAME_SESSION_COMPONENT_ID,
-    payer: authority,
-  });
 
-  const tx = new Transaction().add(delegateIx);
+  const tx = new Transaction();
+  for (const componentId of [GAME_SESSION_COMPONENT_ID, WAVE_HISTORY_COMPONENT_ID]) {
+    const delegateIx = createDelegateInstruction({
+      entity: sessionEntity,
+      account: FindComponentPda({ componentId, entity: sessionEntity }),
+      ownerProgram: componentId,
+      payer: authority,
+    });
+    tx.add(delegateIx);
+  }
   return tx;
 }
 
@@ -439,8 +691,10 @@ export async function buildSubmitScoreTx(
     }
   }
 
-  // Build args: pubkey(32) + best_time(4) + best_wave(1) + total_gold(8) + games_played(4) = 49 bytes
-  const args = new Uint8Array(49);
+  // Build args: pubkey(32) + best_time(4) + best_wave(1) + total_gold(8) + games_played(4)
+  // + environment_id(1) = 50 bytes; the chain takes the stats and character from the Player
+  // component, so the stats here only need to keep the layout
+  const args = new Uint8Array(50);
   const view = new DataView(args.buffer);
   let off = 0;
 
@@ -461,7 +715,14 @@ export async function buildSubmitScoreTx(
   off += 8;
 
   // games_played u32
-  view.setUint32(off, data.gamesPlayed, true);
+  view.setUint32(off, data.gamesPlayed, true); off += 4;
+
+  // environment_id u8 (default map)
+  args[off] = 0;
+
+  // World-wide config entity (submit interval)
+  const configEntity = FindEntityPda({ worldId, seed: new TextEncoder().encode("config") });
+  const playerEntity = FindEntityPda({ worldId, seed: getEntitySeed(authority, "player") });
 
   const result = await ApplySystem({
     authority,
@@ -472,6 +733,14 @@ export async function buildSubmitScoreTx(
         entity: lbEntity,
         components: [{ componentId: LEADERBOARD_COMPONENT_ID }],
       },
+      {
+        entity: configEntity,
+        components: [{ componentId: CONFIG_COMPONENT_ID }],
+      },
+      {
+        entity: playerEntity,
+        components: [{ componentId: PLAYER_COMPONENT_ID }],
+      },
     ],
     args: Buffer.from(args),
   });
@@ -479,25 +748,24 @@ export async function buildSubmitScoreTx(
   return result.transaction;
 }
 
-// Undelegate GameSession back to L1
+// Undelegate GameSession and its WaveHistory back to L1
 export async function buildUndelegateSessionTx(
   worldPda: PublicKey,
   worldId: BN,
-  authority: PublicKey
+  authority: PublicKey,
+  slot = 0
 ): Promise<Transaction> {
-  const sessionSeed = getEntitySeed(authority, "session");
+  const sessionSeed = getEntitySeed(authority, sessionSeedSuffix(slot));
   const sessionEntity = FindEntityPda({ worldId, seed: sessionSeed });
-  const sessionComponent = FindComponentPda({
-    componentId: GAME_SESSION_COMPONENT_ID,
-    entity: sessionEntity,
-  });
 
-  const instruction = createUndelegateInstruction({
-    payer: authority,
-    delegatedAccount: sessionComponent,
-    componentPda: GAME_SESSION_COMPONENT_ID,  // Note: confusingly named, this is the Program ID
-  });
-
-  const tx = new Transaction().add(instruction);
+  const tx = new Transaction();
+  for (const componentId of [GAME_SESSION_COMPONENT_ID, WAVE_HISTORY_COMPONENT_ID]) {
+    const instruction = createUndelegateInstruction({
+      payer: authority,
+      delegatedAccount: FindComponentPda({ componentId, entity: sessionEntity }),
+      componentPda: componentId,  // Note: confusingly named, this is the Program ID
+    });
+    tx.add(instruction);
+  }
   return tx;
 }
@@ -0,0 +1,181 @@
+use crate::characters::hp_regen_per_sec;
+use crate::progression::{kill_xp, level_min_xp, XP_TOLERANCE};
+use crate::waves::{enemies_for_wave, WAVE_DURATION_MS};
+
+/// Largest HP increase a stats update may report after `elapsed` seconds
+/// One extra second of allowance covers ticks that land inside the same second
+pub fn max_hp_gain(character_id: &str, elapsed: i64) -> u16 {
+    let seconds = elapsed.clamp(0, u16::MAX as i64) as u16;
+    hp_regen_per_sec(character_id).saturating_mul(seconds.saturating_add(1))
+}
+
+/// Most kills one second of play can plausibly yield (area weapons clearing a dense wave)
+pub const MAX_KILLS_PER_SEC: u32 = 10;
+
+/// Gold per second a run can earn at wave 0, before the per-wave increase
+pub const MAX_GOLD_PER_SEC: u32 = 20;
+
+/// Extra gold per second allowed for each wave reached
+pub const MAX_GOLD_PER_SEC_PER_WAVE: u32 = 5;
+
+/// Most boss damage one second of play can plausibly deal (a full loadout of maxed weapons)
+pub const MAX_BOSS_DAMAGE_PER_SEC: u32 = 5_000;
+
+/// Seconds of allowance for an update `elapsed` after the previous one, plus one for ticks
+/// landing inside the same second
+fn allowance_secs(elapsed: i64) -> u32 {
+    elapsed.clamp(0, u32::MAX as i64 - 1) as u32 + 1
+}
+
+/// Largest kill increase a stats update may report after `elapsed` seconds
+pub fn max_kills_gain(elapsed: i64) -> u32 {
+    MAX_KILLS_PER_SEC.saturating_mul(allowance_secs(elapsed))
+}
+
+/// Most kills a run can have by the end of `wave`: every enemy waves `1..=wave` spawn for the
+/// run's wave `seed`. Leftovers carry into the next wave, so only the run total is bounded
+pub fn max_kills_through_wave(wave: u8, seed: u64) -> u32 {
+    (1..=wave)
+        .map(|wave| enemies_for_wave(wave, seed))
+        .fold(0, u32::saturating_add)
+}
+
+/// Highest wave a run can have reached after `played_secs` of play: waves start at 1 and
+/// advance on the client's `WAVE_DURATION_MS` timer, plus one second for ticks landing early
+pub fn max_wave_at(played_secs: u32) -> u8 {
+    let played_ms = (played_secs as u64 + 1) * 1_000;
+    (1 + played_ms / WAVE_DURATION_MS as u64).min(u8::MAX as u64) as u8
+}
+
+/// Largest gold increase a stats update may report after `elapsed` seconds at `wave`
+pub fn max_gold_gain(wave: u8, elapsed: i64) -> u32 {
+    MAX_GOLD_PER_SEC
+        .saturating_add(MAX_GOLD_PER_SEC_PER_WAVE * wave as u32)
+        .saturating_mul(allowance_secs(elapsed))
+}
+
+/// Largest boss damage one `damage_boss` call may report `elapsed` seconds after the last one
+pub fn max_boss_damage(elapsed: i64) -> u32 {
+    MAX_BOSS_DAMAGE_PER_SEC.saturating_mul(allowance_secs(elapsed))
+}
+
+/// Largest XP increase a stats update may report after `elapsed` seconds: the most kills that
+/// time allows at the reported combo and wave, plus `XP_TOLERANCE`
+pub fn max_xp_gain(elapsed: i64, combo: u32, wave: u8) -> u32 {
+    kill_xp(max_kills_gain(elapsed), combo, wave).saturating_add(XP_TOLERANCE)
+}
+
+/// Level never goes down, so reported XP may not fall under the current level's threshold
+pub fn xp_within_level(xp: u32, level: u8) -> bool {
+    xp >= level_min_xp(level)
+}
+
+/// Death state after a stats update
+/// HP at or below `death_hp_threshold` always means dead whatever the client says, and once
+/// dead only a revive clears it. A threshold of 0 is the classic "dead at zero HP"
+pub fn resolve_is_dead(
+    was_dead: bool,
+    reported_dead: bool,
+    hp: u16,
+    death_hp_threshold: u16,
+) -> bool {
+    was_dead || reported_dead || hp <= death_hp_threshold
+}
+
+/// HP a revive restores: half of max, but always above the death threshold
+pub fn revive_hp(max_hp: u16, death_hp_threshold: u16) -> u16 {
+    (max_hp / 2).max(death_hp_threshold.saturating_add(1))
+}
+
+/// A start timestamp ahead of `now` can only come from tampered or restored state
+pub fn started_in_future(started_at: i64, now: i64) -> bool {
+    started_at > now
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hp_gain_scales_with_regen_and_elapsed() {
+        assert_eq!(max_hp_gain("imelda", 0), 3);
+        assert_eq!(max_hp_gain("imelda", 4), 15);
+        assert_eq!(max_hp_gain("vitalis", 4), 30);
+        assert_eq!(max_hp_gain("imelda", -10), 3);
+        assert_eq!(max_hp_gain("vitalis", i64::MAX), u16::MAX);
+    }
+
+    #[test]
+    fn rate_caps_allow_one_extra_second() {
+        assert_eq!(max_kills_gain(0), MAX_KILLS_PER_SEC);
+        assert_eq!(max_kills_gain(-5), MAX_KILLS_PER_SEC);
+        assert_eq!(max_kills_gain(9), 10 * MAX_KILLS_PER_SEC);
+        assert_eq!(max_gold_gain(0, 0), MAX_GOLD_PER_SEC);
+        assert_eq!(
+            max_gold_gain(2, 1),
+            2 * (MAX_GOLD_PER_SEC + 2 * MAX_GOLD_PER_SEC_PER_WAVE)
+        );
+        assert_eq!(max_boss_damage(1), 2 * MAX_BOSS_DAMAGE_PER_SEC);
+        assert_eq!(max_kills_gain(i64::MAX), u32::MAX);
+    }
+
+    #[test]
+    fn kills_bounded_by_spawned_enemies() {
+        assert_eq!(max_kills_through_wave(0, 9), 0);
+        assert_eq!(max_kills_through_wave(1, 9), enemies_for_wave(1, 9));
+        assert_eq!(
+            max_kills_through_wave(3, 9),
+            enemies_for_wave(1, 9) + enemies_for_wave(2, 9) + enemies_for_wave(3, 9)
+        );
+        assert!(max_kills_through_wave(u8::MAX, u64::MAX) > max_kills_through_wave(10, u64::MAX));
+    }
+
+    #[test]
+    fn waves_advance_with_played_time() {
+        assert_eq!(max_wave_at(0), 1);
+        assert_eq!(max_wave_at(28), 1);
+        assert_eq!(max_wave_at(29), 2);
+        assert_eq!(max_wave_at(10 * 30), 11);
+        assert_eq!(max_wave_at(u32::MAX), u8::MAX);
+    }
+
+    #[test]
+    fn xp_gain_covers_max_kills_plus_tolerance() {
+        assert_eq!(
+            max_xp_gain(0, 0, 0),
+            kill_xp(MAX_KILLS_PER_SEC, 0, 0) + XP_TOLERANCE
+        );
+        assert!(max_xp_gain(5, 50, 3) > max_xp_gain(5, 0, 3));
+    }
+
+    #[test]
+    fn xp_below_level_threshold_is_rejected() {
+        assert!(xp_within_level(0, 1));
+        assert!(xp_within_level(100, 2));
+        assert!(!xp_within_level(99, 2));
+        assert!(!xp_within_level(31_999, 10));
+    }
+
+    #[test]
+    fn death_sticks_and_threshold_overrides_client() {
+        assert!(!resolve_is_dead(false, false, 1, 0));
+        assert!(resolve_is_dead(false, false, 0, 0));
+        assert!(resolve_is_dead(false, false, 5, 5));
+        assert!(resolve_is_dead(false, true, 100, 0));
+        assert!(resolve_is_dead(true, false, 100, 0));
+    }
+
+    #[test]
+    fn revive_hp_stays_above_threshold() {
+        assert_eq!(revive_hp(100, 0), 50);
+        assert_eq!(revive_hp(100, 60), 61);
+        assert_eq!(revive_hp(0, u16::MAX), u16::MAX);
+    }
+
+    #[test]
+    fn future_start_is_detected() {
+        assert!(started_in_future(11, 10));
+        assert!(!started_in_future(10, 10));
+        assert!(!started_in_future(9, 10));
+    }
+}
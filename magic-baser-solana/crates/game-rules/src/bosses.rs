@@ -0,0 +1,83 @@
+use crate::waves::FIRST_BOSS_WAVE;
+
+/// Characters unlocked by defeating each boss for the first time, indexed by `boss_id - 1`
+pub const BOSS_REWARD_CHARACTERS: [&str; 2] = ["mortaccio", "vitalis"];
+
+/// Boss HP on its first wave, before the per-wave increase (client `Demon Lord` `baseHp`)
+pub const BOSS_BASE_HP: u32 = 500;
+
+/// Wave a boss appears on: boss 1 on the first boss wave, then every `FIRST_BOSS_WAVE` waves
+pub fn boss_wave(boss_id: u8) -> u8 {
+    boss_id.saturating_mul(FIRST_BOSS_WAVE)
+}
+
+/// Boss that spawns on `wave`, if it is a boss wave
+pub fn boss_for_wave(wave: u8) -> Option<u8> {
+    let boss_id = wave / FIRST_BOSS_WAVE;
+    (boss_id > 0 && boss_wave(boss_id) == wave).then_some(boss_id)
+}
+
+/// Boss a run that has defeated `bosses_defeated` bosses fights once it reaches `wave`
+/// Bosses come strictly in order: a run that jumps past several boss waves still meets the
+/// next undefeated boss first
+pub fn next_boss(bosses_defeated: u8, wave: u8) -> Option<u8> {
+    let boss_id = bosses_defeated.checked_add(1)?;
+    let due = boss_id.checked_mul(FIRST_BOSS_WAVE)?;
+    (wave >= due).then_some(boss_id)
+}
+
+/// Full HP of a boss, scaled by its wave like the client's `getEnemyStats` (+15% per wave)
+pub fn boss_max_hp(boss_id: u8) -> u32 {
+    let wave = boss_wave(boss_id).max(1) as u32;
+    BOSS_BASE_HP.saturating_mul(100 + (wave - 1) * 15) / 100
+}
+
+/// Character a boss unlocks, if it has a reward
+pub fn boss_reward_character(boss_id: u8) -> Option<&'static str> {
+    BOSS_REWARD_CHARACTERS
+        .get((boss_id as usize).checked_sub(1)?)
+        .copied()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bosses_spawn_on_their_waves() {
+        assert_eq!(boss_for_wave(FIRST_BOSS_WAVE), Some(1));
+        assert_eq!(boss_for_wave(FIRST_BOSS_WAVE * 2), Some(2));
+        assert_eq!(boss_for_wave(FIRST_BOSS_WAVE + 1), None);
+        assert_eq!(boss_for_wave(0), None);
+    }
+
+    #[test]
+    fn next_boss_waits_for_its_wave() {
+        assert_eq!(next_boss(0, FIRST_BOSS_WAVE - 1), None);
+        assert_eq!(next_boss(0, FIRST_BOSS_WAVE), Some(1));
+        assert_eq!(next_boss(1, FIRST_BOSS_WAVE + 1), None);
+        assert_eq!(next_boss(1, FIRST_BOSS_WAVE * 2), Some(2));
+    }
+
+    #[test]
+    fn wave_jump_past_boss_two_meets_boss_one_first() {
+        let wave = FIRST_BOSS_WAVE * 2 + 3;
+        assert_eq!(next_boss(0, wave), Some(1));
+        assert_eq!(next_boss(1, wave), Some(2));
+        assert_eq!(next_boss(2, wave), None);
+    }
+
+    #[test]
+    fn next_boss_never_overflows() {
+        assert_eq!(next_boss(u8::MAX, u8::MAX), None);
+        assert_eq!(next_boss(u8::MAX / FIRST_BOSS_WAVE, u8::MAX), None);
+    }
+
+    #[test]
+    fn only_the_first_bosses_have_rewards() {
+        assert_eq!(boss_reward_character(0), None);
+        assert_eq!(boss_reward_character(1), Some(BOSS_REWARD_CHARACTERS[0]));
+        assert_eq!(boss_reward_character(2), Some(BOSS_REWARD_CHARACTERS[1]));
+        assert_eq!(boss_reward_character(3), None);
+    }
+}
@@ -0,0 +1,30 @@
+/// Timed buff/debuff slots on a GameSession
+pub const BUFF_SLOTS: usize = 8;
+
+/// Longest a single buff application may last
+pub const MAX_BUFF_DURATION_SECS: u32 = 300;
+
+/// Largest effect strength either way; the client interprets it per buff id and negative
+/// values are debuffs
+pub const MAX_BUFF_MAGNITUDE: i16 = 1_000;
+
+/// True if a buff application is within bounds (id 0 marks an empty slot)
+pub fn valid_buff(id: u8, magnitude: i16, duration_secs: u32) -> bool {
+    id != 0
+        && magnitude.unsigned_abs() <= MAX_BUFF_MAGNITUDE as u16
+        && (1..=MAX_BUFF_DURATION_SECS).contains(&duration_secs)
+}
+
+/// True if a slot holds a buff still running at `now`
+pub fn buff_live(id: u8, expires_at: i64, now: i64) -> bool {
+    id != 0 && now < expires_at
+}
+
+/// Slot for buff `id` at `now`: the slot it already runs in (re-applying refreshes it), else the
+/// first empty or expired slot; None if all slots hold other live buffs
+pub fn buff_slot(ids: &[u8], expires_at: &[i64], id: u8, now: i64) -> Option<usize> {
+    let live = |slot: usize| buff_live(ids[slot], expires_at[slot], now);
+    (0..ids.len())
+        .find(|&slot| ids[slot] == id && live(slot))
+        .or_else(|| (0..ids.len()).find(|&slot| !live(slot)))
+}
@@ -0,0 +1,140 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+
+/// Character ids recognised on-chain, in canonical (catalog) order
+pub const KNOWN_CHARACTERS: [&str; 6] = [
+    "imelda",
+    "antonio",
+    "pasqualina",
+    "gennaro",
+    "mortaccio",
+    "vitalis",
+];
+
+/// Starter character every player owns
+pub const STARTER_CHARACTER: &str = "imelda";
+
+/// Check whether a character id is part of the on-chain catalog
+pub fn is_known_character(id: &str) -> bool {
+    KNOWN_CHARACTERS.contains(&id)
+}
+
+/// Position of a character in `KNOWN_CHARACTERS`; `u8::MAX` for unknown ids
+pub fn character_index(id: &str) -> u8 {
+    KNOWN_CHARACTERS
+        .iter()
+        .position(|known| *known == id)
+        .map_or(u8::MAX, |index| index as u8)
+}
+
+/// Seconds per featured-character day (UTC days)
+pub const SECONDS_PER_DAY: i64 = 86_400;
+
+/// Day number for a unix timestamp, as fed to `featured_character`
+pub fn day_of(timestamp: i64) -> i64 {
+    timestamp.div_euclid(SECONDS_PER_DAY)
+}
+
+/// Index into `KNOWN_CHARACTERS` of the day's featured character
+/// A pure hash of the day number, so clients can show the pick without reading chain state
+pub fn featured_character(day: i64) -> u8 {
+    (crate::waves::mix(day as u64) % KNOWN_CHARACTERS.len() as u64) as u8
+}
+
+/// Base max HP for a character; unknown ids get the imelda default
+pub fn character_base_hp(character_id: &str) -> u16 {
+    match character_id {
+        "antonio" => 120,
+        "pasqualina" => 80,
+        "gennaro" => 110,
+        "mortaccio" => 90,
+        "vitalis" => 95,
+        _ => 100, // imelda default
+    }
+}
+
+/// Passive HP regeneration per second allowed for a character (pickups included)
+pub fn hp_regen_per_sec(character_id: &str) -> u16 {
+    match character_id {
+        "vitalis" => 6,
+        "gennaro" => 4,
+        _ => 3,
+    }
+}
+
+/// Parse a JSON array of strings (`["imelda","antonio"]`) into its entries
+/// Lenient on whitespace; entries that aren't quoted strings are skipped
+pub fn parse_character_list(raw: &str) -> Vec<&str> {
+    let inner = raw.trim().trim_start_matches('[').trim_end_matches(']');
+    inner
+        .split(',')
+        .filter_map(|entry| entry.trim().strip_prefix('"')?.strip_suffix('"'))
+        .filter(|id| !id.is_empty())
+        .collect()
+}
+
+/// Serialize character ids back into the compact JSON array format
+pub fn format_character_list(ids: &[&str]) -> String {
+    let mut out = String::from("[");
+    for (i, id) in ids.iter().enumerate() {
+        if i > 0 {
+            out.push(',');
+        }
+        out.push('"');
+        out.push_str(id);
+        out.push('"');
+    }
+    out.push(']');
+    out
+}
+
+/// Canonical form of an owned-characters list: known ids only, no duplicates,
+/// catalog order, starter always included
+pub fn normalize_character_list(raw: &str) -> String {
+    let owned = parse_character_list(raw);
+    let ids: Vec<&str> = KNOWN_CHARACTERS
+        .iter()
+        .copied()
+        .filter(|id| *id == STARTER_CHARACTER || owned.contains(id))
+        .collect();
+    format_character_list(&ids)
+}
+
+/// Owned-characters list with `id` added, in canonical form
+/// Normalizing keeps the string bounded by the catalog, whatever the stored list held
+pub fn add_owned_character(raw: &str, id: &str) -> String {
+    let mut owned = parse_character_list(raw);
+    owned.push(id);
+    normalize_character_list(&format_character_list(&owned))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalize_drops_duplicates_and_unknown_ids() {
+        assert_eq!(
+            normalize_character_list(r#"["vitalis","imelda","bogus","antonio","vitalis",""]"#),
+            r#"["imelda","antonio","vitalis"]"#
+        );
+    }
+
+    #[test]
+    fn normalize_always_keeps_the_starter() {
+        assert_eq!(normalize_character_list("[]"), r#"["imelda"]"#);
+        assert_eq!(normalize_character_list("not json"), r#"["imelda"]"#);
+        assert_eq!(
+            normalize_character_list(r#"[ "gennaro" ]"#),
+            r#"["imelda","gennaro"]"#
+        );
+    }
+
+    #[test]
+    fn adding_a_character_keeps_the_canonical_form() {
+        let owned = add_owned_character(r#"["imelda","imelda","mortaccio"]"#, "antonio");
+        assert_eq!(owned, r#"["imelda","antonio","mortaccio"]"#);
+        assert_eq!(add_owned_character(&owned, "antonio"), owned);
+        assert_eq!(add_owned_character(&owned, "bogus"), owned);
+    }
+}
@@ -0,0 +1,25 @@
+/// Default difficulty; leaves character stats unchanged
+pub const DIFFICULTY_NORMAL: u8 = 0;
+pub const DIFFICULTY_HARD: u8 = 1;
+pub const DIFFICULTY_NIGHTMARE: u8 = 2;
+
+/// Hardest supported difficulty; `start_game` rejects higher values
+pub const MAX_DIFFICULTY: u8 = DIFFICULTY_NIGHTMARE;
+
+/// Lowest starting max HP any difficulty can leave a character with
+pub const MIN_START_HP: u16 = 10;
+
+/// Max HP adjustment applied at game start for a difficulty
+pub fn difficulty_hp_modifier(difficulty: u8) -> i16 {
+    match difficulty.min(MAX_DIFFICULTY) {
+        DIFFICULTY_NORMAL => 0,
+        DIFFICULTY_HARD => -20,
+        _ => -40,
+    }
+}
+
+/// Starting max HP for a character's base HP at a difficulty
+pub fn starting_max_hp(base_hp: u16, difficulty: u8) -> u16 {
+    let adjusted = base_hp as i32 + difficulty_hp_modifier(difficulty) as i32;
+    adjusted.clamp(MIN_START_HP as i32, u16::MAX as i32) as u16
+}
@@ -0,0 +1,55 @@
+/// Gold cost of the first coin-funded revive in a run; doubles with each revive used
+pub const COIN_REVIVE_BASE_COST: u32 = 100;
+
+/// Gold needed for the next coin-funded revive
+pub fn coin_revive_cost(revives_used: u8) -> u32 {
+    COIN_REVIVE_BASE_COST.saturating_mul(1u32 << revives_used.min(16))
+}
+
+/// Basis points of collected pickups converted to bonus gold at run end (0 = no conversion)
+pub const PICKUP_GOLD_RATE_BPS: u16 = 0;
+
+/// Bonus gold for `pickups` collected at `rate_bps` basis points, rounded down
+pub fn pickup_bonus_gold(pickups: u32, rate_bps: u16) -> u64 {
+    (pickups as u64) * (rate_bps as u64) / 10_000
+}
+
+/// Extra gold, in percent of the run's gold, for playing the day's featured character
+pub const FEATURED_GOLD_BONUS_PCT: u64 = 25;
+
+/// Bonus gold a featured-character run earns on top of `gold_earned`
+pub fn featured_bonus_gold(gold_earned: u32) -> u64 {
+    gold_earned as u64 * FEATURED_GOLD_BONUS_PCT / 100
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn coin_revive_cost_doubles_and_saturates() {
+        assert_eq!(coin_revive_cost(0), COIN_REVIVE_BASE_COST);
+        assert_eq!(coin_revive_cost(1), 2 * COIN_REVIVE_BASE_COST);
+        assert_eq!(coin_revive_cost(3), 8 * COIN_REVIVE_BASE_COST);
+        assert_eq!(coin_revive_cost(u8::MAX), coin_revive_cost(16));
+    }
+
+    #[test]
+    fn pickup_bonus_rounds_down() {
+        assert_eq!(pickup_bonus_gold(10, PICKUP_GOLD_RATE_BPS), 0);
+        assert_eq!(pickup_bonus_gold(3, 5_000), 1);
+        assert_eq!(pickup_bonus_gold(100, 10_000), 100);
+        assert_eq!(
+            pickup_bonus_gold(u32::MAX, u16::MAX),
+            u32::MAX as u64 * 65_535 / 10_000
+        );
+    }
+
+    #[test]
+    fn featured_bonus_is_a_quarter() {
+        assert_eq!(featured_bonus_gold(0), 0);
+        assert_eq!(featured_bonus_gold(100), 25);
+        assert_eq!(featured_bonus_gold(3), 0);
+        assert_eq!(featured_bonus_gold(u32::MAX), u32::MAX as u64 / 4);
+    }
+}
@@ -0,0 +1,16 @@
+/// Enforce the stats-update bounds (HP regen, XP vs level and kills)
+pub const FEATURE_ANTI_CHEAT: u32 = 1 << 0;
+/// Take session XP from the on-chain kill allowance instead of the client
+pub const FEATURE_AUTO_XP: u32 = 1 << 1;
+/// Derive `time_survived` from the clock instead of the client
+pub const FEATURE_SERVER_TIME: u32 = 1 << 2;
+/// Credit gold only through `collect_gold`, ignoring the `gold_earned` the client reports
+pub const FEATURE_GOLD_PICKUPS: u32 = 1 << 3;
+
+/// Flags a Config gets when its admin is first claimed (matches pre-flag behavior)
+pub const DEFAULT_FEATURE_FLAGS: u32 = FEATURE_ANTI_CHEAT;
+
+/// True if every bit of `flag` is set in `flags`
+pub fn is_enabled(flags: u32, flag: u32) -> bool {
+    flags & flag == flag
+}
@@ -0,0 +1,23 @@
+//! Pure game rules shared by the Bolt systems and off-chain tooling
+//!
+//! Nothing here reads `Clock`, touches accounts or links the Solana runtime: callers pass
+//! plain values (timestamps, stats, ids), so the same code validates runs on-chain and in a
+//! server. On-chain crates use it as-is; off-chain users enable the `sdk` feature.
+#![cfg_attr(not(feature = "sdk"), no_std)]
+
+extern crate alloc;
+
+pub mod anticheat;
+pub mod bosses;
+pub mod buffs;
+pub mod characters;
+pub mod difficulty;
+pub mod economy;
+pub mod features;
+pub mod loadout;
+pub mod progression;
+pub mod randomness;
+pub mod scoring;
+pub mod seeds;
+pub mod time;
+pub mod waves;
@@ -0,0 +1,57 @@
+/// Weapon ids recognised on-chain: weapon id `n` is `KNOWN_WEAPONS[n - 1]` (client `WeaponType`)
+pub const KNOWN_WEAPONS: [&str; 6] = [
+    "magic_wand",
+    "fireball",
+    "lightning",
+    "garlic",
+    "knife",
+    "bible",
+];
+
+/// Highest passive id; passive ids run `1..=MAX_PASSIVE_ID` (0 marks an empty slot)
+pub const MAX_PASSIVE_ID: u8 = 16;
+
+/// Highest level a weapon reaches (client `maxLevel`)
+pub const WEAPON_MAX_LEVEL: u8 = 8;
+
+/// Highest level a passive reaches
+pub const PASSIVE_MAX_LEVEL: u8 = 5;
+
+/// Weapon slots and passive slots a run can fill
+pub const LOADOUT_SLOTS: usize = 6;
+
+/// Upgrade kind: a weapon
+pub const ITEM_WEAPON: u8 = 0;
+/// Upgrade kind: a passive
+pub const ITEM_PASSIVE: u8 = 1;
+
+/// True if `id` names an item of `kind` (`ITEM_WEAPON` or `ITEM_PASSIVE`)
+pub fn is_known_item(kind: u8, id: u8) -> bool {
+    match kind {
+        ITEM_WEAPON => (1..=KNOWN_WEAPONS.len()).contains(&(id as usize)),
+        ITEM_PASSIVE => (1..=MAX_PASSIVE_ID).contains(&id),
+        _ => false,
+    }
+}
+
+/// Highest level an item of `kind` reaches
+pub fn max_item_level(kind: u8) -> u8 {
+    if kind == ITEM_PASSIVE {
+        PASSIVE_MAX_LEVEL
+    } else {
+        WEAPON_MAX_LEVEL
+    }
+}
+
+/// Slot `id` already occupies in `ids`, else the first empty one; None if the loadout is full
+pub fn loadout_slot(ids: &[u8], id: u8) -> Option<usize> {
+    ids.iter()
+        .position(|&slot| slot == id)
+        .or_else(|| ids.iter().position(|&slot| slot == 0))
+}
+
+/// True if a run at `level` may take one more upgrade with `item_levels` already picked
+/// The starting weapon takes the first pick and every level-up grants one more
+pub fn upgrade_allowed(level: u8, item_levels: u32) -> bool {
+    item_levels < level as u32
+}
@@ -0,0 +1,139 @@
+/// XP needed to reach each level, indexed by `level - 1`
+pub const LEVEL_XP_THRESHOLDS: [u32; 10] = [0, 100, 250, 500, 1000, 2000, 4000, 8000, 16000, 32000];
+
+/// Highest reachable level
+pub const MAX_LEVEL: u8 = LEVEL_XP_THRESHOLDS.len() as u8;
+
+/// Max HP gained on each level up
+pub const LEVEL_UP_MAX_HP_BONUS: u16 = 10;
+
+/// Calculate level from XP using exponential curve
+pub fn calculate_level(xp: u32) -> u8 {
+    LEVEL_XP_THRESHOLDS
+        .iter()
+        .take_while(|&&threshold| xp >= threshold)
+        .count() as u8
+}
+
+/// Minimum XP for a level (inverse of `calculate_level`)
+pub fn level_min_xp(level: u8) -> u32 {
+    let index = level.clamp(1, MAX_LEVEL) as usize - 1;
+    LEVEL_XP_THRESHOLDS[index]
+}
+
+/// XP credited per kill before combo and wave scaling
+pub const BASE_XP_PER_KILL: u32 = 30;
+
+/// Combo count that adds another +100% XP per kill
+pub const COMBO_DIVISOR: u32 = 50;
+
+/// Seconds a combo stays alive after its last kill
+pub const COMBO_WINDOW_SECS: i64 = 3;
+
+/// Longest combo tracked on-chain (a 5x multiplier at `COMBO_DIVISOR` 50)
+pub const MAX_COMBO: u32 = 200;
+
+/// Slack on reported XP above the kill-based allowance (boss orbs, rounding)
+pub const XP_TOLERANCE: u32 = 500;
+
+/// XP earned by `kills` new kills: `base * (1 + combo / COMBO_DIVISOR) * (1 + wave / 10)`
+/// Integer-only so the chain and clients agree to the unit
+pub fn kill_xp(kills: u32, combo: u32, wave: u8) -> u32 {
+    let per_kill =
+        BASE_XP_PER_KILL as u64 * (COMBO_DIVISOR as u64 + combo as u64) * (10 + wave as u64)
+            / (COMBO_DIVISOR as u64 * 10);
+    per_kill.saturating_mul(kills as u64).min(u32::MAX as u64) as u32
+}
+
+/// Combo still running at `now`, 0 once `combo_expires_at` has passed
+pub fn active_combo(combo_count: u32, combo_expires_at: i64, now: i64) -> u32 {
+    if now < combo_expires_at {
+        combo_count
+    } else {
+        0
+    }
+}
+
+/// Combo count and expiry after `new_kills` at `now`: kills extend a live combo (or start a
+/// new one) and push its expiry `COMBO_WINDOW_SECS` out; without kills it just runs out
+pub fn advance_combo(
+    combo_count: u32,
+    combo_expires_at: i64,
+    new_kills: u32,
+    now: i64,
+) -> (u32, i64) {
+    let active = active_combo(combo_count, combo_expires_at, now);
+    if new_kills == 0 {
+        return if active == 0 {
+            (0, 0)
+        } else {
+            (combo_count, combo_expires_at)
+        };
+    }
+    (
+        active.saturating_add(new_kills).min(MAX_COMBO),
+        now.saturating_add(COMBO_WINDOW_SECS),
+    )
+}
+
+/// `value` scaled by the combo multiplier `1 + combo / COMBO_DIVISOR`, as in `kill_xp`
+pub fn combo_scaled(value: u32, combo: u32) -> u32 {
+    let scaled = value as u64 * (COMBO_DIVISOR as u64 + combo as u64) / COMBO_DIVISOR as u64;
+    scaled.min(u32::MAX as u64) as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn level_follows_thresholds() {
+        assert_eq!(calculate_level(0), 1);
+        assert_eq!(calculate_level(99), 1);
+        assert_eq!(calculate_level(100), 2);
+        assert_eq!(calculate_level(31_999), 9);
+        assert_eq!(calculate_level(u32::MAX), MAX_LEVEL);
+    }
+
+    #[test]
+    fn level_min_xp_inverts_calculate_level() {
+        for level in 1..=MAX_LEVEL {
+            assert_eq!(calculate_level(level_min_xp(level)), level);
+        }
+        assert_eq!(level_min_xp(0), 0);
+        assert_eq!(level_min_xp(u8::MAX), level_min_xp(MAX_LEVEL));
+    }
+
+    #[test]
+    fn kill_xp_scales_with_combo_and_wave() {
+        assert_eq!(kill_xp(1, 0, 0), BASE_XP_PER_KILL);
+        assert_eq!(kill_xp(1, COMBO_DIVISOR, 0), 2 * BASE_XP_PER_KILL);
+        assert_eq!(kill_xp(1, 0, 10), 2 * BASE_XP_PER_KILL);
+        assert_eq!(kill_xp(3, 0, 0), 3 * BASE_XP_PER_KILL);
+        assert_eq!(kill_xp(u32::MAX, MAX_COMBO, u8::MAX), u32::MAX);
+    }
+
+    #[test]
+    fn combo_runs_out_after_window() {
+        assert_eq!(active_combo(5, 10, 9), 5);
+        assert_eq!(active_combo(5, 10, 10), 0);
+    }
+
+    #[test]
+    fn kills_extend_or_restart_combo() {
+        assert_eq!(advance_combo(0, 0, 2, 100), (2, 100 + COMBO_WINDOW_SECS));
+        assert_eq!(advance_combo(2, 103, 3, 102), (5, 102 + COMBO_WINDOW_SECS));
+        assert_eq!(advance_combo(5, 103, 1, 103), (1, 103 + COMBO_WINDOW_SECS));
+        assert_eq!(advance_combo(5, 103, 0, 102), (5, 103));
+        assert_eq!(advance_combo(5, 103, 0, 103), (0, 0));
+        assert_eq!(advance_combo(MAX_COMBO, 103, 10, 102).0, MAX_COMBO);
+    }
+
+    #[test]
+    fn combo_scaled_matches_multiplier() {
+        assert_eq!(combo_scaled(100, 0), 100);
+        assert_eq!(combo_scaled(100, COMBO_DIVISOR), 200);
+        assert_eq!(combo_scaled(100, 25), 150);
+        assert_eq!(combo_scaled(u32::MAX, MAX_COMBO), u32::MAX);
+    }
+}
@@ -0,0 +1,91 @@
+use crate::waves::mix;
+
+/// Roll for a chest drop
+pub const ROLL_CHEST: u8 = 0;
+/// Roll for an elite spawn
+pub const ROLL_ELITE: u8 = 1;
+/// Roll for a revive-chance event
+pub const ROLL_REVIVE: u8 = 2;
+
+/// Percent chance that a revive rolled with `ROLL_REVIVE` isn't used up
+pub const LUCKY_REVIVE_CHANCE_PCT: u32 = 25;
+
+/// Whether `event` is one of the `ROLL_*` events
+pub fn is_roll_event(event: u8) -> bool {
+    event <= ROLL_REVIVE
+}
+
+/// Uniform-enough value in `0..range` for `event` (`ROLL_*`) from one fulfilled randomness
+/// Each event draws from its own stream, so one value can settle several events; 0 if `range`
+/// is 0
+pub fn roll(randomness: &[u8; 32], event: u8, range: u32) -> u32 {
+    if range == 0 {
+        return 0;
+    }
+    let word = randomness
+        .chunks_exact(8)
+        .map(|chunk| u64::from_le_bytes(chunk.try_into().unwrap()))
+        .fold(event as u64, |acc, word| mix(acc ^ word));
+    (word % range as u64) as u32
+}
+
+/// Bytes hashed into a request's randomness: the oracle's revealed secret, the player's seed from
+/// the request, the session account and the request nonce (little-endian)
+/// The oracle commits to the secret before the player picks the seed, so neither side alone
+/// can steer the outcome
+pub fn reveal_preimage(
+    secret: &[u8; 32],
+    client_seed: &[u8; 32],
+    session: &[u8; 32],
+    nonce: u64,
+) -> [u8; 104] {
+    let mut preimage = [0u8; 104];
+    preimage[..32].copy_from_slice(secret);
+    preimage[32..64].copy_from_slice(client_seed);
+    preimage[64..96].copy_from_slice(session);
+    preimage[96..].copy_from_slice(&nonce.to_le_bytes());
+    preimage
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn roll_is_deterministic_and_in_range() {
+        let randomness = [7u8; 32];
+        assert_eq!(
+            roll(&randomness, ROLL_CHEST, 100),
+            roll(&randomness, ROLL_CHEST, 100)
+        );
+        for range in [1, 2, 6, 100, u32::MAX] {
+            assert!(roll(&randomness, ROLL_ELITE, range) < range);
+        }
+        assert_eq!(roll(&randomness, ROLL_REVIVE, 0), 0);
+    }
+
+    #[test]
+    fn events_draw_separate_streams() {
+        let randomness = [42u8; 32];
+        let chest = roll(&randomness, ROLL_CHEST, u32::MAX);
+        assert_ne!(chest, roll(&randomness, ROLL_ELITE, u32::MAX));
+        assert_ne!(chest, roll(&[43u8; 32], ROLL_CHEST, u32::MAX));
+    }
+
+    #[test]
+    fn preimage_is_secret_seed_session_then_nonce() {
+        let preimage = reveal_preimage(&[1u8; 32], &[2u8; 32], &[3u8; 32], 0x0102);
+        assert_eq!(preimage[..32], [1u8; 32]);
+        assert_eq!(preimage[32..64], [2u8; 32]);
+        assert_eq!(preimage[64..96], [3u8; 32]);
+        assert_eq!(preimage[96..], [2, 1, 0, 0, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn only_known_events_are_accepted() {
+        assert!(
+            is_roll_event(ROLL_CHEST) && is_roll_event(ROLL_ELITE) && is_roll_event(ROLL_REVIVE)
+        );
+        assert!(!is_roll_event(ROLL_REVIVE + 1));
+    }
+}
@@ -0,0 +1,68 @@
+/// Run ended because the player died
+pub const COMPLETION_DIED: u8 = 0;
+/// Run ended while the player was still alive (quit or closed the tab)
+pub const COMPLETION_QUIT: u8 = 1;
+
+/// Points per wave reached
+pub const SCORE_PER_WAVE: u64 = 1_000;
+/// Points per kill
+pub const SCORE_PER_KILL: u64 = 10;
+/// Points per second survived
+pub const SCORE_PER_SECOND: u64 = 1;
+
+/// Shortest run (seconds) that counts towards `games_played`
+pub const MIN_COUNTED_RUN_SECS: u32 = 10;
+/// Reaching this wave counts a run even if it was shorter than `MIN_COUNTED_RUN_SECS`
+pub const MIN_COUNTED_WAVE: u8 = 2;
+
+/// Single comparable number summarising a run
+pub fn compute_score(wave: u8, time_survived: u32, kills: u32) -> u64 {
+    (wave as u64) * SCORE_PER_WAVE
+        + (kills as u64) * SCORE_PER_KILL
+        + (time_survived as u64) * SCORE_PER_SECOND
+}
+
+/// Why a run ended, from the session's death flag
+pub fn completion_reason(is_dead: bool) -> u8 {
+    if is_dead {
+        COMPLETION_DIED
+    } else {
+        COMPLETION_QUIT
+    }
+}
+
+/// Whether a run counts towards `games_played`; instant abandons would skew per-game averages
+pub fn counts_as_game(time_survived: u32, wave: u8) -> bool {
+    time_survived >= MIN_COUNTED_RUN_SECS || wave >= MIN_COUNTED_WAVE
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn score_weights_wave_kills_and_time() {
+        assert_eq!(compute_score(0, 0, 0), 0);
+        assert_eq!(compute_score(3, 120, 45), 3_000 + 450 + 120);
+        assert_eq!(
+            compute_score(u8::MAX, u32::MAX, u32::MAX),
+            255 * SCORE_PER_WAVE + u32::MAX as u64 * (SCORE_PER_KILL + SCORE_PER_SECOND)
+        );
+    }
+
+    #[test]
+    fn completion_reason_follows_death() {
+        assert_eq!(completion_reason(true), COMPLETION_DIED);
+        assert_eq!(completion_reason(false), COMPLETION_QUIT);
+    }
+
+    #[test]
+    fn short_early_runs_are_not_counted() {
+        assert!(!counts_as_game(
+            MIN_COUNTED_RUN_SECS - 1,
+            MIN_COUNTED_WAVE - 1
+        ));
+        assert!(counts_as_game(MIN_COUNTED_RUN_SECS, 1));
+        assert!(counts_as_game(0, MIN_COUNTED_WAVE));
+    }
+}
@@ -0,0 +1,36 @@
+use alloc::string::String;
+
+/// Characters of the base58 wallet address used as the entity seed prefix
+pub const WALLET_PREFIX_LEN: usize = 20;
+
+pub const PLAYER_SEED_SUFFIX: &str = "player";
+pub const SESSION_SEED_SUFFIX: &str = "session";
+pub const LEADERBOARD_SEED_SUFFIX: &str = "leaderboard";
+
+/// Independent runs (save slots) a player can keep, each on its own session entity
+pub const MAX_SESSION_SLOTS: u8 = 4;
+
+/// Bolt entity seed for a wallet: `${walletAddress.slice(0, 20)}-{suffix}`
+/// Matches `getEntitySeed` in the client, so servers can derive the same entity PDAs
+pub fn entity_seed(wallet_base58: &str, suffix: &str) -> String {
+    let prefix_end = wallet_base58
+        .char_indices()
+        .nth(WALLET_PREFIX_LEN)
+        .map_or(wallet_base58.len(), |(i, _)| i);
+    let mut seed = String::with_capacity(prefix_end + 1 + suffix.len());
+    seed.push_str(&wallet_base58[..prefix_end]);
+    seed.push('-');
+    seed.push_str(suffix);
+    seed
+}
+
+/// Session entity suffix for a save slot: `SESSION_SEED_SUFFIX` for slot 0, so sessions created
+/// before slots keep their entity, and `session-{slot}` otherwise
+/// Matches `sessionSeedSuffix` in the client
+pub fn session_seed_suffix(slot: u8) -> String {
+    if slot == 0 {
+        String::from(SESSION_SEED_SUFFIX)
+    } else {
+        alloc::format!("{SESSION_SEED_SUFFIX}-{slot}")
+    }
+}
@@ -0,0 +1,41 @@
+use alloc::format;
+use alloc::string::String;
+
+/// Split seconds into (minutes, seconds) with integer math only
+/// Minutes are not capped at 59, so any `u32` round-trips
+pub fn duration_parts(secs: u32) -> (u32, u32) {
+    (secs / 60, secs % 60)
+}
+
+/// `m:ss` under an hour, `h:mm:ss` from an hour on; float-free so it's usable in `msg!` logs
+pub fn format_duration(secs: u32) -> String {
+    let (minutes, seconds) = duration_parts(secs);
+    if minutes < 60 {
+        format!("{}:{:02}", minutes, seconds)
+    } else {
+        format!("{}:{:02}:{:02}", minutes / 60, minutes % 60, seconds)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parts_split_minutes_and_seconds() {
+        assert_eq!(duration_parts(0), (0, 0));
+        assert_eq!(duration_parts(59), (0, 59));
+        assert_eq!(duration_parts(60), (1, 0));
+        assert_eq!(duration_parts(u32::MAX), (u32::MAX / 60, u32::MAX % 60));
+    }
+
+    #[test]
+    fn formats_minutes_then_hours() {
+        assert_eq!(format_duration(0), "0:00");
+        assert_eq!(format_duration(59), "0:59");
+        assert_eq!(format_duration(60), "1:00");
+        assert_eq!(format_duration(3_599), "59:59");
+        assert_eq!(format_duration(3_600), "1:00:00");
+        assert_eq!(format_duration(u32::MAX), "1193046:28:15");
+    }
+}
@@ -0,0 +1,98 @@
+/// Length of a wave in milliseconds (client `WAVE_DURATION`)
+pub const WAVE_DURATION_MS: u32 = 30_000;
+
+/// Fastest spawn interval any wave reaches, in milliseconds
+pub const MIN_SPAWN_INTERVAL_MS: u32 = 300;
+
+/// First wave that can spawn a boss (bosses come every 5th wave)
+pub const FIRST_BOSS_WAVE: u8 = 5;
+
+/// Waves between two L1 checkpoints of a run
+pub const CHECKPOINT_INTERVAL_WAVES: u8 = 5;
+
+/// Largest extra enemy count a seed can add to a wave
+pub const ENEMY_COUNT_JITTER: u32 = 8;
+
+/// Spawn interval for a wave, mirroring the client's `getWaveConfig`
+pub fn spawn_interval_ms(wave: u8) -> u32 {
+    800u32
+        .saturating_sub(wave as u32 * 50)
+        .max(MIN_SPAWN_INTERVAL_MS)
+}
+
+/// Deterministic enemy count for a wave: regular spawns over the wave's duration, a boss slot
+/// from `FIRST_BOSS_WAVE` on, plus a seed-derived jitter in `0..=ENEMY_COUNT_JITTER`
+/// The jitter depends only on the seed, so for a fixed seed the count never drops as waves rise.
+/// Clients use it to build waves; anti-cheat uses it as the most kills a wave can yield
+pub fn enemies_for_wave(wave: u8, seed: u64) -> u32 {
+    let spawns = WAVE_DURATION_MS / spawn_interval_ms(wave);
+    let boss = u32::from(wave >= FIRST_BOSS_WAVE);
+    spawns + boss + (mix(seed) % (ENEMY_COUNT_JITTER as u64 + 1)) as u32
+}
+
+/// True once a run at `wave` is `CHECKPOINT_INTERVAL_WAVES` past its last checkpoint
+/// (`last_checkpoint_wave` is 0 before the first one)
+pub fn checkpoint_due(last_checkpoint_wave: u8, wave: u8) -> bool {
+    wave >= last_checkpoint_wave.saturating_add(CHECKPOINT_INTERVAL_WAVES)
+}
+
+/// splitmix64 finalizer, so adjacent seeds give unrelated jitter
+pub(crate) fn mix(seed: u64) -> u64 {
+    let mut z = seed.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn spawn_interval_bottoms_out() {
+        assert_eq!(spawn_interval_ms(0), 800);
+        assert_eq!(spawn_interval_ms(1), 750);
+        assert_eq!(spawn_interval_ms(10), MIN_SPAWN_INTERVAL_MS);
+        assert_eq!(spawn_interval_ms(u8::MAX), MIN_SPAWN_INTERVAL_MS);
+    }
+
+    #[test]
+    fn enemy_count_is_deterministic() {
+        for seed in [0, 1, 0xdead_beef, u64::MAX] {
+            assert_eq!(enemies_for_wave(7, seed), enemies_for_wave(7, seed));
+        }
+    }
+
+    #[test]
+    fn enemy_count_never_drops_as_waves_rise() {
+        for seed in [0, 1, 0xdead_beef, u64::MAX] {
+            for wave in 0..u8::MAX {
+                assert!(enemies_for_wave(wave + 1, seed) >= enemies_for_wave(wave, seed));
+            }
+        }
+    }
+
+    #[test]
+    fn jitter_stays_within_bounds() {
+        let base = WAVE_DURATION_MS / spawn_interval_ms(1);
+        for seed in 0..64 {
+            let count = enemies_for_wave(1, seed);
+            assert!((base..=base + ENEMY_COUNT_JITTER).contains(&count));
+        }
+        assert_eq!(
+            enemies_for_wave(FIRST_BOSS_WAVE, 3) - enemies_for_wave(FIRST_BOSS_WAVE - 1, 3),
+            WAVE_DURATION_MS / spawn_interval_ms(FIRST_BOSS_WAVE)
+                - WAVE_DURATION_MS / spawn_interval_ms(FIRST_BOSS_WAVE - 1)
+                + 1
+        );
+    }
+
+    #[test]
+    fn checkpoint_every_interval() {
+        assert!(!checkpoint_due(0, CHECKPOINT_INTERVAL_WAVES - 1));
+        assert!(checkpoint_due(0, CHECKPOINT_INTERVAL_WAVES));
+        assert!(!checkpoint_due(5, 9));
+        assert!(checkpoint_due(5, 10));
+        assert!(checkpoint_due(u8::MAX, u8::MAX));
+    }
+}
@@ -0,0 +1,242 @@
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{quote, ToTokens};
+use syn::{
+    parse::Parser, parse_macro_input, punctuated::Punctuated, Data, DeriveInput, Expr, Fields,
+    ItemFn, Meta, Token,
+};
+
+// Derive `session_keys::Session` for an Anchor accounts struct
+//
+// Exactly one field (the optional session token) carries the attribute:
+//
+//     #[session(signer = signer, authority = user.authority.key())]
+//     pub session_token: Option<Account<'info, SessionToken>>,
+//
+// `signer` names the ephemeral key's `Signer` field; `authority` is an expression over the
+// struct's fields for the wallet that must have created the session. The optional
+// `scope = game_session.key()` names the account the instruction acts on, for tokens bound
+// to one account. The target program is always the program the struct is compiled into
+// (`crate::id()`).
+#[proc_macro_derive(Session, attributes(session))]
+pub fn derive_session(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    match expand_session(input) {
+        Ok(tokens) => tokens.into(),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
+
+fn expand_session(input: DeriveInput) -> syn::Result<TokenStream2> {
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => return Err(syn::Error::new_spanned(name, "Session needs named fields")),
+        },
+        _ => return Err(syn::Error::new_spanned(name, "Session can only be derived for structs")),
+    };
+
+    let mut session = None;
+    for field in fields {
+        for attr in field.attrs.iter().filter(|attr| attr.path().is_ident("session")) {
+            if session.is_some() {
+                return Err(syn::Error::new_spanned(attr, "only one #[session] field is allowed"));
+            }
+            let args = attr.parse_args_with(Punctuated::<Meta, Token![,]>::parse_terminated)?;
+            session = Some((field.ident.clone().unwrap(), parse_session_args(attr, args)?));
+        }
+    }
+    let (token_field, (signer, authority, scope)) = session.ok_or_else(|| {
+        syn::Error::new_spanned(name, "missing #[session(signer = .., authority = ..)] field")
+    })?;
+
+    let scope_fn = scope.map(|scope| {
+        quote! {
+            fn session_scope(&self) -> Option<::anchor_lang::prelude::Pubkey> {
+                Some(self.#scope)
+            }
+        }
+    });
+
+    Ok(quote! {
+        impl #impl_generics ::session_keys::Session<'info> for #name #ty_generics #where_clause {
+            fn session_token(
+                &self,
+            ) -> Option<::anchor_lang::prelude::Account<'info, ::session_keys::SessionToken>> {
+                self.#token_field.clone()
+            }
+
+            fn session_signer(&self) -> ::anchor_lang::prelude::Signer<'info> {
+                self.#signer.clone()
+            }
+
+            fn session_authority(&self) -> ::anchor_lang::prelude::Pubkey {
+                self.#authority
+            }
+
+            fn target_program(&self) -> ::anchor_lang::prelude::Pubkey {
+                crate::id()
+            }
+
+            #scope_fn
+        }
+    })
+}
+
+// `signer = <field>` and `authority = <expr>`, both required, plus optional `scope = <expr>`
+fn parse_session_args(
+    attr: &syn::Attribute,
+    args: Punctuated<Meta, Token![,]>,
+) -> syn::Result<(TokenStream2, TokenStream2, Option<TokenStream2>)> {
+    let mut signer = None;
+    let mut authority = None;
+    let mut scope = None;
+    for arg in args {
+        let Meta::NameValue(arg) = arg else {
+            return Err(syn::Error::new_spanned(arg, "expected `name = value`"));
+        };
+        if arg.path.is_ident("signer") {
+            signer = Some(arg.value.to_token_stream());
+        } else if arg.path.is_ident("authority") {
+            authority = Some(arg.value.to_token_stream());
+        } else if arg.path.is_ident("scope") {
+            scope = Some(arg.value.to_token_stream());
+        } else {
+            return Err(syn::Error::new_spanned(
+                arg.path,
+                "expected `signer`, `authority` or `scope`",
+            ));
+        }
+    }
+    match (signer, authority) {
+        (Some(signer), Some(authority)) => Ok((signer, authority, scope)),
+        _ => Err(syn::Error::new_spanned(attr, "#[session] needs both `signer` and `authority`")),
+    }
+}
+
+// Gate an instruction handler on a valid session, with a fallback check without one
+//
+//     #[session_auth_or(ctx.accounts.user.authority == ctx.accounts.signer.key(), MyError::Auth)]
+//
+// With a session token the handler runs only if `is_valid()` passes (expiry surfaces as
+// `SessionExpired`, other failures as `InvalidToken`); without one `condition` must hold or
+// the handler fails with `error`.
+#[proc_macro_attribute]
+pub fn session_auth_or(args: TokenStream, item: TokenStream) -> TokenStream {
+    let args = match Punctuated::<Expr, Token![,]>::parse_terminated.parse(args) {
+        Ok(args) => args,
+        Err(err) => return err.to_compile_error().into(),
+    };
+    let mut item = parse_macro_input!(item as ItemFn);
+    if args.len() != 2 {
+        return syn::Error::new_spanned(&item.sig, "expected `condition, error`")
+            .to_compile_error()
+            .into();
+    }
+    let condition = &args[0];
+    let error = &args[1];
+
+    let body = &item.block;
+    item.block = syn::parse_quote!({
+        if ::session_keys::Session::session_token(&*ctx.accounts).is_some() {
+            ::anchor_lang::require!(
+                ::session_keys::Session::is_valid(&*ctx.accounts)?,
+                ::session_keys::SessionError::InvalidToken
+            );
+        } else {
+            ::anchor_lang::require!(#condition, #error);
+        }
+        #body
+    });
+    item.into_token_stream().into()
+}
+
+// Require a valid session before an instruction handler runs
+//
+//     #[session_guard]
+//     #[session_guard(MyError::SessionRequired)]
+//
+// The handler's accounts must derive `Session`. A missing session token fails with
+// `NoToken` and an expired one with `SessionExpired`; any other failed check fails with the
+// given error, `SessionError::InvalidToken` by default.
+#[proc_macro_attribute]
+pub fn session_guard(args: TokenStream, item: TokenStream) -> TokenStream {
+    let args = match Punctuated::<Expr, Token![,]>::parse_terminated.parse(args) {
+        Ok(args) => args,
+        Err(err) => return err.to_compile_error().into(),
+    };
+    let item = parse_macro_input!(item as ItemFn);
+    match expand_session_guard(args, item) {
+        Ok(tokens) => tokens.into(),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
+
+fn expand_session_guard(
+    args: Punctuated<Expr, Token![,]>,
+    mut item: ItemFn,
+) -> syn::Result<TokenStream2> {
+    let error = match args.len() {
+        0 => quote!(::session_keys::SessionError::InvalidToken),
+        1 => args[0].to_token_stream(),
+        _ => return Err(syn::Error::new_spanned(&item.sig, "expected at most one error")),
+    };
+
+    let body = &item.block;
+    item.block = syn::parse_quote!({
+        ::anchor_lang::require!(::session_keys::Session::is_valid(&*ctx.accounts)?, #error);
+        #body
+    });
+    Ok(item.into_token_stream())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn handler() -> ItemFn {
+        syn::parse_quote! {
+            pub fn play(ctx: Context<Play>) -> Result<()> {
+                ctx.accounts.game.play()
+            }
+        }
+    }
+
+    // The guard statement `session_guard` put in front of the body, and the body after it
+    fn expand(args: Punctuated<Expr, Token![,]>) -> (String, String) {
+        let expanded: ItemFn = syn::parse2(expand_session_guard(args, handler()).unwrap()).unwrap();
+        assert_eq!(
+            expanded.sig.to_token_stream().to_string(),
+            handler().sig.to_token_stream().to_string()
+        );
+        let [guard, body] = &expanded.block.stmts[..] else {
+            panic!("expected the guard and the original body");
+        };
+        (guard.to_token_stream().to_string(), body.to_token_stream().to_string())
+    }
+
+    #[test]
+    fn guard_runs_before_the_body_with_the_default_error() {
+        let (guard, body) = expand(Punctuated::new());
+        assert!(guard.contains("is_valid"));
+        assert!(guard.contains("SessionError :: InvalidToken"));
+        assert!(body.contains("play"));
+    }
+
+    #[test]
+    fn guard_fails_with_the_given_error() {
+        let (guard, _) = expand(syn::parse_quote!(MyError::SessionRequired));
+        assert!(guard.contains("MyError :: SessionRequired"));
+        assert!(!guard.contains("InvalidToken"));
+    }
+
+    #[test]
+    fn more_than_one_error_is_rejected() {
+        let args = syn::parse_quote!(MyError::A, MyError::B);
+        let err = expand_session_guard(args, handler()).unwrap_err();
+        assert_eq!(err.to_string(), "expected at most one error");
+    }
+}
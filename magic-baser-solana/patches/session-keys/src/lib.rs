@@ -1,12 +1,63 @@
+use anchor_lang::solana_program::instruction::Instruction;
+use anchor_lang::solana_program::program::invoke_signed;
+use anchor_lang::solana_program::{ed25519_program, sysvar::instructions as instructions_sysvar};
 use anchor_lang::{prelude::*, system_program};
+use anchor_spl::token_interface::{self, Mint, TokenAccount, TokenInterface, TransferChecked};
+use ephemeral_rollups_sdk::anchor::{commit, delegate, ephemeral};
+use ephemeral_rollups_sdk::cpi::DelegateConfig;
+use ephemeral_rollups_sdk::ephem::commit_and_undelegate_accounts;
 
 /// Number of lamports per SOL (1 SOL = 1_000_000_000 lamports)
 const LAMPORTS_PER_SOL: u64 = 1_000_000_000;
 
+/// Longest validity a session token may ever be given, in seconds (1 week, inclusive)
+/// `ValidityPolicy::max_validity_secs` can only tighten it
+const MAX_VALIDITY_SECS: i64 = 60 * 60 * 24 * 7;
+
+/// Default shortest validity for new tokens, so clients can't create tokens that expire on arrival
+pub const DEFAULT_MIN_VALIDITY_SECS: i64 = 60;
+
+/// Live tokens per authority while no SessionConfig has been initialized
+pub const DEFAULT_MAX_SESSIONS_PER_AUTHORITY: u8 = 8;
+
+/// How far ahead of now each `heartbeat` keeps a token alive
+pub const HEARTBEAT_EXTENSION_SECS: i64 = 60 * 10;
+
+/// Default seconds past `valid_until` during which a token still validates, so transactions
+/// signed right before expiry don't fail at the boundary
+pub const EXPIRY_GRACE_SECS: i64 = 30;
+
+/// Upper bound on a token's combined grace window and clock tolerance
+pub const MAX_GRACE_SECS: i64 = 60 * 10;
+
+/// Most instruction discriminators a token can be scoped to
+pub const MAX_ALLOWED_INSTRUCTIONS: usize = 8;
+
+/// Most programs one token can cover, counting the primary `target_program`
+pub const MAX_TARGET_PROGRAMS: usize = 4;
+
+/// Most live tokens a SessionIndex can list
+pub const MAX_INDEXED_SESSIONS: usize = 16;
+
+/// Domain prefix of the message an authority signs off-chain for `create_session_from_signature`
+pub const SESSION_AUTHORIZATION_DOMAIN: &[u8] = b"gpl_session:create_session:v1";
+
+/// Most tokens `create_sessions_batch` creates in one instruction
+pub const MAX_BATCH_SESSIONS: usize = 8;
+
+/// Most target programs one SponsorConfig can approve
+pub const MAX_SPONSORED_PROGRAMS: usize = 8;
+
+/// Length of a sponsor's budget period (UTC days since the epoch)
+const SPONSOR_BUDGET_PERIOD_SECS: i64 = 60 * 60 * 24;
+
+/// Longest a pre-signed `create_session_relative` transaction may sit in a queue (1 day)
+pub const MAX_REFERENCE_TOLERANCE_SECS: i64 = 60 * 60 * 24;
+
 #[cfg(feature = "no-entrypoint")]
 pub use session_keys_macros::*;
 
-declare_id!("KeyspM2ssCJbqUhQ4k7sveSiY4WjnYsrXkC8oDbwde5");
+declare_id!("2YrfV6Du5kraNbG4WBcHaGu3dMzDDvPaghj4Twnmd73C");
 
 #[cfg(not(feature = "no-entrypoint"))]
 solana_security_txt::security_txt! {
@@ -18,19 +69,22 @@ solana_security_txt::security_txt! {
     source_code: "https://github.com/magicblock-labs"
 }
 
+#[ephemeral]
 #[program]
 pub mod gpl_session {
     use super::*;
 
-    // create a session token
+    // create a session token; `token_amount` tops the signer up with SPL tokens as well
     pub fn create_session(
         ctx: Context<CreateSessionToken>,
         top_up: Option<bool>,
         valid_until: Option<i64>,
         lamports: Option<u64>,
+        token_amount: Option<u64>,
     ) -> Result<()> {
-        let (top_up, valid_until) = process_session_params(top_up, valid_until)?;
-        create_session_token_handler(ctx, top_up, valid_until, lamports)
+        let config = SessionConfig::load(&ctx.accounts.session_config)?;
+        let (top_up, valid_until) = process_session_params(top_up, valid_until, &config)?;
+        create_session_token_handler(ctx, top_up, valid_until, lamports, token_amount)
     }
 
     pub fn create_session_with_payer(
@@ -39,21 +93,354 @@ pub mod gpl_session {
         valid_until: Option<i64>,
         lamports: Option<u64>,
     ) -> Result<()> {
-        let (top_up, valid_until) = process_session_params(top_up, valid_until)?;
+        let config = SessionConfig::load(&ctx.accounts.session_config)?;
+        let (top_up, valid_until) = process_session_params(top_up, valid_until, &config)?;
         create_session_token_with_payer_handler(ctx, top_up, valid_until, lamports)
     }
+    // create a session token whose validity runs from execution time, for durable-nonce
+    // transactions signed well before they land
+    pub fn create_session_relative(
+        ctx: Context<CreateSessionToken>,
+        top_up: Option<bool>,
+        reference_timestamp: i64,
+        validity_secs: i64,
+        tolerance_secs: i64,
+        lamports: Option<u64>,
+        token_amount: Option<u64>,
+    ) -> Result<()> {
+        let valid_until = relative_valid_until(reference_timestamp, validity_secs, tolerance_secs)?;
+        create_session_token_handler(
+            ctx,
+            top_up.unwrap_or(false),
+            valid_until,
+            lamports,
+            token_amount,
+        )
+    }
+
+    // approve target programs and a daily lamport budget for sessions this payer sponsors
+    pub fn set_sponsor_config(
+        ctx: Context<SetSponsorConfig>,
+        target_programs: Vec<Pubkey>,
+        daily_budget_lamports: u64,
+    ) -> Result<()> {
+        set_sponsor_config_handler(ctx, target_programs, daily_budget_lamports)
+    }
+
+    // create a session token from an authority's off-chain ed25519 signature, submitted by a relayer
+    pub fn create_session_from_signature(
+        ctx: Context<CreateSessionTokenFromSignature>,
+        valid_until: i64,
+        nonce: u64,
+        top_up: Option<bool>,
+        lamports: Option<u64>,
+    ) -> Result<()> {
+        create_session_from_signature_handler(
+            ctx,
+            valid_until,
+            nonce,
+            top_up.unwrap_or(false),
+            lamports,
+        )
+    }
+
+    // create one session token per target program passed in remaining accounts
+    pub fn create_sessions_batch<'info>(
+        ctx: Context<'_, '_, 'info, 'info, CreateSessionTokensBatch<'info>>,
+        top_up: Option<bool>,
+        valid_until: Option<i64>,
+        lamports: Option<u64>,
+    ) -> Result<()> {
+        let config = SessionConfig::load(&ctx.accounts.session_config)?;
+        let (top_up, valid_until) = process_session_params(top_up, valid_until, &config)?;
+        create_sessions_batch_handler(ctx, top_up, valid_until, lamports)
+    }
+
+    // create a session token, or refresh its expiry if it already exists
+    pub fn create_or_refresh_session(
+        ctx: Context<CreateOrRefreshSessionToken>,
+        top_up: Option<bool>,
+        valid_until: Option<i64>,
+        lamports: Option<u64>,
+    ) -> Result<()> {
+        let config = SessionConfig::load(&ctx.accounts.session_config)?;
+        let (top_up, valid_until) = process_session_params(top_up, valid_until, &config)?;
+        create_or_refresh_session_token_handler(ctx, top_up, valid_until, lamports)
+    }
+
+    // push a session token's expiry to a new time
+    pub fn refresh_session(ctx: Context<RefreshSessionToken>, valid_until: Option<i64>) -> Result<()> {
+        let config = SessionConfig::load(&ctx.accounts.session_config)?;
+        let (_, valid_until) = process_session_params(None, valid_until, &config)?;
+        refresh_session_token_handler(ctx, valid_until)
+    }
+
+    // push a live session token's expiry forward, clamped to the policy's max validity
+    pub fn extend_session(ctx: Context<RefreshSessionToken>, extend_by_secs: i64) -> Result<()> {
+        extend_session_token_handler(ctx, extend_by_secs)
+    }
+
+    // limit a session token to a set of target program instructions (empty clears the limit)
+    pub fn set_allowed_instructions(
+        ctx: Context<SetAllowedInstructions>,
+        allowed_instructions: Vec<[u8; 8]>,
+    ) -> Result<()> {
+        set_allowed_instructions_handler(ctx, allowed_instructions)
+    }
+
+    // change a session token's expiry grace window and clock tolerance
+    pub fn set_grace_config(ctx: Context<SetGraceConfig>, grace: GraceConfig) -> Result<()> {
+        set_grace_config_handler(ctx, grace)
+    }
+
+    // let a session token also cover other programs (replaces the previous list)
+    pub fn set_extra_target_programs(
+        ctx: Context<SetExtraTargetPrograms>,
+        extra_target_programs: Vec<Pubkey>,
+    ) -> Result<()> {
+        set_extra_target_programs_handler(ctx, extra_target_programs)
+    }
+
+    // send more fee lamports to a live session token's signer
+    pub fn top_up_session(ctx: Context<TopUpSession>, lamports: Option<u64>) -> Result<()> {
+        top_up_session_handler(ctx, lamports)
+    }
+
+    // keep a live session token alive for another `HEARTBEAT_EXTENSION_SECS`, signed by its signer
+    pub fn heartbeat(ctx: Context<Heartbeat>) -> Result<()> {
+        heartbeat_handler(ctx)
+    }
+
+    // cap the lamports a session token may spend (0 = unlimited)
+    pub fn set_spend_cap(ctx: Context<SetSpendCap>, max_spend_lamports: u64) -> Result<()> {
+        set_spend_cap_handler(ctx, max_spend_lamports)
+    }
+
+    // record lamports spent through a session token, called by target programs via CPI
+    pub fn record_spend(ctx: Context<RecordSpend>, lamports: u64) -> Result<()> {
+        record_spend_handler(ctx, lamports)
+    }
+
+    // check a session token for a caller that can't read its layout, answering via return data
+    pub fn validate_session(
+        ctx: Context<ValidateSession>,
+        target_program: Pubkey,
+        instruction: Option<[u8; 8]>,
+        scope: Option<Pubkey>,
+    ) -> Result<SessionValidation> {
+        validate_session_handler(ctx, target_program, instruction, scope)
+    }
+
+    // bind a session token to a single account (e.g. one GameSession), or clear the binding
+    pub fn set_scope(ctx: Context<SetSessionScope>, scope: Option<Pubkey>) -> Result<()> {
+        set_scope_handler(ctx, scope)
+    }
+
+    // attach app-specific context (client version, device hash, ...) to a session token
+    pub fn set_metadata(ctx: Context<SetSessionMetadata>, metadata: [u8; 32]) -> Result<()> {
+        set_metadata_handler(ctx, metadata)
+    }
+
+    // designate (or clear) a key that may revoke a session token besides its authority
+    pub fn set_revoker(ctx: Context<SetSessionRevoker>, revoker: Option<Pubkey>) -> Result<()> {
+        set_revoker_handler(ctx, revoker)
+    }
+
+    // upgrade a token created with the legacy layout to the current one in place
+    pub fn migrate_session(ctx: Context<MigrateSessionToken>) -> Result<()> {
+        migrate_session_handler(ctx)
+    }
+
+    // move a live session token under a new authority (wallet rotation), signed by both wallets
+    pub fn transfer_session_authority(ctx: Context<TransferSessionAuthority>) -> Result<()> {
+        transfer_session_authority_handler(ctx)
+    }
+
     // revoke a session token
     pub fn revoke_session(ctx: Context<RevokeSessionToken>) -> Result<()> {
         revoke_session_token_handler(ctx)
     }
+
+    // close every session token passed in remaining accounts, signed by their authority
+    pub fn revoke_all<'info>(
+        ctx: Context<'_, '_, 'info, 'info, RevokeAllSessionTokens<'info>>,
+    ) -> Result<()> {
+        revoke_all_handler(ctx)
+    }
+
+    // mark a session token revoked without closing it
+    pub fn soft_revoke(ctx: Context<SoftRevokeSessionToken>) -> Result<()> {
+        soft_revoke_handler(ctx)
+    }
+
+    // close an expired session token, callable by anyone
+    pub fn crank_expired(ctx: Context<CrankExpiredSessionToken>) -> Result<()> {
+        crank_expired_handler(ctx)
+    }
+
+    // close many expired session tokens in one instruction, callable by anyone
+    pub fn close_expired_session<'info>(
+        ctx: Context<'_, '_, 'info, 'info, CloseExpiredSessions>,
+    ) -> Result<()> {
+        close_expired_session_handler(ctx)
+    }
+
+    // delegate a session token to the Ephemeral Rollup, next to the accounts it authorizes
+    pub fn delegate_session(ctx: Context<DelegateSessionToken>) -> Result<()> {
+        delegate_session_handler(ctx)
+    }
+
+    // commit a session token back from the Ephemeral Rollup and return it to L1 (run on the ER)
+    pub fn undelegate_session(ctx: Context<UndelegateSessionToken>) -> Result<()> {
+        undelegate_session_handler(ctx)
+    }
+
+    // create the authority's SessionIndex so new tokens can be listed in it
+    pub fn initialize_session_index(ctx: Context<InitializeSessionIndex>) -> Result<()> {
+        initialize_session_index_handler(ctx)
+    }
+
+    // create the program config, signed by the program upgrade authority
+    pub fn initialize_config(
+        ctx: Context<InitializeSessionConfig>,
+        max_sessions_per_authority: u8,
+    ) -> Result<()> {
+        initialize_config_handler(ctx, max_sessions_per_authority)
+    }
+
+    // change the program config, signed by the config admin
+    pub fn update_config(
+        ctx: Context<UpdateSessionConfig>,
+        max_sessions_per_authority: u8,
+    ) -> Result<()> {
+        update_config_handler(ctx, max_sessions_per_authority)
+    }
+
+    // change the validity bounds and defaults for new tokens, signed by the config admin
+    pub fn set_validity_policy(
+        ctx: Context<UpdateSessionConfig>,
+        policy: ValidityPolicy,
+    ) -> Result<()> {
+        set_validity_policy_handler(ctx, policy)
+    }
+
+    // hand the pause switch to another key, signed by the config admin
+    pub fn set_freeze_authority(
+        ctx: Context<UpdateSessionConfig>,
+        freeze_authority: Pubkey,
+    ) -> Result<()> {
+        set_freeze_authority_handler(ctx, freeze_authority)
+    }
+
+    // halt or resume session creation, signed by the freeze authority
+    pub fn set_paused(ctx: Context<SetSessionPause>, paused: bool) -> Result<()> {
+        set_paused_handler(ctx, paused)
+    }
 }
 
-fn process_session_params(top_up: Option<bool>, valid_until: Option<i64>) -> Result<(bool, i64)> {
+fn process_session_params(
+    top_up: Option<bool>,
+    valid_until: Option<i64>,
+    config: &SessionConfig,
+) -> Result<(bool, i64)> {
     let top_up = top_up.unwrap_or(false);
-    let valid_until = valid_until.unwrap_or(Clock::get()?.unix_timestamp + 60 * 60 * 1);
+    let valid_until = valid_until
+        .unwrap_or(Clock::get()?.unix_timestamp + config.policy.default_validity_secs);
     Ok((top_up, valid_until))
 }
 
+// `valid_until` for a relative creation: `validity_secs` from now, as long as now is within
+// `tolerance_secs` of the client's `reference_timestamp`
+// A durable-nonce transaction can't carry a fixed `valid_until` safely, since it may land hours
+// after signing; the tolerance still bounds how stale a queued transaction may be.
+fn relative_valid_until(
+    reference_timestamp: i64,
+    validity_secs: i64,
+    tolerance_secs: i64,
+) -> Result<i64> {
+    require!(
+        (0..=MAX_REFERENCE_TOLERANCE_SECS).contains(&tolerance_secs),
+        SessionError::ToleranceTooLong
+    );
+    let now = Clock::get()?.unix_timestamp;
+    require!(
+        now.saturating_sub(reference_timestamp).abs() <= tolerance_secs,
+        SessionError::StaleReference
+    );
+    Ok(now.saturating_add(validity_secs))
+}
+
+// Valid until must fall within the config's validity bounds from now
+// Both bounds are inclusive: `now + max_validity_secs` itself is accepted, one second more is not
+fn require_valid_until(valid_until: i64, now: i64, policy: &ValidityPolicy) -> Result<()> {
+    require!(
+        valid_until <= now + policy.max_validity_secs,
+        SessionError::ValidityTooLong
+    );
+    // Never accept an already-expired token, even under a config written before `min > 0`
+    require!(
+        valid_until >= now + policy.min_validity_secs.max(1),
+        SessionError::ValidityTooShort
+    );
+    Ok(())
+}
+
+// List a new token in the authority's SessionIndex, when the client passed one
+fn index_new_session(
+    session_index: &mut Option<Account<SessionIndex>>,
+    session_token: Pubkey,
+) -> Result<()> {
+    match session_index {
+        Some(session_index) => session_index.add(session_token),
+        None => Ok(()),
+    }
+}
+
+// Bookkeeping shared by every path that closes a session token: free its slot in the
+// authority's count and drop it from the authority's SessionIndex, when the client passed one
+fn release_session(
+    session_count: &mut AuthoritySessionCount,
+    session_index: Option<&mut SessionIndex>,
+    session_token: &Pubkey,
+) -> Result<()> {
+    session_count.release()?;
+    if let Some(session_index) = session_index {
+        session_index.remove(session_token);
+    }
+    Ok(())
+}
+
+// The signer lookup passed in `remaining_accounts` next to a closing token, if it still names
+// the token and should be closed with it
+// Accounts that aren't one of our lookups (the system program stands in for "none") and
+// lookups that moved on to a newer token are left alone
+fn lookup_to_close<'info>(
+    info: &'info AccountInfo<'info>,
+    session_token: &Pubkey,
+) -> Result<Option<Account<'info, SessionSignerLookup>>> {
+    if *info.owner != crate::ID || info.data_is_empty() {
+        return Ok(None);
+    }
+    let lookup = Account::<SessionSignerLookup>::try_from(info)?;
+    Ok((lookup.session_token == *session_token).then_some(lookup))
+}
+
+// Count a new live token against the authority's cap
+fn track_new_session(
+    session_count: &mut AuthoritySessionCount,
+    authority: Pubkey,
+    config: &SessionConfig,
+) -> Result<()> {
+    // Every create_session* path counts its tokens here, so this is the one pause check
+    require!(!config.paused, SessionError::CreationPaused);
+    require!(
+        session_count.count < config.max_sessions_per_authority as u16,
+        SessionError::TooManySessions
+    );
+    session_count.add(authority);
+    Ok(())
+}
+
 // Create a SessionToken account
 #[derive(Accounts)]
 pub struct CreateSessionToken<'info> {
@@ -71,6 +458,37 @@ pub struct CreateSessionToken<'info> {
     )]
     pub session_token: Account<'info, SessionToken>,
 
+    #[account(
+        init_if_needed,
+        seeds = [AuthoritySessionCount::SEED_PREFIX.as_bytes(), authority.key().as_ref()],
+        bump,
+        payer = authority,
+        space = AuthoritySessionCount::LEN
+    )]
+    pub session_count: Account<'info, AuthoritySessionCount>,
+
+    // Optional: when passed, the new token is listed in the authority's index
+    #[account(
+        mut,
+        seeds = [SessionIndex::SEED_PREFIX.as_bytes(), authority.key().as_ref()],
+        bump
+    )]
+    pub session_index: Option<Account<'info, SessionIndex>>,
+
+    // Optional: when passed, the signer's lookup pointer is aimed at the new token
+    #[account(
+        init_if_needed,
+        seeds = [SessionSignerLookup::SEED_PREFIX.as_bytes(), session_signer.key().as_ref()],
+        bump,
+        payer = authority,
+        space = SessionSignerLookup::LEN
+    )]
+    pub signer_lookup: Option<Account<'info, SessionSignerLookup>>,
+
+    /// CHECK: address checked by seeds, contents by `SessionConfig::load`
+    #[account(seeds = [SessionConfig::SEED_PREFIX.as_bytes()], bump)]
+    pub session_config: UncheckedAccount<'info>,
+
     #[account(mut)]
     pub session_signer: Signer<'info>,
     #[account(mut)]
@@ -81,6 +499,49 @@ pub struct CreateSessionToken<'info> {
     pub target_program: AccountInfo<'info>,
 
     pub system_program: Program<'info, System>,
+
+    // Optional SPL top-up (e.g. from a wSOL treasury), all four or none; required when the
+    // instruction gets a `token_amount`, which moves that many base units of `top_up_mint` on
+    // top of any lamport top-up (the signer still needs lamports for fees)
+    pub top_up_mint: Option<InterfaceAccount<'info, Mint>>,
+    // Authority-owned source of the tokens
+    #[account(mut)]
+    pub top_up_source: Option<InterfaceAccount<'info, TokenAccount>>,
+    // Session signer-owned destination
+    #[account(mut)]
+    pub top_up_destination: Option<InterfaceAccount<'info, TokenAccount>>,
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+}
+
+// Top up the session signer with SPL tokens (token or token-2022)
+fn spl_top_up(accounts: &CreateSessionToken, amount: u64) -> Result<()> {
+    let (Some(mint), Some(source), Some(destination), Some(token_program)) = (
+        &accounts.top_up_mint,
+        &accounts.top_up_source,
+        &accounts.top_up_destination,
+        &accounts.token_program,
+    ) else {
+        return err!(SessionError::IncompleteTokenTopUp);
+    };
+    require_keys_eq!(
+        destination.owner,
+        accounts.session_signer.key(),
+        SessionError::IncompleteTokenTopUp
+    );
+
+    token_interface::transfer_checked(
+        CpiContext::new(
+            token_program.to_account_info(),
+            TransferChecked {
+                from: source.to_account_info(),
+                mint: mint.to_account_info(),
+                to: destination.to_account_info(),
+                authority: accounts.authority.to_account_info(),
+            },
+        ),
+        amount,
+        mint.decimals,
+    )
 }
 
 fn create_session_token_internal<'info>(
@@ -94,18 +555,26 @@ fn create_session_token_internal<'info>(
     top_up: bool,
     valid_until: i64,
     lamports: Option<u64>,
+    policy: &ValidityPolicy,
+    bump: u8,
 ) -> Result<()> {
-    // Valid until can't be greater than a week
-    require!(
-        valid_until <= Clock::get()?.unix_timestamp + (60 * 60 * 24 * 7),
-        SessionError::ValidityTooLong
-    );
+    let now = Clock::get()?.unix_timestamp;
+    require_valid_until(valid_until, now, policy)?;
 
-    session_token.set_inner(SessionToken {
+    session_token.set_inner(SessionToken::new(
         authority,
         target_program,
         session_signer,
         valid_until,
+        now,
+        bump,
+    ));
+
+    emit!(SessionCreated {
+        authority,
+        session_signer,
+        target_program,
+        valid_until,
     });
 
     // Top up the session signer account with some lamports to pay for the transaction fees
@@ -118,20 +587,44 @@ fn create_session_token_internal<'info>(
                     to: session_signer_account,
                 },
             ),
-            lamports.unwrap_or(LAMPORTS_PER_SOL / 100),
+            lamports.unwrap_or(policy.default_top_up_lamports),
         )?;
     }
 
     Ok(())
 }
 
+// Aim the signer's lookup pointer at a freshly created token, when the client passed one
+fn point_signer_lookup(
+    signer_lookup: &mut Option<Account<SessionSignerLookup>>,
+    session_token: &Account<SessionToken>,
+) {
+    if let Some(signer_lookup) = signer_lookup {
+        signer_lookup.session_token = session_token.key();
+        signer_lookup.authority = session_token.authority;
+        signer_lookup.target_program = session_token.target_program;
+    }
+}
+
 // Handler to create a session token account
 pub fn create_session_token_handler(
     ctx: Context<CreateSessionToken>,
     top_up: bool,
     valid_until: i64,
     lamports: Option<u64>,
+    token_amount: Option<u64>,
 ) -> Result<()> {
+    let config = SessionConfig::load(&ctx.accounts.session_config)?;
+    track_new_session(
+        &mut ctx.accounts.session_count,
+        ctx.accounts.authority.key(),
+        &config,
+    )?;
+    index_new_session(
+        &mut ctx.accounts.session_index,
+        ctx.accounts.session_token.key(),
+    )?;
+
     create_session_token_internal(
         &mut ctx.accounts.session_token,
         ctx.accounts.authority.key(),
@@ -143,10 +636,22 @@ pub fn create_session_token_handler(
         top_up,
         valid_until,
         lamports,
-    )
+        &config.policy,
+        ctx.bumps.session_token,
+    )?;
+    point_signer_lookup(&mut ctx.accounts.signer_lookup, &ctx.accounts.session_token);
+
+    if let Some(amount) = token_amount {
+        spl_top_up(ctx.accounts, amount)?;
+    }
+    Ok(())
 }
 
-// Create a SessionToken account
+// Create a SessionToken account with a separate fee payer
+// Roles: `authority` is the wallet the session acts for, `session_signer` is the ephemeral key
+// that will sign game transactions, and `payer` (e.g. a relayer) funds the rent and any top-up.
+// The payer may be the authority, but never the session signer when topping up: a signer
+// funding its own top-up is circular and usually means the roles were wired up wrong.
 #[derive(Accounts)]
 pub struct CreateSessionTokenWithPayer<'info> {
     #[account(
@@ -163,6 +668,45 @@ pub struct CreateSessionTokenWithPayer<'info> {
     )]
     pub session_token: Account<'info, SessionToken>,
 
+    #[account(
+        init_if_needed,
+        seeds = [AuthoritySessionCount::SEED_PREFIX.as_bytes(), authority.key().as_ref()],
+        bump,
+        payer = payer,
+        space = AuthoritySessionCount::LEN
+    )]
+    pub session_count: Account<'info, AuthoritySessionCount>,
+
+    // Optional: when passed, the new token is listed in the authority's index
+    #[account(
+        mut,
+        seeds = [SessionIndex::SEED_PREFIX.as_bytes(), authority.key().as_ref()],
+        bump
+    )]
+    pub session_index: Option<Account<'info, SessionIndex>>,
+
+    // Optional: when passed, the signer's lookup pointer is aimed at the new token
+    #[account(
+        init_if_needed,
+        seeds = [SessionSignerLookup::SEED_PREFIX.as_bytes(), session_signer.key().as_ref()],
+        bump,
+        payer = payer,
+        space = SessionSignerLookup::LEN
+    )]
+    pub signer_lookup: Option<Account<'info, SessionSignerLookup>>,
+
+    /// CHECK: address pinned by the seeds; required unless the payer is the authority
+    #[account(
+        mut,
+        seeds = [SponsorConfig::SEED_PREFIX.as_bytes(), payer.key().as_ref()],
+        bump
+    )]
+    pub sponsor_config: UncheckedAccount<'info>,
+
+    /// CHECK: address checked by seeds, contents by `SessionConfig::load`
+    #[account(seeds = [SessionConfig::SEED_PREFIX.as_bytes()], bump)]
+    pub session_config: UncheckedAccount<'info>,
+
     #[account(mut)]
     pub session_signer: Signer<'info>,
     #[account(mut)]
@@ -183,6 +727,36 @@ pub fn create_session_token_with_payer_handler(
     valid_until: i64,
     lamports: Option<u64>,
 ) -> Result<()> {
+    let config = SessionConfig::load(&ctx.accounts.session_config)?;
+    let top_up_lamports = payer_top_up_lamports(
+        &ctx.accounts.payer.key(),
+        &ctx.accounts.session_signer.key(),
+        top_up,
+        lamports,
+        &config.policy,
+    )?;
+
+    // Token rent plus top-up count against the payer's sponsorship budget
+    charge_sponsor(
+        &ctx.accounts.sponsor_config,
+        &ctx.accounts.payer.key(),
+        &ctx.accounts.authority.key(),
+        &ctx.accounts.target_program.key(),
+        Rent::get()?
+            .minimum_balance(SessionToken::LEN)
+            .saturating_add(top_up_lamports),
+        Clock::get()?.unix_timestamp,
+    )?;
+
+    track_new_session(
+        &mut ctx.accounts.session_count,
+        ctx.accounts.authority.key(),
+        &config,
+    )?;
+    index_new_session(
+        &mut ctx.accounts.session_index,
+        ctx.accounts.session_token.key(),
+    )?;
     create_session_token_internal(
         &mut ctx.accounts.session_token,
         ctx.accounts.authority.key(),
@@ -194,110 +768,2472 @@ pub fn create_session_token_with_payer_handler(
         top_up,
         valid_until,
         lamports,
-    )
+        &config.policy,
+        ctx.bumps.session_token,
+    )?;
+    point_signer_lookup(&mut ctx.accounts.signer_lookup, &ctx.accounts.session_token);
+    Ok(())
 }
 
-// Revoke a session token
-// We allow *anyone* to revoke a session token. This is because the session token is designed to
-// expire on it's own after a certain amount of time. However, if the session token is compromised
-// anyone can revoke it immediately.
-//
-// One attack vector here to consider, however is that a malicious actor could enumerate all the tokens
-// created using the program and revoke them all or keep revoking them as they are created. It is a
-// nuisance but not a security risk. We can easily address this by whitelisting a revoker.
+// Lamports the payer moves to the session signer, refusing a signer that tops itself up
+fn payer_top_up_lamports(
+    payer: &Pubkey,
+    session_signer: &Pubkey,
+    top_up: bool,
+    lamports: Option<u64>,
+    policy: &ValidityPolicy,
+) -> Result<u64> {
+    if !top_up {
+        return Ok(0);
+    }
+    require_keys_neq!(*payer, *session_signer, SessionError::PayerIsSessionSigner);
+    Ok(lamports.unwrap_or(policy.default_top_up_lamports))
+}
+
+// Charge a sponsored creation to the payer's SponsorConfig
+// An authority paying for its own session isn't sponsoring and is never charged; any other
+// payer needs a SponsorConfig, so no relayer sponsors without a program list and budget
+fn charge_sponsor(
+    sponsor_config: &AccountInfo,
+    payer: &Pubkey,
+    authority: &Pubkey,
+    target_program: &Pubkey,
+    lamports: u64,
+    now: i64,
+) -> Result<()> {
+    if payer == authority {
+        return Ok(());
+    }
+    require!(
+        sponsor_config.owner == &crate::id() && !sponsor_config.data_is_empty(),
+        SessionError::PayerNotSponsor
+    );
+    let mut config = SponsorConfig::try_deserialize(&mut &sponsor_config.try_borrow_data()?[..])?;
+    config.charge(target_program, lamports, now)?;
+    config.try_serialize(&mut &mut sponsor_config.try_borrow_mut_data()?[..])
+}
+
+// Create or update a payer's sponsorship terms
+// A relayer that co-signs `create_session_with_payer` for many users sets these once; from then
+// on the program refuses sponsored creations for other target programs or past the daily budget,
+// however the relayer's backend is tricked into signing.
 #[derive(Accounts)]
-pub struct RevokeSessionToken<'info> {
+pub struct SetSponsorConfig<'info> {
     #[account(
-        mut,
-        seeds = [
-            SessionToken::SEED_PREFIX.as_bytes(),
-            session_token.target_program.key().as_ref(),
-            session_token.session_signer.key().as_ref(),
-            session_token.authority.key().as_ref()
-        ],
+        init_if_needed,
+        seeds = [SponsorConfig::SEED_PREFIX.as_bytes(), sponsor.key().as_ref()],
         bump,
-        has_one = authority,
-        close = authority,
+        payer = sponsor,
+        space = SponsorConfig::LEN
     )]
-    pub session_token: Account<'info, SessionToken>,
+    pub sponsor_config: Account<'info, SponsorConfig>,
 
     #[account(mut)]
-    // Only the token authority can reclaim the rent
-    pub authority: SystemAccount<'info>,
+    pub sponsor: Signer<'info>,
 
     pub system_program: Program<'info, System>,
 }
 
-// Handler to revoke a session token
-pub fn revoke_session_token_handler(_: Context<RevokeSessionToken>) -> Result<()> {
+// Handler to set sponsorship terms
+// Today's spending is kept, so lowering the budget takes effect immediately
+pub fn set_sponsor_config_handler(
+    ctx: Context<SetSponsorConfig>,
+    target_programs: Vec<Pubkey>,
+    daily_budget_lamports: u64,
+) -> Result<()> {
+    require!(
+        target_programs.len() <= MAX_SPONSORED_PROGRAMS,
+        SessionError::TooManyTargetPrograms
+    );
+    let sponsor_config = &mut ctx.accounts.sponsor_config;
+    sponsor_config.sponsor = ctx.accounts.sponsor.key();
+    sponsor_config.target_programs = target_programs;
+    sponsor_config.daily_budget_lamports = daily_budget_lamports;
     Ok(())
 }
 
-pub struct ValidityChecker<'info> {
+// Create a SessionToken authorized by an off-chain signature
+// The authority signs `session_authorization_message(..)` with its wallet and hands it to a
+// relayer, which submits an Ed25519 program instruction carrying that signature immediately
+// before this one. The wallet never has to be online for the transaction itself.
+#[derive(Accounts)]
+pub struct CreateSessionTokenFromSignature<'info> {
+    #[account(
+        init,
+        seeds = [
+            SessionToken::SEED_PREFIX.as_bytes(),
+            target_program.key().as_ref(),
+            session_signer.key().as_ref(),
+            authority.key().as_ref()
+        ],
+        bump,
+        payer = payer,
+        space = SessionToken::LEN
+    )]
     pub session_token: Account<'info, SessionToken>,
-    pub session_signer: Signer<'info>,
-    pub authority: Pubkey,
-    pub target_program: Pubkey,
-}
 
-// SessionToken Account
-#[account]
-#[derive(Copy)]
-pub struct SessionToken {
-    pub authority: Pubkey,
-    pub target_program: Pubkey,
-    pub session_signer: Pubkey,
+    #[account(
+        init_if_needed,
+        seeds = [AuthoritySessionCount::SEED_PREFIX.as_bytes(), authority.key().as_ref()],
+        bump,
+        payer = payer,
+        space = AuthoritySessionCount::LEN
+    )]
+    pub session_count: Account<'info, AuthoritySessionCount>,
+
+    // Optional: when passed, the new token is listed in the authority's index
+    #[account(
+        mut,
+        seeds = [SessionIndex::SEED_PREFIX.as_bytes(), authority.key().as_ref()],
+        bump
+    )]
+    pub session_index: Option<Account<'info, SessionIndex>>,
+
+    // Replay protection for the authority's signed messages
+    #[account(
+        init_if_needed,
+        seeds = [AuthorityNonce::SEED_PREFIX.as_bytes(), authority.key().as_ref()],
+        bump,
+        payer = payer,
+        space = AuthorityNonce::LEN
+    )]
+    pub authority_nonce: Account<'info, AuthorityNonce>,
+
+    /// CHECK: address checked by seeds, contents by `SessionConfig::load`
+    #[account(seeds = [SessionConfig::SEED_PREFIX.as_bytes()], bump)]
+    pub session_config: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub session_signer: Signer<'info>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    /// CHECK: proven by the ed25519 signature over the authorization message
+    pub authority: UncheckedAccount<'info>,
+
+    /// CHECK the target program is actually a program.
+    #[account(executable)]
+    pub target_program: AccountInfo<'info>,
+
+    /// CHECK: the instructions sysvar, for reading the Ed25519 instruction
+    #[account(address = instructions_sysvar::ID)]
+    pub instructions: AccountInfo<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+// The exact bytes an authority signs to approve a session
+// `nonce` must be the authority's current `AuthorityNonce::nonce` (0 before its first use)
+pub fn session_authorization_message(
+    target_program: &Pubkey,
+    session_signer: &Pubkey,
+    valid_until: i64,
+    nonce: u64,
+) -> Vec<u8> {
+    let mut message = SESSION_AUTHORIZATION_DOMAIN.to_vec();
+    message.extend_from_slice(crate::id().as_ref());
+    message.extend_from_slice(target_program.as_ref());
+    message.extend_from_slice(session_signer.as_ref());
+    message.extend_from_slice(&valid_until.to_le_bytes());
+    message.extend_from_slice(&nonce.to_le_bytes());
+    message
+}
+
+// Check that the instruction right before this one is an Ed25519 program verification of
+// `message` signed by `signer`
+// The precompile already failed the transaction if the signature were bad; what's left is
+// making sure it verified the key and message we expect, with all data inline.
+fn verify_ed25519_authorization(
+    instructions: &AccountInfo,
+    signer: &Pubkey,
+    message: &[u8],
+) -> Result<()> {
+    // Layout: count(1) + padding(1), then per signature seven u16 offsets/indices
+    const HEADER_LEN: usize = 2;
+    const OFFSETS_LEN: usize = 14;
+    const SAME_INSTRUCTION: u16 = u16::MAX;
+
+    let current = instructions_sysvar::load_current_index_checked(instructions)?;
+    require!(current > 0, SessionError::InvalidAuthorization);
+    let ix = instructions_sysvar::load_instruction_at_checked(current as usize - 1, instructions)?;
+    require_keys_eq!(ix.program_id, ed25519_program::ID, SessionError::InvalidAuthorization);
+
+    let data = &ix.data;
+    require!(
+        data.len() >= HEADER_LEN + OFFSETS_LEN && data[0] == 1,
+        SessionError::InvalidAuthorization
+    );
+    let read_u16 = |at: usize| u16::from_le_bytes([data[at], data[at + 1]]);
+    let offsets = HEADER_LEN;
+    let signature_ix = read_u16(offsets + 2);
+    let public_key_offset = read_u16(offsets + 4) as usize;
+    let public_key_ix = read_u16(offsets + 6);
+    let message_offset = read_u16(offsets + 8) as usize;
+    let message_len = read_u16(offsets + 10) as usize;
+    let message_ix = read_u16(offsets + 12);
+    require!(
+        signature_ix == SAME_INSTRUCTION
+            && public_key_ix == SAME_INSTRUCTION
+            && message_ix == SAME_INSTRUCTION,
+        SessionError::InvalidAuthorization
+    );
+
+    let public_key = data
+        .get(public_key_offset..public_key_offset + 32)
+        .ok_or(SessionError::InvalidAuthorization)?;
+    let signed = data
+        .get(message_offset..message_offset + message_len)
+        .ok_or(SessionError::InvalidAuthorization)?;
+    require!(
+        public_key == signer.as_ref() && signed == message,
+        SessionError::InvalidAuthorization
+    );
+    Ok(())
+}
+
+// Handler to create a session token from an off-chain signature
+pub fn create_session_from_signature_handler(
+    ctx: Context<CreateSessionTokenFromSignature>,
+    valid_until: i64,
+    nonce: u64,
+    top_up: bool,
+    lamports: Option<u64>,
+) -> Result<()> {
+    let config = SessionConfig::load(&ctx.accounts.session_config)?;
+    verify_ed25519_authorization(
+        &ctx.accounts.instructions,
+        ctx.accounts.authority.key,
+        &session_authorization_message(
+            ctx.accounts.target_program.key,
+            ctx.accounts.session_signer.key,
+            valid_until,
+            nonce,
+        ),
+    )?;
+    ctx.accounts
+        .authority_nonce
+        .consume(ctx.accounts.authority.key(), nonce)?;
+
+    if top_up {
+        require_keys_neq!(
+            ctx.accounts.payer.key(),
+            ctx.accounts.session_signer.key(),
+            SessionError::PayerIsSessionSigner
+        );
+    }
+    track_new_session(
+        &mut ctx.accounts.session_count,
+        ctx.accounts.authority.key(),
+        &config,
+    )?;
+    index_new_session(
+        &mut ctx.accounts.session_index,
+        ctx.accounts.session_token.key(),
+    )?;
+    create_session_token_internal(
+        &mut ctx.accounts.session_token,
+        ctx.accounts.authority.key(),
+        ctx.accounts.target_program.key(),
+        ctx.accounts.session_signer.key(),
+        ctx.accounts.system_program.to_account_info(),
+        ctx.accounts.payer.to_account_info(),
+        ctx.accounts.session_signer.to_account_info(),
+        top_up,
+        valid_until,
+        lamports,
+        &config.policy,
+        ctx.bumps.session_token,
+    )
+}
+
+// Create several SessionToken accounts for one signer in a single instruction
+// Bolt games run many system programs; this issues a token per program behind one wallet
+// approval. `remaining_accounts` holds `[target_program, session_token]` pairs, the token
+// being the uninitialized PDA for that program (writable).
+#[derive(Accounts)]
+pub struct CreateSessionTokensBatch<'info> {
+    #[account(
+        init_if_needed,
+        seeds = [AuthoritySessionCount::SEED_PREFIX.as_bytes(), authority.key().as_ref()],
+        bump,
+        payer = authority,
+        space = AuthoritySessionCount::LEN
+    )]
+    pub session_count: Account<'info, AuthoritySessionCount>,
+
+    // Optional: when passed, the new tokens are listed in the authority's index
+    #[account(
+        mut,
+        seeds = [SessionIndex::SEED_PREFIX.as_bytes(), authority.key().as_ref()],
+        bump
+    )]
+    pub session_index: Option<Account<'info, SessionIndex>>,
+
+    /// CHECK: address checked by seeds, contents by `SessionConfig::load`
+    #[account(seeds = [SessionConfig::SEED_PREFIX.as_bytes()], bump)]
+    pub session_config: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub session_signer: Signer<'info>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+// Handler to create a batch of session tokens
+// Top-up happens once for the shared signer, not per token
+pub fn create_sessions_batch_handler<'info>(
+    ctx: Context<'_, '_, 'info, 'info, CreateSessionTokensBatch<'info>>,
+    top_up: bool,
+    valid_until: i64,
+    lamports: Option<u64>,
+) -> Result<()> {
+    let config = SessionConfig::load(&ctx.accounts.session_config)?;
+    let now = Clock::get()?.unix_timestamp;
+    require_valid_until(valid_until, now, &config.policy)?;
+
+    let pairs = ctx.remaining_accounts.chunks_exact(2);
+    require!(
+        pairs.remainder().is_empty() && pairs.len() > 0 && pairs.len() <= MAX_BATCH_SESSIONS,
+        SessionError::InvalidBatch
+    );
+
+    let authority = ctx.accounts.authority.key();
+    let session_signer = ctx.accounts.session_signer.key();
+    let rent = Rent::get()?.minimum_balance(SessionToken::LEN);
+
+    for pair in pairs {
+        let (target_program, session_token) = (&pair[0], &pair[1]);
+        require!(target_program.executable, SessionError::InvalidBatch);
+
+        let (pda, bump) = Pubkey::find_program_address(
+            &[
+                SessionToken::SEED_PREFIX.as_bytes(),
+                target_program.key.as_ref(),
+                session_signer.as_ref(),
+                authority.as_ref(),
+            ],
+            &crate::id(),
+        );
+        require_keys_eq!(pda, *session_token.key, SessionError::InvalidToken);
+
+        track_new_session(&mut ctx.accounts.session_count, authority, &config)?;
+        index_new_session(&mut ctx.accounts.session_index, pda)?;
+
+        system_program::create_account(
+            CpiContext::new_with_signer(
+                ctx.accounts.system_program.to_account_info(),
+                system_program::CreateAccount {
+                    from: ctx.accounts.authority.to_account_info(),
+                    to: session_token.clone(),
+                },
+                &[&[
+                    SessionToken::SEED_PREFIX.as_bytes(),
+                    target_program.key.as_ref(),
+                    session_signer.as_ref(),
+                    authority.as_ref(),
+                    &[bump],
+                ]],
+            ),
+            rent,
+            SessionToken::LEN as u64,
+            &crate::id(),
+        )?;
+
+        let token = SessionToken::new(
+            authority,
+            *target_program.key,
+            session_signer,
+            valid_until,
+            now,
+            bump,
+        );
+        token.try_serialize(&mut &mut session_token.try_borrow_mut_data()?[..])?;
+
+        emit!(SessionCreated {
+            authority,
+            session_signer,
+            target_program: *target_program.key,
+            valid_until,
+        });
+    }
+
+    // Top up the session signer account with some lamports to pay for the transaction fees
+    if top_up {
+        system_program::transfer(
+            CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                system_program::Transfer {
+                    from: ctx.accounts.authority.to_account_info(),
+                    to: ctx.accounts.session_signer.to_account_info(),
+                },
+            ),
+            lamports.unwrap_or(config.policy.default_top_up_lamports),
+        )?;
+    }
+
+    Ok(())
+}
+
+// Create a SessionToken account, or reuse the existing one for the same triple
+// `init_if_needed` hands us an already-initialized account on the second call, so the handler
+// must never overwrite its identity fields, only `valid_until`.
+#[derive(Accounts)]
+pub struct CreateOrRefreshSessionToken<'info> {
+    #[account(
+        init_if_needed,
+        seeds = [
+            SessionToken::SEED_PREFIX.as_bytes(),
+            target_program.key().as_ref(),
+            session_signer.key().as_ref(),
+            authority.key().as_ref()
+        ],
+        bump,
+        payer = authority,
+        space = SessionToken::LEN
+    )]
+    pub session_token: Account<'info, SessionToken>,
+
+    #[account(
+        init_if_needed,
+        seeds = [AuthoritySessionCount::SEED_PREFIX.as_bytes(), authority.key().as_ref()],
+        bump,
+        payer = authority,
+        space = AuthoritySessionCount::LEN
+    )]
+    pub session_count: Account<'info, AuthoritySessionCount>,
+
+    // Optional: when passed, the new token is listed in the authority's index
+    #[account(
+        mut,
+        seeds = [SessionIndex::SEED_PREFIX.as_bytes(), authority.key().as_ref()],
+        bump
+    )]
+    pub session_index: Option<Account<'info, SessionIndex>>,
+
+    /// CHECK: address checked by seeds, contents by `SessionConfig::load`
+    #[account(seeds = [SessionConfig::SEED_PREFIX.as_bytes()], bump)]
+    pub session_config: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub session_signer: Signer<'info>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    /// CHECK the target program is actually a program.
+    #[account(executable)]
+    pub target_program: AccountInfo<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+// Handler to create or refresh a session token account
+pub fn create_or_refresh_session_token_handler(
+    ctx: Context<CreateOrRefreshSessionToken>,
+    top_up: bool,
+    valid_until: i64,
+    lamports: Option<u64>,
+) -> Result<()> {
+    let config = SessionConfig::load(&ctx.accounts.session_config)?;
+    let refreshed = refresh_existing_token(
+        &mut ctx.accounts.session_token,
+        ctx.accounts.authority.key(),
+        ctx.accounts.target_program.key(),
+        ctx.accounts.session_signer.key(),
+        valid_until,
+        Clock::get()?.unix_timestamp,
+        &config.policy,
+    )?;
+    if let Some(refreshed) = refreshed {
+        emit!(refreshed);
+        return Ok(());
+    }
+
+    track_new_session(
+        &mut ctx.accounts.session_count,
+        ctx.accounts.authority.key(),
+        &config,
+    )?;
+    index_new_session(
+        &mut ctx.accounts.session_index,
+        ctx.accounts.session_token.key(),
+    )?;
+    create_session_token_internal(
+        &mut ctx.accounts.session_token,
+        ctx.accounts.authority.key(),
+        ctx.accounts.target_program.key(),
+        ctx.accounts.session_signer.key(),
+        ctx.accounts.system_program.to_account_info(),
+        ctx.accounts.authority.to_account_info(),
+        ctx.accounts.session_signer.to_account_info(),
+        top_up,
+        valid_until,
+        lamports,
+        &config.policy,
+        ctx.bumps.session_token,
+    )
+}
+
+// Refresh path of `create_or_refresh_session`
+// A freshly created account is zeroed and gets `None`, for the caller to create; anything else
+// is an existing token, whose expiry moves. The seeds already pin the keys, but never let the
+// refresh path rewrite identity
+fn refresh_existing_token(
+    session_token: &mut SessionToken,
+    authority: Pubkey,
+    target_program: Pubkey,
+    session_signer: Pubkey,
+    valid_until: i64,
+    now: i64,
+    policy: &ValidityPolicy,
+) -> Result<Option<SessionRefreshed>> {
+    if session_token.authority == Pubkey::default() {
+        return Ok(None);
+    }
+    require_valid_until(valid_until, now, policy)?;
+    require_keys_eq!(
+        session_token.authority,
+        authority,
+        SessionError::InvalidToken
+    );
+    require_keys_eq!(
+        session_token.target_program,
+        target_program,
+        SessionError::InvalidToken
+    );
+    require_keys_eq!(
+        session_token.session_signer,
+        session_signer,
+        SessionError::InvalidToken
+    );
+    Ok(Some(session_token.refresh(valid_until)))
+}
+
+// Refresh a session token
+// Only the authority that created the token may change its expiry.
+#[derive(Accounts)]
+pub struct RefreshSessionToken<'info> {
+    #[account(
+        mut,
+        seeds = [
+            SessionToken::SEED_PREFIX.as_bytes(),
+            session_token.target_program.key().as_ref(),
+            session_token.session_signer.key().as_ref(),
+            session_token.authority.key().as_ref()
+        ],
+        bump = session_token.bump,
+        has_one = authority,
+    )]
+    pub session_token: Account<'info, SessionToken>,
+
+    /// CHECK: address checked by seeds, contents by `SessionConfig::load`
+    #[account(seeds = [SessionConfig::SEED_PREFIX.as_bytes()], bump)]
+    pub session_config: UncheckedAccount<'info>,
+
+    pub authority: Signer<'info>,
+}
+
+// Handler to refresh a session token
+pub fn refresh_session_token_handler(
+    ctx: Context<RefreshSessionToken>,
+    valid_until: i64,
+) -> Result<()> {
+    let config = SessionConfig::load(&ctx.accounts.session_config)?;
+    require_valid_until(valid_until, Clock::get()?.unix_timestamp, &config.policy)?;
+
+    let session_token = &mut ctx.accounts.session_token;
+    emit!(session_token.refresh(valid_until));
+
+    Ok(())
+}
+
+// Handler to extend a session token
+// Unlike `refresh_session` this only ever moves expiry later and never fails on the cap: the
+// new expiry is clamped to `now + max_validity_secs`, so a client can extend blindly mid-run.
+pub fn extend_session_token_handler(
+    ctx: Context<RefreshSessionToken>,
+    extend_by_secs: i64,
+) -> Result<()> {
+    let config = SessionConfig::load(&ctx.accounts.session_config)?;
+    require!(extend_by_secs > 0, SessionError::InvalidExtension);
+
+    let now = Clock::get()?.unix_timestamp;
+    let max_validity_secs = config.policy.max_validity_secs;
+    let session_token = &mut ctx.accounts.session_token;
+    // Dead tokens are closed and recreated, not revived
+    require!(
+        !session_token.revoked && session_token.is_within_grace(now),
+        SessionError::InvalidToken
+    );
+
+    let old_valid_until = session_token.valid_until;
+    let new_valid_until = old_valid_until
+        .saturating_add(extend_by_secs)
+        .min(now + max_validity_secs)
+        .max(old_valid_until);
+    session_token.valid_until = new_valid_until;
+
+    emit!(SessionExtended {
+        authority: session_token.authority,
+        session_signer: session_token.session_signer,
+        target_program: session_token.target_program,
+        old_valid_until,
+        valid_until: new_valid_until,
+    });
+
+    Ok(())
+}
+
+// Scope a session token to specific instructions of its target program
+// Tokens start unrestricted; the authority can narrow them (usually in the same transaction
+// as the create) or widen them again by passing an empty list.
+#[derive(Accounts)]
+pub struct SetAllowedInstructions<'info> {
+    #[account(
+        mut,
+        seeds = [
+            SessionToken::SEED_PREFIX.as_bytes(),
+            session_token.target_program.key().as_ref(),
+            session_token.session_signer.key().as_ref(),
+            session_token.authority.key().as_ref()
+        ],
+        bump = session_token.bump,
+        has_one = authority,
+    )]
+    pub session_token: Account<'info, SessionToken>,
+
+    pub authority: Signer<'info>,
+}
+
+// Handler to scope a session token
+pub fn set_allowed_instructions_handler(
+    ctx: Context<SetAllowedInstructions>,
+    allowed_instructions: Vec<[u8; 8]>,
+) -> Result<()> {
+    require!(
+        allowed_instructions.len() <= MAX_ALLOWED_INSTRUCTIONS,
+        SessionError::TooManyAllowedInstructions
+    );
+    ctx.accounts.session_token.allowed_instructions = allowed_instructions;
+    Ok(())
+}
+
+// Tune how long past `valid_until` a session token keeps validating
+#[derive(Accounts)]
+pub struct SetGraceConfig<'info> {
+    #[account(
+        mut,
+        seeds = [
+            SessionToken::SEED_PREFIX.as_bytes(),
+            session_token.target_program.key().as_ref(),
+            session_token.session_signer.key().as_ref(),
+            session_token.authority.key().as_ref()
+        ],
+        bump = session_token.bump,
+        has_one = authority,
+    )]
+    pub session_token: Account<'info, SessionToken>,
+
+    pub authority: Signer<'info>,
+}
+
+// Handler to set a session token's grace config
+pub fn set_grace_config_handler(ctx: Context<SetGraceConfig>, grace: GraceConfig) -> Result<()> {
+    require!(grace.window_secs() <= MAX_GRACE_SECS, SessionError::GraceTooLong);
+    ctx.accounts.session_token.grace = grace;
+    Ok(())
+}
+
+// Cover more programs with one session token
+// The PDA stays derived from the primary `target_program`; the extras only widen what
+// `validate` accepts, so a game spread over several programs needs a single wallet approval.
+#[derive(Accounts)]
+pub struct SetExtraTargetPrograms<'info> {
+    #[account(
+        mut,
+        seeds = [
+            SessionToken::SEED_PREFIX.as_bytes(),
+            session_token.target_program.key().as_ref(),
+            session_token.session_signer.key().as_ref(),
+            session_token.authority.key().as_ref()
+        ],
+        bump = session_token.bump,
+        has_one = authority,
+    )]
+    pub session_token: Account<'info, SessionToken>,
+
+    pub authority: Signer<'info>,
+}
+
+// Handler to set the extra target programs
+pub fn set_extra_target_programs_handler(
+    ctx: Context<SetExtraTargetPrograms>,
+    extra_target_programs: Vec<Pubkey>,
+) -> Result<()> {
+    require!(
+        extra_target_programs.len() < MAX_TARGET_PROGRAMS,
+        SessionError::TooManyTargetPrograms
+    );
+    ctx.accounts.session_token.extra_target_programs = extra_target_programs;
+    Ok(())
+}
+
+// Cap what a session token may spend
+// Bounds the damage of a leaked session key; lowering the cap below what was already spent
+// simply exhausts the token
+#[derive(Accounts)]
+pub struct SetSpendCap<'info> {
+    #[account(
+        mut,
+        seeds = [
+            SessionToken::SEED_PREFIX.as_bytes(),
+            session_token.target_program.key().as_ref(),
+            session_token.session_signer.key().as_ref(),
+            session_token.authority.key().as_ref()
+        ],
+        bump = session_token.bump,
+        has_one = authority,
+    )]
+    pub session_token: Account<'info, SessionToken>,
+
+    pub authority: Signer<'info>,
+}
+
+// Handler to cap a session token's spending
+pub fn set_spend_cap_handler(ctx: Context<SetSpendCap>, max_spend_lamports: u64) -> Result<()> {
+    ctx.accounts.session_token.max_spend_lamports = max_spend_lamports;
+    Ok(())
+}
+
+// Refill a session signer's fee balance without re-authorizing
+// Usually the authority or the relayer that created the token pays, but any payer may; it's
+// their own lamports going to the token's signer.
+#[derive(Accounts)]
+pub struct TopUpSession<'info> {
+    #[account(
+        seeds = [
+            SessionToken::SEED_PREFIX.as_bytes(),
+            session_token.target_program.key().as_ref(),
+            session_token.session_signer.key().as_ref(),
+            session_token.authority.key().as_ref()
+        ],
+        bump = session_token.bump,
+        has_one = session_signer,
+    )]
+    pub session_token: Account<'info, SessionToken>,
+
+    /// CHECK: address checked by seeds, contents by `SessionConfig::load`
+    #[account(seeds = [SessionConfig::SEED_PREFIX.as_bytes()], bump)]
+    pub session_config: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub session_signer: SystemAccount<'info>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+// Handler to top up a session signer
+pub fn top_up_session_handler(ctx: Context<TopUpSession>, lamports: Option<u64>) -> Result<()> {
+    let config = SessionConfig::load(&ctx.accounts.session_config)?;
+    let session_token = &ctx.accounts.session_token;
+    // Don't strand lamports on a key that can no longer be used
+    require!(
+        !session_token.revoked && session_token.is_within_grace(Clock::get()?.unix_timestamp),
+        SessionError::InvalidToken
+    );
+    require_keys_neq!(
+        ctx.accounts.payer.key(),
+        ctx.accounts.session_signer.key(),
+        SessionError::PayerIsSessionSigner
+    );
+
+    system_program::transfer(
+        CpiContext::new(
+            ctx.accounts.system_program.to_account_info(),
+            system_program::Transfer {
+                from: ctx.accounts.payer.to_account_info(),
+                to: ctx.accounts.session_signer.to_account_info(),
+            },
+        ),
+        lamports.unwrap_or(config.policy.default_top_up_lamports),
+    )
+}
+
+// Keep-alive from the session signer itself
+// Lets clients create short-lived tokens and slide their expiry while the game is running;
+// once the client stops sending heartbeats the token lapses within minutes
+#[derive(Accounts)]
+pub struct Heartbeat<'info> {
+    #[account(
+        mut,
+        seeds = [
+            SessionToken::SEED_PREFIX.as_bytes(),
+            session_token.target_program.key().as_ref(),
+            session_token.session_signer.key().as_ref(),
+            session_token.authority.key().as_ref()
+        ],
+        bump = session_token.bump,
+        has_one = session_signer,
+    )]
+    pub session_token: Account<'info, SessionToken>,
+
+    /// CHECK: address checked by seeds, contents by `SessionConfig::load`
+    #[account(seeds = [SessionConfig::SEED_PREFIX.as_bytes()], bump)]
+    pub session_config: UncheckedAccount<'info>,
+
+    pub session_signer: Signer<'info>,
+}
+
+// Expiry a heartbeat at `now` leaves a token with: `now + HEARTBEAT_EXTENSION_SECS`, never
+// backwards and never past `created_at` plus the policy's longest validity (itself capped at
+// `MAX_VALIDITY_SECS`), so heartbeats can't outlast what the admin allows
+pub fn heartbeat_valid_until(
+    created_at: i64,
+    valid_until: i64,
+    now: i64,
+    policy: &ValidityPolicy,
+) -> i64 {
+    let max_validity_secs = policy.max_validity_secs.min(MAX_VALIDITY_SECS);
+    (now + HEARTBEAT_EXTENSION_SECS)
+        .min(created_at.saturating_add(max_validity_secs))
+        .max(valid_until)
+}
+
+// Handler for a heartbeat
+pub fn heartbeat_handler(ctx: Context<Heartbeat>) -> Result<()> {
+    let config = SessionConfig::load(&ctx.accounts.session_config)?;
+    let now = Clock::get()?.unix_timestamp;
+    let policy = config.policy;
+    let session_token = &mut ctx.accounts.session_token;
+    // A lapsed token stays dead; heartbeats only keep live ones going
+    require!(
+        !session_token.revoked && session_token.is_within_grace(now),
+        SessionError::InvalidToken
+    );
+
+    let old_valid_until = session_token.valid_until;
+    let new_valid_until =
+        heartbeat_valid_until(session_token.created_at, old_valid_until, now, &policy);
+    session_token.valid_until = new_valid_until;
+
+    emit!(SessionExtended {
+        authority: session_token.authority,
+        session_signer: session_token.session_signer,
+        target_program: session_token.target_program,
+        old_valid_until,
+        valid_until: new_valid_until,
+    });
+
+    Ok(())
+}
+
+// Record spending against a session token
+// Target programs CPI here (see `record_session_spend`) before acting on the session signer's
+// behalf; the session signer must sign, so nobody can burn another player's allowance.
+#[derive(Accounts)]
+pub struct RecordSpend<'info> {
+    #[account(
+        mut,
+        seeds = [
+            SessionToken::SEED_PREFIX.as_bytes(),
+            session_token.target_program.key().as_ref(),
+            session_token.session_signer.key().as_ref(),
+            session_token.authority.key().as_ref()
+        ],
+        bump = session_token.bump,
+        has_one = session_signer,
+    )]
+    pub session_token: Account<'info, SessionToken>,
+
+    pub session_signer: Signer<'info>,
+}
+
+// Handler to record spending
+pub fn record_spend_handler(ctx: Context<RecordSpend>, lamports: u64) -> Result<()> {
+    let session_token = &mut ctx.accounts.session_token;
+    let spent = session_token
+        .spent_lamports
+        .checked_add(lamports)
+        .ok_or(SessionError::SpendCapExceeded)?;
+    require!(
+        session_token.max_spend_lamports == 0 || spent <= session_token.max_spend_lamports,
+        SessionError::SpendCapExceeded
+    );
+    session_token.spent_lamports = spent;
+    Ok(())
+}
+
+// Validate a session token on a caller's behalf
+// For non-Anchor programs and other runtimes: they CPI here instead of vendoring the
+// `SessionToken` layout and read a `SessionValidation` from the return data. The caller passes
+// its own program id as `target_program` and compares the returned `authority` with the wallet
+// it expects; the session signer must sign, as for any session-authorized call.
+#[derive(Accounts)]
+pub struct ValidateSession<'info> {
+    pub session_token: Account<'info, SessionToken>,
+
+    pub session_signer: Signer<'info>,
+}
+
+// Result of `validate_session`, Borsh-encoded in the return data
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy)]
+pub struct SessionValidation {
+    pub valid: bool,
+    // `SessionError` code of the first failed check (as from `validate_strict`), 0 when valid
+    pub error_code: u32,
+    pub authority: Pubkey,
+    pub valid_until: i64,
+}
+
+// Handler to validate a session token
+// A failed check is reported in the return data rather than as an error, so the caller decides
+pub fn validate_session_handler(
+    ctx: Context<ValidateSession>,
+    target_program: Pubkey,
+    instruction: Option<[u8; 8]>,
+    scope: Option<Pubkey>,
+) -> Result<SessionValidation> {
+    let session_token = &ctx.accounts.session_token;
+    let checker = ValidityChecker {
+        session_token: session_token.clone(),
+        session_signer: ctx.accounts.session_signer.clone(),
+        authority: session_token.authority,
+        target_program,
+        instruction,
+        scope,
+    };
+    let error_code = match session_token.validate_strict(checker) {
+        Ok(()) => 0,
+        Err(Error::AnchorError(err)) => err.error_code_number,
+        Err(err) => return Err(err),
+    };
+    Ok(SessionValidation {
+        valid: error_code == 0,
+        error_code,
+        authority: session_token.authority,
+        valid_until: session_token.valid_until,
+    })
+}
+
+// Record `lamports` of spending on `session_token` from a target program
+// Fails with `SpendCapExceeded` once the cap would be passed, aborting the caller's instruction
+#[cfg(feature = "cpi")]
+pub fn record_session_spend<'info>(
+    session_program: AccountInfo<'info>,
+    session_token: AccountInfo<'info>,
+    session_signer: AccountInfo<'info>,
+    lamports: u64,
+) -> Result<()> {
+    cpi::record_spend(
+        CpiContext::new(
+            session_program,
+            cpi::accounts::RecordSpend {
+                session_token,
+                session_signer,
+            },
+        ),
+        lamports,
+    )
+}
+
+// Bind a session token to one account
+// A leaked key for one game run then can't touch the player's other state; the target
+// program tells `validate` which account an instruction acts on (`Session::session_scope`)
+#[derive(Accounts)]
+pub struct SetSessionScope<'info> {
+    #[account(
+        mut,
+        seeds = [
+            SessionToken::SEED_PREFIX.as_bytes(),
+            session_token.target_program.key().as_ref(),
+            session_token.session_signer.key().as_ref(),
+            session_token.authority.key().as_ref()
+        ],
+        bump = session_token.bump,
+        has_one = authority,
+    )]
+    pub session_token: Account<'info, SessionToken>,
+
+    pub authority: Signer<'info>,
+}
+
+// Handler to scope a session token to an account
+pub fn set_scope_handler(ctx: Context<SetSessionScope>, scope: Option<Pubkey>) -> Result<()> {
+    ctx.accounts.session_token.scope = scope;
+    Ok(())
+}
+
+// Set a session token's opaque metadata
+// The program never interprets it; games use it to audit sessions later
+#[derive(Accounts)]
+pub struct SetSessionMetadata<'info> {
+    #[account(
+        mut,
+        seeds = [
+            SessionToken::SEED_PREFIX.as_bytes(),
+            session_token.target_program.key().as_ref(),
+            session_token.session_signer.key().as_ref(),
+            session_token.authority.key().as_ref()
+        ],
+        bump = session_token.bump,
+        has_one = authority,
+    )]
+    pub session_token: Account<'info, SessionToken>,
+
+    pub authority: Signer<'info>,
+}
+
+// Handler to set a session token's metadata
+pub fn set_metadata_handler(ctx: Context<SetSessionMetadata>, metadata: [u8; 32]) -> Result<()> {
+    ctx.accounts.session_token.metadata = metadata;
+    Ok(())
+}
+
+// Designate a revoker for a session token
+// Lets e.g. a game server kill a player's session without holding the player's wallet
+#[derive(Accounts)]
+pub struct SetSessionRevoker<'info> {
+    #[account(
+        mut,
+        seeds = [
+            SessionToken::SEED_PREFIX.as_bytes(),
+            session_token.target_program.key().as_ref(),
+            session_token.session_signer.key().as_ref(),
+            session_token.authority.key().as_ref()
+        ],
+        bump = session_token.bump,
+        has_one = authority,
+    )]
+    pub session_token: Account<'info, SessionToken>,
+
+    pub authority: Signer<'info>,
+}
+
+// Handler to designate a revoker
+pub fn set_revoker_handler(ctx: Context<SetSessionRevoker>, revoker: Option<Pubkey>) -> Result<()> {
+    ctx.accounts.session_token.revoker = revoker;
+    Ok(())
+}
+
+// Re-parent a session token under a new authority
+// The authority is part of the PDA seeds, so the state is copied into the new authority's PDA
+// and the old account is closed; the session signer keeps working without re-issuing a key.
+// The new authority signs too, so nobody can push tokens (and their session count) onto a
+// wallet that didn't ask for them.
+#[derive(Accounts)]
+pub struct TransferSessionAuthority<'info> {
+    #[account(
+        mut,
+        seeds = [
+            SessionToken::SEED_PREFIX.as_bytes(),
+            session_token.target_program.key().as_ref(),
+            session_token.session_signer.key().as_ref(),
+            session_token.authority.key().as_ref()
+        ],
+        bump = session_token.bump,
+        has_one = authority,
+        close = authority,
+    )]
+    pub session_token: Account<'info, SessionToken>,
+
+    #[account(
+        init,
+        seeds = [
+            SessionToken::SEED_PREFIX.as_bytes(),
+            session_token.target_program.key().as_ref(),
+            session_token.session_signer.key().as_ref(),
+            new_authority.key().as_ref()
+        ],
+        bump,
+        payer = authority,
+        space = SessionToken::LEN
+    )]
+    pub new_session_token: Account<'info, SessionToken>,
+
+    #[account(
+        mut,
+        seeds = [AuthoritySessionCount::SEED_PREFIX.as_bytes(), authority.key().as_ref()],
+        bump
+    )]
+    pub session_count: Account<'info, AuthoritySessionCount>,
+
+    #[account(
+        init_if_needed,
+        seeds = [AuthoritySessionCount::SEED_PREFIX.as_bytes(), new_authority.key().as_ref()],
+        bump,
+        payer = authority,
+        space = AuthoritySessionCount::LEN
+    )]
+    pub new_session_count: Account<'info, AuthoritySessionCount>,
+
+    // Optional: when passed, the old token is dropped from the current authority's index
+    #[account(
+        mut,
+        seeds = [SessionIndex::SEED_PREFIX.as_bytes(), authority.key().as_ref()],
+        bump
+    )]
+    pub session_index: Option<Account<'info, SessionIndex>>,
+
+    // Optional: when passed, the new token is listed in the new authority's index
+    #[account(
+        mut,
+        seeds = [SessionIndex::SEED_PREFIX.as_bytes(), new_authority.key().as_ref()],
+        bump
+    )]
+    pub new_session_index: Option<Account<'info, SessionIndex>>,
+
+    /// CHECK: the session signer's lookup PDA, which only exists if a create call set it up;
+    /// repointed in the handler when it refers to the moved token
+    #[account(
+        mut,
+        seeds = [
+            SessionSignerLookup::SEED_PREFIX.as_bytes(),
+            session_token.session_signer.key().as_ref()
+        ],
+        bump
+    )]
+    pub signer_lookup: UncheckedAccount<'info>,
+
+    /// CHECK: address checked by seeds, contents by `SessionConfig::load`
+    #[account(seeds = [SessionConfig::SEED_PREFIX.as_bytes()], bump)]
+    pub session_config: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub new_authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+// Aim the signer's lookup at the token's new PDA if it pointed at the old one; a missing lookup
+// or one already aimed elsewhere is left alone
+fn move_signer_lookup(
+    signer_lookup: &AccountInfo,
+    old_token: &Pubkey,
+    new_token: &Pubkey,
+    new_authority: Pubkey,
+) -> Result<()> {
+    if *signer_lookup.owner != crate::ID || signer_lookup.data_is_empty() {
+        return Ok(());
+    }
+    let mut lookup =
+        SessionSignerLookup::try_deserialize(&mut &signer_lookup.try_borrow_data()?[..])?;
+    if lookup.session_token != *old_token {
+        return Ok(());
+    }
+    lookup.session_token = *new_token;
+    lookup.authority = new_authority;
+    lookup.try_serialize(&mut &mut signer_lookup.try_borrow_mut_data()?[..])
+}
+
+// Handler to re-parent a session token
+// Only live tokens move. Expiry, limits, scope and metadata carry over; the designated revoker
+// was chosen by the old wallet and is cleared.
+pub fn transfer_session_authority_handler(ctx: Context<TransferSessionAuthority>) -> Result<()> {
+    let config = SessionConfig::load(&ctx.accounts.session_config)?;
+    let new_authority = ctx.accounts.new_authority.key();
+    let old = &ctx.accounts.session_token;
+    require!(!old.revoked, SessionError::TokenRevoked);
+    old.check_expiry(Clock::get()?.unix_timestamp)?;
+
+    // Not a new session, so the pause doesn't apply, but the new authority's cap does
+    let new_count = &mut ctx.accounts.new_session_count;
+    require!(
+        new_count.count < config.max_sessions_per_authority as u16,
+        SessionError::TooManySessions
+    );
+    new_count.add(new_authority);
+    release_session(
+        &mut ctx.accounts.session_count,
+        ctx.accounts.session_index.as_deref_mut(),
+        &old.key(),
+    )?;
+
+    index_new_session(
+        &mut ctx.accounts.new_session_index,
+        ctx.accounts.new_session_token.key(),
+    )?;
+    move_signer_lookup(
+        &ctx.accounts.signer_lookup,
+        &old.key(),
+        &ctx.accounts.new_session_token.key(),
+        new_authority,
+    )?;
+
+    let mut token = (**old).clone();
+    token.authority = new_authority;
+    token.bump = ctx.bumps.new_session_token;
+    token.revoker = None;
+
+    emit!(old.revoked_event(true));
+    emit!(SessionCreated {
+        authority: new_authority,
+        session_signer: token.session_signer,
+        target_program: token.target_program,
+        valid_until: token.valid_until,
+    });
+
+    ctx.accounts.new_session_token.set_inner(token);
+    Ok(())
+}
+
+// Migrate a legacy session token
+// Legacy tokens no longer deserialize as `SessionToken`, so the account is read raw, grown to
+// `SessionToken::LEN` (the authority pays the extra rent) and rewritten in the current layout.
+// The token joins the authority's session count, so closing it later releases a real slot.
+// Under the fork's own program ID no account was ever written in the legacy layout; this only
+// does anything if the fork is deployed over an ID that holds upstream tokens.
+#[derive(Accounts)]
+pub struct MigrateSessionToken<'info> {
+    /// CHECK: owner is checked here; layout, PDA and authority in the handler
+    #[account(mut, owner = crate::id())]
+    pub session_token: UncheckedAccount<'info>,
+
+    #[account(
+        init_if_needed,
+        seeds = [AuthoritySessionCount::SEED_PREFIX.as_bytes(), authority.key().as_ref()],
+        bump,
+        payer = authority,
+        space = AuthoritySessionCount::LEN
+    )]
+    pub session_count: Account<'info, AuthoritySessionCount>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+// Handler to migrate a legacy session token
+pub fn migrate_session_handler(ctx: Context<MigrateSessionToken>) -> Result<()> {
+    let info = ctx.accounts.session_token.to_account_info();
+    let legacy = LegacySessionToken::try_deserialize(&info.try_borrow_data()?)?;
+
+    require_keys_eq!(
+        legacy.authority,
+        ctx.accounts.authority.key(),
+        SessionError::InvalidToken
+    );
+    let (pda, bump) = Pubkey::find_program_address(
+        &[
+            SessionToken::SEED_PREFIX.as_bytes(),
+            legacy.target_program.as_ref(),
+            legacy.session_signer.as_ref(),
+            legacy.authority.as_ref(),
+        ],
+        &crate::id(),
+    );
+    require_keys_eq!(pda, info.key(), SessionError::InvalidToken);
+
+    let rent_due = Rent::get()?
+        .minimum_balance(SessionToken::LEN)
+        .saturating_sub(info.lamports());
+    if rent_due > 0 {
+        system_program::transfer(
+            CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                system_program::Transfer {
+                    from: ctx.accounts.authority.to_account_info(),
+                    to: info.clone(),
+                },
+            ),
+            rent_due,
+        )?;
+    }
+    info.resize(SessionToken::LEN)?;
+
+    // Already live, so it is counted even past the cap; refusing would strand the token
+    ctx.accounts.session_count.add(legacy.authority);
+
+    let mut token = SessionToken::from(legacy);
+    token.created_at = Clock::get()?.unix_timestamp;
+    token.bump = bump;
+    let mut data = info.try_borrow_mut_data()?;
+    token.try_serialize(&mut &mut data[..])?;
+    Ok(())
+}
+
+// Revoke a session token
+// Who may close a token is decided by `can_revoke`: the authority, its designated revoker and
+// the config admin at any time, anyone else only once the token is dead. This closes the old enumerate-and-revoke
+// griefing vector while keeping cleanup of dead tokens permissionless.
+#[derive(Accounts)]
+pub struct RevokeSessionToken<'info> {
+    #[account(
+        mut,
+        seeds = [
+            SessionToken::SEED_PREFIX.as_bytes(),
+            session_token.target_program.key().as_ref(),
+            session_token.session_signer.key().as_ref(),
+            session_token.authority.key().as_ref()
+        ],
+        bump = session_token.bump,
+        has_one = authority,
+        close = authority,
+    )]
+    pub session_token: Account<'info, SessionToken>,
+
+    #[account(
+        mut,
+        seeds = [AuthoritySessionCount::SEED_PREFIX.as_bytes(), authority.key().as_ref()],
+        bump
+    )]
+    pub session_count: Account<'info, AuthoritySessionCount>,
+
+    // Optional: when passed, the closed token is dropped from the authority's index
+    #[account(
+        mut,
+        seeds = [SessionIndex::SEED_PREFIX.as_bytes(), authority.key().as_ref()],
+        bump
+    )]
+    pub session_index: Option<Account<'info, SessionIndex>>,
+
+    // Optional: when passed and still pointing at this token, the signer lookup is closed too
+    #[account(
+        mut,
+        seeds = [
+            SessionSignerLookup::SEED_PREFIX.as_bytes(),
+            session_token.session_signer.key().as_ref()
+        ],
+        bump,
+        constraint = signer_lookup.session_token == session_token.key() @ SessionError::InvalidToken,
+        close = authority,
+    )]
+    pub signer_lookup: Option<Account<'info, SessionSignerLookup>>,
+
+    /// CHECK: address checked by seeds, contents by `SessionConfig::load`
+    #[account(seeds = [SessionConfig::SEED_PREFIX.as_bytes()], bump)]
+    pub session_config: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    // Only the token authority can reclaim the rent
+    pub authority: SystemAccount<'info>,
+
+    // Whoever asks for the revocation; checked by `can_revoke`
+    pub revoker: Signer<'info>,
+
+    // Optional co-signature from the session key; when present its whole balance (leftover
+    // top-up) is swept back to the authority
+    #[account(mut, address = session_token.session_signer @ SessionError::InvalidToken)]
+    pub session_signer: Option<Signer<'info>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+// Authorization matrix for closing a token:
+// - the token authority: always
+// - the token's designated `revoker`, if any: always
+// - the config admin: always (force-revoke, e.g. for a leaked signer)
+// - anyone else: only a soft-revoked token or one past `valid_until` and its grace period;
+//   `NotAuthorizedToRevoke` while the token is live, `TokenStillValid` while it is expired but
+//   still inside the grace period (retry after it)
+pub fn can_revoke(
+    token: &SessionToken,
+    signer: &Pubkey,
+    config: &SessionConfig,
+    now: i64,
+) -> Result<()> {
+    if *signer == token.authority
+        || token.revoker == Some(*signer)
+        || *signer == config.admin
+        || token.revoked
+    {
+        return Ok(());
+    }
+    require!(now >= token.valid_until, SessionError::NotAuthorizedToRevoke);
+    require!(!token.is_within_grace(now), SessionError::TokenStillValid);
+    Ok(())
+}
+
+// Handler to revoke a session token
+pub fn revoke_session_token_handler(ctx: Context<RevokeSessionToken>) -> Result<()> {
+    let config = SessionConfig::load(&ctx.accounts.session_config)?;
+    can_revoke(
+        &ctx.accounts.session_token,
+        ctx.accounts.revoker.key,
+        &config,
+        Clock::get()?.unix_timestamp,
+    )?;
+    release_session(
+        &mut ctx.accounts.session_count,
+        ctx.accounts.session_index.as_deref_mut(),
+        &ctx.accounts.session_token.key(),
+    )?;
+    emit!(ctx.accounts.session_token.revoked_event(true));
+
+    // Refund the unused top-up so it isn't stranded on a key nobody will use again
+    if let Some(session_signer) = &ctx.accounts.session_signer {
+        let lamports = session_signer.lamports();
+        if lamports > 0 {
+            system_program::transfer(
+                CpiContext::new(
+                    ctx.accounts.system_program.to_account_info(),
+                    system_program::Transfer {
+                        from: session_signer.to_account_info(),
+                        to: ctx.accounts.authority.to_account_info(),
+                    },
+                ),
+                lamports,
+            )?;
+        }
+    }
+    Ok(())
+}
+
+// Revoke all of an authority's session tokens at once ("panic button")
+// `remaining_accounts` holds `[session_token, signer_lookup]` pairs, all writable; each token
+// must belong to the signing authority or the whole transaction fails, so a client can't close
+// someone else's session. Pass the system program as the lookup of a signer without one.
+#[derive(Accounts)]
+pub struct RevokeAllSessionTokens<'info> {
+    #[account(
+        mut,
+        seeds = [AuthoritySessionCount::SEED_PREFIX.as_bytes(), authority.key().as_ref()],
+        bump
+    )]
+    pub session_count: Account<'info, AuthoritySessionCount>,
+
+    // Optional: when passed, the closed token is dropped from the authority's index
+    #[account(
+        mut,
+        seeds = [SessionIndex::SEED_PREFIX.as_bytes(), authority.key().as_ref()],
+        bump
+    )]
+    pub session_index: Option<Account<'info, SessionIndex>>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+}
+
+// Handler to revoke all passed session tokens
+pub fn revoke_all_handler<'info>(
+    ctx: Context<'_, '_, 'info, 'info, RevokeAllSessionTokens<'info>>,
+) -> Result<()> {
+    let authority = ctx.accounts.authority.to_account_info();
+    let pairs = ctx.remaining_accounts.chunks_exact(2);
+    require!(pairs.remainder().is_empty(), SessionError::InvalidBatch);
+    for pair in pairs {
+        let (token_info, lookup_info) = (&pair[0], &pair[1]);
+        // Owner and discriminator are checked here; only this program writes SessionTokens,
+        // so a matching authority is enough to know the token is the authority's
+        let session_token = Account::<SessionToken>::try_from(token_info)?;
+        require_keys_eq!(
+            session_token.authority,
+            authority.key(),
+            SessionError::InvalidToken
+        );
+        release_session(
+            &mut ctx.accounts.session_count,
+            ctx.accounts.session_index.as_deref_mut(),
+            token_info.key,
+        )?;
+        if let Some(lookup) = lookup_to_close(lookup_info, token_info.key)? {
+            lookup.close(authority.clone())?;
+        }
+        emit!(session_token.revoked_event(true));
+        session_token.close(authority.clone())?;
+    }
+    Ok(())
+}
+
+// Soft-revoke a session token
+// Unlike `revoke_session` the account stays open, so the revocation is visible on-chain (and to
+// anything that cached the token) until the authority closes it to reclaim the rent.
+#[derive(Accounts)]
+pub struct SoftRevokeSessionToken<'info> {
+    #[account(
+        mut,
+        seeds = [
+            SessionToken::SEED_PREFIX.as_bytes(),
+            session_token.target_program.key().as_ref(),
+            session_token.session_signer.key().as_ref(),
+            session_token.authority.key().as_ref()
+        ],
+        bump = session_token.bump,
+        has_one = authority,
+    )]
+    pub session_token: Account<'info, SessionToken>,
+
+    pub authority: Signer<'info>,
+}
+
+// Handler to soft-revoke a session token
+pub fn soft_revoke_handler(ctx: Context<SoftRevokeSessionToken>) -> Result<()> {
+    ctx.accounts.session_token.revoked = true;
+    emit!(ctx.accounts.session_token.revoked_event(false));
+    Ok(())
+}
+
+// Close an expired session token
+// Permissionless: once a token is past `valid_until` (and its grace period) it is dead weight,
+// so anyone may clean it up. Rent always goes back to the token authority, never to the caller.
+#[derive(Accounts)]
+pub struct CrankExpiredSessionToken<'info> {
+    #[account(
+        mut,
+        seeds = [
+            SessionToken::SEED_PREFIX.as_bytes(),
+            session_token.target_program.key().as_ref(),
+            session_token.session_signer.key().as_ref(),
+            session_token.authority.key().as_ref()
+        ],
+        bump = session_token.bump,
+        has_one = authority,
+        close = authority,
+    )]
+    pub session_token: Account<'info, SessionToken>,
+
+    #[account(
+        mut,
+        seeds = [AuthoritySessionCount::SEED_PREFIX.as_bytes(), authority.key().as_ref()],
+        bump
+    )]
+    pub session_count: Account<'info, AuthoritySessionCount>,
+
+    // Optional: when passed, the closed token is dropped from the authority's index
+    #[account(
+        mut,
+        seeds = [SessionIndex::SEED_PREFIX.as_bytes(), authority.key().as_ref()],
+        bump
+    )]
+    pub session_index: Option<Account<'info, SessionIndex>>,
+
+    // Optional: when passed and still pointing at this token, the signer lookup is closed too
+    #[account(
+        mut,
+        seeds = [
+            SessionSignerLookup::SEED_PREFIX.as_bytes(),
+            session_token.session_signer.key().as_ref()
+        ],
+        bump,
+        constraint = signer_lookup.session_token == session_token.key() @ SessionError::InvalidToken,
+        close = authority,
+    )]
+    pub signer_lookup: Option<Account<'info, SessionSignerLookup>>,
+
+    #[account(mut)]
+    // Rent is returned to the token authority
+    pub authority: SystemAccount<'info>,
+}
+
+// Handler to close an expired session token
+pub fn crank_expired_handler(ctx: Context<CrankExpiredSessionToken>) -> Result<()> {
+    // Wait out the grace period so a token can't be closed under an in-flight transaction
+    require!(
+        !ctx.accounts.session_token.is_within_grace(Clock::get()?.unix_timestamp),
+        SessionError::TokenNotExpired
+    );
+    release_session(
+        &mut ctx.accounts.session_count,
+        ctx.accounts.session_index.as_deref_mut(),
+        &ctx.accounts.session_token.key(),
+    )?;
+    emit!(ctx.accounts.session_token.revoked_event(true));
+    Ok(())
+}
+
+// Sweep expired session tokens in bulk
+// `remaining_accounts` holds `[session_token, authority, session_count, session_index,
+// signer_lookup]` groups, all writable; pass the system program for an index or lookup the
+// authority doesn't have. Same rule and cleanup as `crank_expired`: only tokens past
+// `valid_until` and their grace period, rent back to each token's authority.
+#[derive(Accounts)]
+pub struct CloseExpiredSessions {}
+
+// Handler to sweep expired session tokens
+pub fn close_expired_session_handler<'info>(
+    ctx: Context<'_, '_, 'info, 'info, CloseExpiredSessions>,
+) -> Result<()> {
+    let groups = ctx.remaining_accounts.chunks_exact(5);
+    require!(
+        groups.remainder().is_empty() && groups.len() > 0,
+        SessionError::InvalidBatch
+    );
+
+    let now = Clock::get()?.unix_timestamp;
+    for group in groups {
+        let [token_info, authority, count_info, index_info, lookup_info] = group else {
+            unreachable!()
+        };
+
+        let session_token = Account::<SessionToken>::try_from(token_info)?;
+        require_keys_eq!(
+            session_token.authority,
+            *authority.key,
+            SessionError::InvalidToken
+        );
+        require!(
+            !session_token.is_within_grace(now),
+            SessionError::TokenNotExpired
+        );
+
+        let mut session_count = Account::<AuthoritySessionCount>::try_from(count_info)?;
+        require_keys_eq!(
+            session_count.authority,
+            *authority.key,
+            SessionError::InvalidToken
+        );
+        let mut session_index = if *index_info.owner == crate::ID {
+            let session_index = Account::<SessionIndex>::try_from(index_info)?;
+            require_keys_eq!(
+                session_index.authority,
+                *authority.key,
+                SessionError::InvalidToken
+            );
+            Some(session_index)
+        } else {
+            None
+        };
+        release_session(
+            &mut session_count,
+            session_index.as_deref_mut(),
+            token_info.key,
+        )?;
+        session_count.exit(&crate::id())?;
+        if let Some(session_index) = &session_index {
+            session_index.exit(&crate::id())?;
+        }
+        if let Some(lookup) = lookup_to_close(lookup_info, token_info.key)? {
+            lookup.close(authority.clone())?;
+        }
+
+        emit!(session_token.revoked_event(true));
+        session_token.close(authority.clone())?;
+    }
+    Ok(())
+}
+
+// Delegate a session token to the Ephemeral Rollup
+// The token then validates inside the ER without L1 reads (e.g. on the `update_stats` path).
+// While delegated its owner is the delegation program, so L1 instructions that take it
+// (revoke, refresh, ...) fail until `undelegate_session` brings it back.
+#[delegate]
+#[derive(Accounts)]
+pub struct DelegateSessionToken<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    /// CHECK: deserialized and matched against `authority` in the handler
+    #[account(mut, del, owner = crate::id())]
+    pub session_token: AccountInfo<'info>,
+}
+
+// Handler to delegate a session token
+pub fn delegate_session_handler(ctx: Context<DelegateSessionToken>) -> Result<()> {
+    let token = SessionToken::try_deserialize(&mut &ctx.accounts.session_token.data.borrow()[..])?;
+    require_keys_eq!(
+        token.authority,
+        ctx.accounts.authority.key(),
+        SessionError::InvalidToken
+    );
+
+    ctx.accounts.delegate_session_token(
+        &ctx.accounts.authority,
+        &[
+            SessionToken::SEED_PREFIX.as_bytes(),
+            token.target_program.as_ref(),
+            token.session_signer.as_ref(),
+            token.authority.as_ref(),
+        ],
+        DelegateConfig::default(),
+    )?;
+    Ok(())
+}
+
+// Undelegate a session token, committing its ER state (e.g. `spent_lamports`) to L1
+#[commit]
+#[derive(Accounts)]
+pub struct UndelegateSessionToken<'info> {
+    #[account(
+        mut,
+        seeds = [
+            SessionToken::SEED_PREFIX.as_bytes(),
+            session_token.target_program.key().as_ref(),
+            session_token.session_signer.key().as_ref(),
+            session_token.authority.key().as_ref()
+        ],
+        bump = session_token.bump,
+        has_one = authority,
+    )]
+    pub session_token: Account<'info, SessionToken>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+}
+
+// Handler to undelegate a session token
+pub fn undelegate_session_handler(ctx: Context<UndelegateSessionToken>) -> Result<()> {
+    commit_and_undelegate_accounts(
+        &ctx.accounts.authority,
+        vec![&ctx.accounts.session_token.to_account_info()],
+        &ctx.accounts.magic_context,
+        &ctx.accounts.magic_program,
+    )?;
+    Ok(())
+}
+
+// Create an authority's SessionIndex
+// Opt-in: tokens created before it exists (or without passing it) are simply not listed
+#[derive(Accounts)]
+pub struct InitializeSessionIndex<'info> {
+    #[account(
+        init,
+        seeds = [SessionIndex::SEED_PREFIX.as_bytes(), authority.key().as_ref()],
+        bump,
+        payer = authority,
+        space = SessionIndex::LEN
+    )]
+    pub session_index: Account<'info, SessionIndex>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+// Handler to create a SessionIndex
+pub fn initialize_session_index_handler(ctx: Context<InitializeSessionIndex>) -> Result<()> {
+    ctx.accounts.session_index.set_inner(SessionIndex {
+        authority: ctx.accounts.authority.key(),
+        sessions: Vec::new(),
+    });
+    Ok(())
+}
+
+// Create the program-wide SessionConfig
+// Only the program's upgrade authority may create it; it becomes the config admin.
+#[derive(Accounts)]
+pub struct InitializeSessionConfig<'info> {
+    #[account(
+        init,
+        seeds = [SessionConfig::SEED_PREFIX.as_bytes()],
+        bump,
+        payer = admin,
+        space = SessionConfig::LEN
+    )]
+    pub session_config: Account<'info, SessionConfig>,
+
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    #[account(constraint = program.programdata_address()? == Some(program_data.key()))]
+    pub program: Program<'info, crate::program::GplSession>,
+
+    #[account(
+        constraint = program_data.upgrade_authority_address == Some(admin.key())
+            @ SessionError::NotUpgradeAuthority
+    )]
+    pub program_data: Account<'info, ProgramData>,
+
+    pub system_program: Program<'info, System>,
+}
+
+// Handler to create the SessionConfig
+pub fn initialize_config_handler(
+    ctx: Context<InitializeSessionConfig>,
+    max_sessions_per_authority: u8,
+) -> Result<()> {
+    ctx.accounts.session_config.set_inner(SessionConfig {
+        admin: ctx.accounts.admin.key(),
+        max_sessions_per_authority,
+        policy: ValidityPolicy::default(),
+        freeze_authority: ctx.accounts.admin.key(),
+        paused: false,
+    });
+    Ok(())
+}
+
+// Update the SessionConfig
+#[derive(Accounts)]
+pub struct UpdateSessionConfig<'info> {
+    #[account(
+        mut,
+        seeds = [SessionConfig::SEED_PREFIX.as_bytes()],
+        bump,
+        has_one = admin,
+    )]
+    pub session_config: Account<'info, SessionConfig>,
+
+    pub admin: Signer<'info>,
+}
+
+// Handler to update the SessionConfig
+// Lowering the cap never closes live tokens; it only blocks new ones until enough expire
+pub fn update_config_handler(
+    ctx: Context<UpdateSessionConfig>,
+    max_sessions_per_authority: u8,
+) -> Result<()> {
+    ctx.accounts.session_config.max_sessions_per_authority = max_sessions_per_authority;
+    Ok(())
+}
+
+// Handler to change the validity policy
+// Only affects tokens created, refreshed or extended afterwards
+pub fn set_validity_policy_handler(
+    ctx: Context<UpdateSessionConfig>,
+    policy: ValidityPolicy,
+) -> Result<()> {
+    require!(policy.is_consistent(), SessionError::InvalidValidityPolicy);
+    ctx.accounts.session_config.policy = policy;
+    Ok(())
+}
+
+// Handler to change the freeze authority
+pub fn set_freeze_authority_handler(
+    ctx: Context<UpdateSessionConfig>,
+    freeze_authority: Pubkey,
+) -> Result<()> {
+    ctx.accounts.session_config.freeze_authority = freeze_authority;
+    Ok(())
+}
+
+// Pause or resume session creation
+// Emergency switch for an exploit: it halts every create_session* path without a program
+// upgrade. Revoking, closing and validating existing tokens are never affected.
+#[derive(Accounts)]
+pub struct SetSessionPause<'info> {
+    #[account(
+        mut,
+        seeds = [SessionConfig::SEED_PREFIX.as_bytes()],
+        bump,
+        has_one = freeze_authority,
+    )]
+    pub session_config: Account<'info, SessionConfig>,
+
+    pub freeze_authority: Signer<'info>,
+}
+
+// Handler to pause or resume session creation
+pub fn set_paused_handler(ctx: Context<SetSessionPause>, paused: bool) -> Result<()> {
+    ctx.accounts.session_config.paused = paused;
+    Ok(())
+}
+
+pub struct ValidityChecker<'info> {
+    pub session_token: Account<'info, SessionToken>,
+    pub session_signer: Signer<'info>,
+    pub authority: Pubkey,
+    pub target_program: Pubkey,
+    // Discriminator of the instruction being authorized; `None` only passes unscoped tokens
+    pub instruction: Option<[u8; 8]>,
+    // Account the instruction acts on; `None` only passes tokens without a `scope`
+    pub scope: Option<Pubkey>,
+}
+
+// SessionToken Account
+// New fields are appended and bump `VERSION`; tokens in the original upstream layout are
+// upgraded in place with `migrate_session` (see `LegacySessionToken`)
+#[account]
+#[derive(InitSpace)]
+pub struct SessionToken {
+    pub authority: Pubkey,
+    pub target_program: Pubkey,
+    pub session_signer: Pubkey,
     pub valid_until: i64,
+    pub revoked: bool,
+    // Target program instructions the token may authorize; empty means all of them
+    #[max_len(MAX_ALLOWED_INSTRUCTIONS)]
+    pub allowed_instructions: Vec<[u8; 8]>,
+    // Extra key allowed to revoke the token while it is live, set by the authority
+    pub revoker: Option<Pubkey>,
+    // Programs the token covers besides `target_program`
+    #[max_len(MAX_TARGET_PROGRAMS - 1)]
+    pub extra_target_programs: Vec<Pubkey>,
+    // Spending cap in lamports recorded through `record_spend`; 0 means no cap
+    pub max_spend_lamports: u64,
+    pub spent_lamports: u64,
+    // Layout version the token was written with, `SessionToken::VERSION` for new tokens
+    pub version: u8,
+    pub grace: GraceConfig,
+    // Creation time; `heartbeat` never keeps a token alive past `created_at` plus the config's
+    // `max_validity_secs`
+    pub created_at: i64,
+    // Only account this token may act on, if set
+    pub scope: Option<Pubkey>,
+    // Canonical PDA bump, stored so validation skips `find_program_address`
+    pub bump: u8,
+    // Opaque bytes set by the authority (client version, device fingerprint hash, ...); zeroed if unset
+    pub metadata: [u8; 32],
+}
+
+// How long past `valid_until` a token keeps validating
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, InitSpace)]
+pub struct GraceConfig {
+    // Soft-expiry window for transactions signed just before `valid_until`
+    pub grace_secs: u16,
+    // Extra slack for clock drift between validators (e.g. an ephemeral rollup lagging L1)
+    pub clock_tolerance_secs: u16,
+}
+
+impl Default for GraceConfig {
+    fn default() -> Self {
+        GraceConfig {
+            grace_secs: EXPIRY_GRACE_SECS as u16,
+            clock_tolerance_secs: 0,
+        }
+    }
+}
+
+impl GraceConfig {
+    // Total seconds past `valid_until` during which the token still validates
+    pub fn window_secs(&self) -> i64 {
+        self.grace_secs as i64 + self.clock_tolerance_secs as i64
+    }
 }
 
-impl SessionToken {
-    pub const LEN: usize = 8 + std::mem::size_of::<Self>();
-    pub const SEED_PREFIX: &'static str = "session_token";
+impl SessionToken {
+    pub const LEN: usize = 8 + Self::INIT_SPACE;
+    pub const SEED_PREFIX: &'static str = "session_token";
+    pub const VERSION: u8 = 6;
+
+    // A fresh, unrestricted token at the current layout version
+    pub fn new(
+        authority: Pubkey,
+        target_program: Pubkey,
+        session_signer: Pubkey,
+        valid_until: i64,
+        created_at: i64,
+        bump: u8,
+    ) -> Self {
+        SessionToken {
+            authority,
+            target_program,
+            session_signer,
+            valid_until,
+            revoked: false,
+            allowed_instructions: Vec::new(),
+            revoker: None,
+            extra_target_programs: Vec::new(),
+            max_spend_lamports: 0,
+            spent_lamports: 0,
+            version: SessionToken::VERSION,
+            grace: GraceConfig::default(),
+            created_at,
+            scope: None,
+            bump,
+            metadata: [0; 32],
+        }
+    }
+
+    // true once `valid_until` has passed; what UIs should show, ignoring the grace period
+    pub fn is_expired(&self) -> Result<bool> {
+        let now = Clock::get()?.unix_timestamp;
+        Ok(now >= self.valid_until)
+    }
+
+    // true while the token still validates: before `valid_until` plus its grace window
+    pub fn is_within_grace(&self, now: i64) -> bool {
+        now < self.valid_until.saturating_add(self.grace.window_secs())
+    }
+
+    // `SessionExpired` once the grace window has passed
+    pub fn check_expiry(&self, now: i64) -> Result<()> {
+        require!(self.is_within_grace(now), SessionError::SessionExpired);
+        Ok(())
+    }
+
+    // Lifecycle event for this token going away (`closed`) or being soft-revoked
+    pub fn revoked_event(&self, closed: bool) -> SessionRevoked {
+        SessionRevoked {
+            authority: self.authority,
+            session_signer: self.session_signer,
+            target_program: self.target_program,
+            valid_until: self.valid_until,
+            closed,
+        }
+    }
+
+    // Move expiry to `valid_until`, returning the event recording the old and new expiry
+    pub fn refresh(&mut self, valid_until: i64) -> SessionRefreshed {
+        let old_valid_until = self.valid_until;
+        self.valid_until = valid_until;
+        SessionRefreshed {
+            authority: self.authority,
+            session_signer: self.session_signer,
+            old_valid_until,
+            new_valid_until: valid_until,
+        }
+    }
+
+    // true once a capped token has spent its whole allowance
+    pub fn is_spend_exhausted(&self) -> bool {
+        self.max_spend_lamports != 0 && self.spent_lamports >= self.max_spend_lamports
+    }
 
-    fn is_expired(&self) -> Result<bool> {
-        let now = Clock::get()?.unix_timestamp;
-        Ok(now < self.valid_until)
+    // true if the token may be used by `program`
+    pub fn covers_program(&self, program: &Pubkey) -> bool {
+        self.target_program == *program || self.extra_target_programs.contains(program)
+    }
+
+    // true if the token may act on `scope`; a bound token fails closed on `None`
+    pub fn allows_scope(&self, scope: Option<Pubkey>) -> bool {
+        self.scope.is_none() || self.scope == scope
+    }
+
+    // true if the token may authorize `instruction`; a scoped token fails closed on `None`
+    pub fn allows_instruction(&self, instruction: Option<[u8; 8]>) -> bool {
+        self.allowed_instructions.is_empty()
+            || instruction.is_some_and(|ix| self.allowed_instructions.contains(&ix))
     }
 
     // validate the token
+    // Expiry is an error (`SessionExpired`) rather than `Ok(false)`, so callers can tell an
+    // expired session apart from a revoked, exhausted or out-of-scope one
     pub fn validate(&self, ctx: ValidityChecker) -> Result<bool> {
-        let target_program = ctx.target_program;
-        let session_signer = ctx.session_signer.key();
-        let authority = ctx.authority.key();
+        // The calling program must be the primary target or one of the extras
+        require!(
+            self.covers_program(&ctx.target_program),
+            SessionError::InvalidToken
+        );
+
+        // Check the PDA seeds; they always use the primary target program
+        require!(
+            self.session_signer == ctx.session_signer.key()
+                && self.authority == ctx.authority
+                && self.address() == Some(ctx.session_token.key()),
+            SessionError::InvalidToken
+        );
+
+        // A soft-revoked token is dead even before it expires
+        if self.revoked {
+            return Ok(false);
+        }
+
+        // A token that spent its cap can't authorize anything more
+        if self.is_spend_exhausted() {
+            return Ok(false);
+        }
+
+        // A scoped token only covers its listed instructions
+        if !self.allows_instruction(ctx.instruction) {
+            return Ok(false);
+        }
+
+        // A bound token only acts on its own account
+        if !self.allows_scope(ctx.scope) {
+            return Ok(false);
+        }
+
+        // Check if the token is still usable (soft expiry: grace period past `valid_until`)
+        self.check_expiry(Clock::get()?.unix_timestamp)?;
+        Ok(true)
+    }
 
-        // Check the PDA seeds
+    // validate the token, failing with the specific reason instead of `Ok(false)`
+    // Same checks as `validate`; prefer this in new code
+    pub fn validate_strict(&self, ctx: ValidityChecker) -> Result<()> {
+        self.validate_keys(
+            &ctx.session_token.key(),
+            &ctx.session_signer.key(),
+            &ctx.authority,
+            &ctx.target_program,
+            ctx.instruction,
+            ctx.scope,
+        )
+    }
+
+    // `validate_strict` over plain keys, for callers holding raw `AccountInfo`s (e.g. Bolt
+    // systems reading the token from remaining accounts); the caller must have checked that
+    // `session_signer` signed the transaction and that the token account is owned by this program
+    pub fn validate_keys(
+        &self,
+        session_token: &Pubkey,
+        session_signer: &Pubkey,
+        authority: &Pubkey,
+        target_program: &Pubkey,
+        instruction: Option<[u8; 8]>,
+        scope: Option<Pubkey>,
+    ) -> Result<()> {
+        require!(
+            self.covers_program(target_program),
+            SessionError::WrongProgram
+        );
+        require_keys_eq!(
+            self.session_signer,
+            *session_signer,
+            SessionError::WrongSigner
+        );
+        require!(
+            self.authority == *authority && self.address() == Some(*session_token),
+            SessionError::InvalidPda
+        );
+        require!(!self.revoked, SessionError::TokenRevoked);
+        require!(!self.is_spend_exhausted(), SessionError::SpendCapExceeded);
+        require!(
+            self.allows_instruction(instruction),
+            SessionError::InstructionNotAllowed
+        );
+        require!(self.allows_scope(scope), SessionError::WrongScope);
+        self.check_expiry(Clock::get()?.unix_timestamp)
+    }
+
+    // The PDA this token must live at, derived from its own fields and stored bump
+    // `create_program_address` is a single hash, unlike the bump search in `find_program_address`;
+    // `None` if the bump doesn't give a valid PDA
+    pub fn address(&self) -> Option<Pubkey> {
         let seeds = &[
             SessionToken::SEED_PREFIX.as_bytes(),
-            target_program.as_ref(),
-            session_signer.as_ref(),
-            authority.as_ref(),
+            self.target_program.as_ref(),
+            self.session_signer.as_ref(),
+            self.authority.as_ref(),
+            &[self.bump],
         ];
+        Pubkey::create_program_address(seeds, &crate::id()).ok()
+    }
+}
+
+// The original upstream SessionToken layout (version 0), kept to read tokens for migration
+// It shares the `SessionToken` discriminator and is told apart by its exact size
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy)]
+pub struct LegacySessionToken {
+    pub authority: Pubkey,
+    pub target_program: Pubkey,
+    pub session_signer: Pubkey,
+    pub valid_until: i64,
+}
+
+impl LegacySessionToken {
+    pub const LEN: usize = 8 + 32 + 32 + 32 + 8;
+
+    pub fn try_deserialize(data: &[u8]) -> Result<Self> {
+        require_eq!(data.len(), Self::LEN, SessionError::NotLegacyToken);
+        require!(
+            &data[..8] == SessionToken::DISCRIMINATOR,
+            SessionError::InvalidToken
+        );
+        Ok(Self::deserialize(&mut &data[8..])?)
+    }
+}
+
+impl From<LegacySessionToken> for SessionToken {
+    fn from(legacy: LegacySessionToken) -> Self {
+        SessionToken::new(
+            legacy.authority,
+            legacy.target_program,
+            legacy.session_signer,
+            legacy.valid_until,
+            // Unknown for legacy tokens; `migrate_session` stamps the migration time
+            0,
+            // Legacy tokens never stored it; `migrate_session` fills in the canonical bump
+            0,
+        )
+    }
+}
+
+// Program-wide settings, one PDA per deployment
+#[account]
+pub struct SessionConfig {
+    pub admin: Pubkey,
+    pub max_sessions_per_authority: u8,
+    pub policy: ValidityPolicy,
+    // Key that may pause session creation; the admin at initialization
+    pub freeze_authority: Pubkey,
+    // While set, every create_session* instruction fails with `CreationPaused`
+    pub paused: bool,
+}
+
+impl SessionConfig {
+    pub const LEN: usize = 8 + 32 + 1 + ValidityPolicy::INIT_SPACE + 32 + 1;
+    pub const SEED_PREFIX: &'static str = "session_config";
+
+    // The config at `info`, or the defaults while `initialize_config` hasn't run
+    // Sessions work from deployment on; the config account only tightens or loosens them
+    pub fn load(info: &AccountInfo) -> Result<Self> {
+        if info.data_is_empty() {
+            return Ok(Self::default());
+        }
+        require_keys_eq!(*info.owner, crate::ID, ErrorCode::AccountOwnedByWrongProgram);
+        Self::try_deserialize(&mut &info.try_borrow_data()?[..])
+    }
+}
+
+// Settings before `initialize_config`: nobody holds the admin or freeze role yet
+impl Default for SessionConfig {
+    fn default() -> Self {
+        SessionConfig {
+            admin: Pubkey::default(),
+            max_sessions_per_authority: DEFAULT_MAX_SESSIONS_PER_AUTHORITY,
+            policy: ValidityPolicy::default(),
+            freeze_authority: Pubkey::default(),
+            paused: false,
+        }
+    }
+}
+
+// Bounds and defaults for new tokens, set by the config admin
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, InitSpace)]
+pub struct ValidityPolicy {
+    // Shortest allowed `valid_until - now`
+    pub min_validity_secs: i64,
+    // Longest allowed `valid_until - now`, at most `MAX_VALIDITY_SECS`
+    pub max_validity_secs: i64,
+    // Validity when the caller passes no `valid_until`
+    pub default_validity_secs: i64,
+    // Top-up when the caller asks for one without an amount
+    pub default_top_up_lamports: u64,
+}
+
+impl Default for ValidityPolicy {
+    fn default() -> Self {
+        ValidityPolicy {
+            min_validity_secs: DEFAULT_MIN_VALIDITY_SECS,
+            max_validity_secs: MAX_VALIDITY_SECS,
+            default_validity_secs: 60 * 60,
+            default_top_up_lamports: LAMPORTS_PER_SOL / 100,
+        }
+    }
+}
+
+impl ValidityPolicy {
+    // 0 < min <= default <= max <= MAX_VALIDITY_SECS
+    pub fn is_consistent(&self) -> bool {
+        0 < self.min_validity_secs
+            && self.min_validity_secs <= self.default_validity_secs
+            && self.default_validity_secs <= self.max_validity_secs
+            && self.max_validity_secs <= MAX_VALIDITY_SECS
+    }
+}
+
+// Sponsorship terms of one fee payer for `create_session_with_payer`
+#[account]
+#[derive(InitSpace)]
+pub struct SponsorConfig {
+    pub sponsor: Pubkey,
+    // Target programs the sponsor pays sessions for
+    #[max_len(MAX_SPONSORED_PROGRAMS)]
+    pub target_programs: Vec<Pubkey>,
+    // Token rent plus top-ups the sponsor pays per UTC day
+    pub daily_budget_lamports: u64,
+    // Budget period `spent_lamports` belongs to
+    pub day: i64,
+    pub spent_lamports: u64,
+}
+
+impl SponsorConfig {
+    pub const LEN: usize = 8 + Self::INIT_SPACE;
+    pub const SEED_PREFIX: &'static str = "sponsor";
+
+    // Spend `lamports` of today's budget on a session for `target_program`
+    pub fn charge(&mut self, target_program: &Pubkey, lamports: u64, now: i64) -> Result<()> {
+        require!(
+            self.target_programs.contains(target_program),
+            SessionError::ProgramNotSponsored
+        );
+        let day = now.div_euclid(SPONSOR_BUDGET_PERIOD_SECS);
+        if day != self.day {
+            self.day = day;
+            self.spent_lamports = 0;
+        }
+        self.spent_lamports = self
+            .spent_lamports
+            .checked_add(lamports)
+            .filter(|spent| *spent <= self.daily_budget_lamports)
+            .ok_or(SessionError::SponsorBudgetExceeded)?;
+        Ok(())
+    }
+}
+
+// Pointer from a session signer to its latest session token
+// Backends that only see the signer pubkey derive this from it and fetch the token it names,
+// then verify it like any other. A signer with tokens for several programs points at the last
+// one created with a lookup.
+#[account]
+#[derive(InitSpace)]
+pub struct SessionSignerLookup {
+    pub session_token: Pubkey,
+    pub authority: Pubkey,
+    pub target_program: Pubkey,
+}
+
+impl SessionSignerLookup {
+    pub const LEN: usize = 8 + Self::INIT_SPACE;
+    pub const SEED_PREFIX: &'static str = "session_signer";
+}
+
+// Live session tokens held by one authority, capped by `SessionConfig`
+#[account]
+pub struct AuthoritySessionCount {
+    pub authority: Pubkey,
+    pub count: u16,
+}
+
+impl AuthoritySessionCount {
+    pub const LEN: usize = 8 + 32 + 2;
+    pub const SEED_PREFIX: &'static str = "session_count";
+
+    // Count a live token; callers enforce the cap where it applies
+    pub fn add(&mut self, authority: Pubkey) {
+        self.authority = authority;
+        self.count += 1;
+    }
+
+    // Free a slot when a token is closed
+    // Every live token is counted (migrated ones included), so a release with nothing counted
+    // means the counter drifted, and is refused rather than hidden
+    pub fn release(&mut self) -> Result<()> {
+        self.count = self.count.checked_sub(1).ok_or(SessionError::SessionCountDrift)?;
+        Ok(())
+    }
+}
+
+// Live session tokens of one authority, so clients can list them without a program scan
+#[account]
+#[derive(InitSpace)]
+pub struct SessionIndex {
+    pub authority: Pubkey,
+    #[max_len(MAX_INDEXED_SESSIONS)]
+    pub sessions: Vec<Pubkey>,
+}
+
+impl SessionIndex {
+    pub const LEN: usize = 8 + Self::INIT_SPACE;
+    pub const SEED_PREFIX: &'static str = "session_index";
+
+    pub fn add(&mut self, session_token: Pubkey) -> Result<()> {
+        require!(
+            self.sessions.len() < MAX_INDEXED_SESSIONS,
+            SessionError::SessionIndexFull
+        );
+        self.sessions.push(session_token);
+        Ok(())
+    }
+
+    // No-op for tokens that were never indexed
+    pub fn remove(&mut self, session_token: &Pubkey) {
+        self.sessions.retain(|key| key != session_token);
+    }
+}
 
-        let (pda, _) = Pubkey::find_program_address(seeds, &crate::id());
+// Next nonce an authority's signed session message must carry
+// Each accepted message bumps it, so an old signature can never be submitted again
+#[account]
+pub struct AuthorityNonce {
+    pub authority: Pubkey,
+    pub nonce: u64,
+}
 
-        require_eq!(pda, ctx.session_token.key(), SessionError::InvalidToken);
+impl AuthorityNonce {
+    pub const LEN: usize = 8 + 32 + 8;
+    pub const SEED_PREFIX: &'static str = "session_nonce";
 
-        // Check if the token has expired
-        self.is_expired()
+    pub fn consume(&mut self, authority: Pubkey, nonce: u64) -> Result<()> {
+        require_eq!(nonce, self.nonce, SessionError::InvalidNonce);
+        self.authority = authority;
+        self.nonce = self.nonce.checked_add(1).ok_or(SessionError::InvalidNonce)?;
+        Ok(())
     }
 }
 
+// Emitted when a session token's expiry is moved by `refresh_session` or `create_or_refresh_session`
+#[event]
+pub struct SessionRefreshed {
+    pub authority: Pubkey,
+    pub session_signer: Pubkey,
+    pub old_valid_until: i64,
+    pub new_valid_until: i64,
+}
+
+// Emitted when a session token is created
+#[event]
+pub struct SessionCreated {
+    pub authority: Pubkey,
+    pub session_signer: Pubkey,
+    pub target_program: Pubkey,
+    pub valid_until: i64,
+}
+
+// Emitted when a session token stops authorizing: closed by `revoke_session`, `revoke_all` or
+// `crank_expired`, or soft-revoked (`closed == false`, the account stays until closed)
+#[event]
+pub struct SessionRevoked {
+    pub authority: Pubkey,
+    pub session_signer: Pubkey,
+    pub target_program: Pubkey,
+    pub valid_until: i64,
+    pub closed: bool,
+}
+
+// Emitted when `extend_session` or `heartbeat` pushes a session token's expiry forward
+#[event]
+pub struct SessionExtended {
+    pub authority: Pubkey,
+    pub session_signer: Pubkey,
+    pub target_program: Pubkey,
+    pub old_valid_until: i64,
+    pub valid_until: i64,
+}
+
 pub trait Session<'info> {
     fn session_token(&self) -> Option<Account<'info, SessionToken>>;
     fn session_signer(&self) -> Signer<'info>;
     fn session_authority(&self) -> Pubkey;
     fn target_program(&self) -> Pubkey;
 
+    // Account the instruction acts on, checked against a token's `scope`
+    // `#[derive(Session)]` fills it from `#[session(scope = ..)]`
+    fn session_scope(&self) -> Option<Pubkey> {
+        None
+    }
+
     fn is_valid(&self) -> Result<bool> {
+        self.check_session(None)
+    }
+
+    // Like `is_valid`, but also lets tokens scoped to `instruction` through
+    // Pass the handler's own 8-byte instruction discriminator
+    fn is_valid_for(&self, instruction: [u8; 8]) -> Result<bool> {
+        self.check_session(Some(instruction))
+    }
+
+    fn check_session(&self, instruction: Option<[u8; 8]>) -> Result<bool> {
+        let session_token = self.session_token().ok_or(SessionError::NoToken)?;
+        // Check if the token is valid
+        session_token.validate(self.validity_checker(&session_token, instruction))
+    }
+
+    // Strict counterpart of `is_valid_for`: `Ok(())` or the specific `SessionError`
+    // Pass `None` for handlers that only accept unscoped tokens
+    fn require_valid(&self, instruction: Option<[u8; 8]>) -> Result<()> {
         let session_token = self.session_token().ok_or(SessionError::NoToken)?;
-        let validity_ctx = ValidityChecker {
+        session_token.validate_strict(self.validity_checker(&session_token, instruction))
+    }
+
+    // Validate the session strictly, then CPI with `instruction` on its behalf
+    // The session signer's account is passed along so `instruction` can list it as a signer
+    // (its signature carries over from the outer transaction); `signer_seeds` are the calling
+    // program's own PDA seeds, as for `invoke_signed`, and can be empty
+    fn invoke_signed_with_session(
+        &self,
+        discriminator: Option<[u8; 8]>,
+        instruction: &Instruction,
+        account_infos: &[AccountInfo<'info>],
+        signer_seeds: &[&[&[u8]]],
+    ) -> Result<()> {
+        self.require_valid(discriminator)?;
+        let mut infos = account_infos.to_vec();
+        infos.push(self.session_signer().to_account_info());
+        invoke_signed(instruction, &infos, signer_seeds)?;
+        Ok(())
+    }
+
+    fn validity_checker(
+        &self,
+        session_token: &Account<'info, SessionToken>,
+        instruction: Option<[u8; 8]>,
+    ) -> ValidityChecker<'info> {
+        ValidityChecker {
             session_token: session_token.clone(),
             session_signer: self.session_signer(),
             authority: self.session_authority(),
             target_program: self.target_program(),
-        };
-        // Check if the token is valid
-        session_token.validate(validity_ctx)
+            instruction,
+            scope: self.session_scope(),
+        }
     }
 }
 
@@ -309,4 +3245,751 @@ pub enum SessionError {
     InvalidToken,
     #[msg("No session token provided")]
     NoToken,
+    #[msg("Session token has not expired yet")]
+    TokenNotExpired,
+    #[msg("Authority has reached the maximum number of live sessions")]
+    TooManySessions,
+    #[msg("Signer is not the program upgrade authority")]
+    NotUpgradeAuthority,
+    #[msg("Only the authority or config admin may revoke a live session token")]
+    NotAuthorizedToRevoke,
+    #[msg("Session token is still inside its expiry grace period")]
+    TokenStillValid,
+    #[msg("Payer cannot be the session signer when topping up")]
+    PayerIsSessionSigner,
+    #[msg("Too many allowed instructions for one session token")]
+    TooManyAllowedInstructions,
+    #[msg("Session extension must be positive")]
+    InvalidExtension,
+    #[msg("Too many target programs for one session token")]
+    TooManyTargetPrograms,
+    #[msg("Session token spending cap exceeded")]
+    SpendCapExceeded,
+    #[msg("Session token is not in the legacy layout")]
+    NotLegacyToken,
+    #[msg("Session index is full")]
+    SessionIndexFull,
+    #[msg("Grace window is too long")]
+    GraceTooLong,
+    #[msg("Session token has expired")]
+    SessionExpired,
+    #[msg("Batch must be 1 to MAX_BATCH_SESSIONS [target_program, session_token] pairs")]
+    InvalidBatch,
+    #[msg("SPL top-up needs mint, source, destination and token program, with the destination owned by the session signer")]
+    IncompleteTokenTopUp,
+    #[msg("Requested validity is too short")]
+    ValidityTooShort,
+    #[msg("Validity policy must satisfy 0 < min <= default <= max <= one week")]
+    InvalidValidityPolicy,
+    #[msg("Missing or mismatched ed25519 session authorization")]
+    InvalidAuthorization,
+    #[msg("Signed message nonce is not the authority's next nonce")]
+    InvalidNonce,
+    #[msg("Session token does not cover the calling program")]
+    WrongProgram,
+    #[msg("Transaction signer is not the session signer")]
+    WrongSigner,
+    #[msg("Session token is not at the PDA for its authority")]
+    InvalidPda,
+    #[msg("Session token has been revoked")]
+    TokenRevoked,
+    #[msg("Session token is not allowed to authorize this instruction")]
+    InstructionNotAllowed,
+    #[msg("Session token is bound to a different account")]
+    WrongScope,
+    #[msg("Reference tolerance must be 0 to MAX_REFERENCE_TOLERANCE_SECS")]
+    ToleranceTooLong,
+    #[msg("Reference timestamp is outside the allowed tolerance")]
+    StaleReference,
+    #[msg("Session creation is paused")]
+    CreationPaused,
+    #[msg("Payer does not sponsor sessions for this target program")]
+    ProgramNotSponsored,
+    #[msg("Payer's daily sponsorship budget is exhausted")]
+    SponsorBudgetExceeded,
+    #[msg("Session count is out of step with the live tokens")]
+    SessionCountDrift,
+    #[msg("Payer has no sponsor config and is not the session authority")]
+    PayerNotSponsor,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOW: i64 = 1_700_000_000;
+
+    #[test]
+    fn heartbeat_slides_expiry_forward() {
+        let policy = ValidityPolicy::default();
+        assert_eq!(
+            heartbeat_valid_until(NOW - 60, NOW + 60, NOW, &policy),
+            NOW + HEARTBEAT_EXTENSION_SECS
+        );
+        // Never moves expiry backwards
+        assert_eq!(
+            heartbeat_valid_until(NOW - 60, NOW + 3_600, NOW, &policy),
+            NOW + 3_600
+        );
+    }
+
+    #[test]
+    fn heartbeat_stops_at_the_policy_max() {
+        let policy = ValidityPolicy {
+            max_validity_secs: 60 * 60,
+            ..ValidityPolicy::default()
+        };
+        let created_at = NOW - 3_500;
+        assert_eq!(
+            heartbeat_valid_until(created_at, NOW + 60, NOW, &policy),
+            created_at + 60 * 60
+        );
+    }
+
+    #[test]
+    fn heartbeat_never_outlasts_max_validity_secs() {
+        // A config written before the cap was enforced can't stretch heartbeats past it
+        let policy = ValidityPolicy {
+            max_validity_secs: MAX_VALIDITY_SECS * 2,
+            ..ValidityPolicy::default()
+        };
+        let created_at = NOW - MAX_VALIDITY_SECS + 60;
+        assert_eq!(
+            heartbeat_valid_until(created_at, NOW + 30, NOW, &policy),
+            created_at + MAX_VALIDITY_SECS
+        );
+    }
+
+    // Token expiring at `NOW`, with the default grace
+    fn token() -> SessionToken {
+        SessionToken::new(
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            NOW,
+            NOW - 60,
+            255,
+        )
+    }
+
+    #[test]
+    fn token_validates_inside_the_grace_window() {
+        let token = token();
+        assert!(token.check_expiry(NOW - 1).is_ok());
+        assert!(token.check_expiry(NOW).is_ok());
+        assert!(token.check_expiry(NOW + EXPIRY_GRACE_SECS - 1).is_ok());
+    }
+
+    #[test]
+    fn token_past_the_grace_window_is_expired() {
+        let mut token = token();
+        assert_eq!(
+            token.check_expiry(NOW + EXPIRY_GRACE_SECS).unwrap_err(),
+            SessionError::SessionExpired.into()
+        );
+        // Clock tolerance widens the window, no grace at all closes it at `valid_until`
+        token.grace.clock_tolerance_secs = 10;
+        assert!(token.check_expiry(NOW + EXPIRY_GRACE_SECS).is_ok());
+        token.grace = GraceConfig {
+            grace_secs: 0,
+            clock_tolerance_secs: 0,
+        };
+        assert!(!token.is_within_grace(NOW));
+    }
+
+    #[test]
+    fn refresh_reports_the_old_and_new_expiry() {
+        let mut token = token();
+        let refreshed = token.refresh(NOW + 3_600);
+        assert_eq!(token.valid_until, NOW + 3_600);
+        assert_eq!(refreshed.old_valid_until, NOW);
+        assert_eq!(refreshed.new_valid_until, NOW + 3_600);
+        assert_eq!(refreshed.authority, token.authority);
+        assert_eq!(refreshed.session_signer, token.session_signer);
+
+        // A second refresh reports the first one's expiry as its old value
+        let refreshed = token.refresh(NOW + 60);
+        assert_eq!(
+            (refreshed.old_valid_until, refreshed.new_valid_until),
+            (NOW + 3_600, NOW + 60)
+        );
+    }
+
+    // What `init_if_needed` leaves at a token PDA that did not exist yet
+    fn fresh_token() -> SessionToken {
+        SessionToken::try_deserialize_unchecked(&mut &[0; SessionToken::LEN][..]).unwrap()
+    }
+
+    // `create_or_refresh_session` by the holder of `keys`, asking for `NOW + secs`
+    fn create_or_refresh(
+        session_token: &mut SessionToken,
+        keys: &SessionToken,
+        secs: i64,
+    ) -> Result<Option<SessionRefreshed>> {
+        refresh_existing_token(
+            session_token,
+            keys.authority,
+            keys.target_program,
+            keys.session_signer,
+            NOW + secs,
+            NOW,
+            &ValidityPolicy::default(),
+        )
+    }
+
+    #[test]
+    fn create_or_refresh_creates_at_a_fresh_account() {
+        let keys = token();
+        let mut session_token = fresh_token();
+        assert!(create_or_refresh(&mut session_token, &keys, 3_600)
+            .unwrap()
+            .is_none());
+        assert_eq!(session_token.valid_until, 0);
+
+        // The create path then counts it against the authority's cap
+        let mut count = AuthoritySessionCount {
+            authority: Pubkey::default(),
+            count: 0,
+        };
+        track_new_session(&mut count, keys.authority, &SessionConfig::default()).unwrap();
+        assert_eq!((count.authority, count.count), (keys.authority, 1));
+    }
+
+    #[test]
+    fn create_or_refresh_moves_the_expiry_of_an_existing_token() {
+        let mut session_token = token();
+        let mut keys = token();
+        keys.authority = session_token.authority;
+        keys.target_program = session_token.target_program;
+        keys.session_signer = session_token.session_signer;
+        let refreshed = create_or_refresh(&mut session_token, &keys, 3_600)
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            (refreshed.old_valid_until, refreshed.new_valid_until),
+            (NOW, NOW + 3_600)
+        );
+        assert_eq!(session_token.valid_until, NOW + 3_600);
+
+        // Another signer's keys are refused, and expiry stays put
+        keys.session_signer = Pubkey::new_unique();
+        assert_eq!(
+            create_or_refresh(&mut session_token, &keys, 60).err(),
+            Some(SessionError::InvalidToken.into())
+        );
+        assert_eq!(session_token.valid_until, NOW + 3_600);
+    }
+
+    #[test]
+    fn one_week_validity_is_inclusive() {
+        let policy = ValidityPolicy::default();
+        assert_eq!(policy.max_validity_secs, 60 * 60 * 24 * 7);
+        assert!(require_valid_until(NOW + MAX_VALIDITY_SECS - 1, NOW, &policy).is_ok());
+        assert!(require_valid_until(NOW + MAX_VALIDITY_SECS, NOW, &policy).is_ok());
+        assert_eq!(
+            require_valid_until(NOW + MAX_VALIDITY_SECS + 1, NOW, &policy).unwrap_err(),
+            SessionError::ValidityTooLong.into()
+        );
+    }
+
+    #[test]
+    fn validity_below_the_minimum_is_rejected() {
+        let policy = ValidityPolicy::default();
+        assert!(require_valid_until(NOW + DEFAULT_MIN_VALIDITY_SECS, NOW, &policy).is_ok());
+        assert_eq!(
+            require_valid_until(NOW + DEFAULT_MIN_VALIDITY_SECS - 1, NOW, &policy).unwrap_err(),
+            SessionError::ValidityTooShort.into()
+        );
+        // A zero minimum still never accepts an already-expired token
+        let policy = ValidityPolicy {
+            min_validity_secs: 0,
+            ..policy
+        };
+        assert_eq!(
+            require_valid_until(NOW, NOW, &policy).unwrap_err(),
+            SessionError::ValidityTooShort.into()
+        );
+    }
+
+    fn config(admin: Pubkey) -> SessionConfig {
+        SessionConfig {
+            admin,
+            max_sessions_per_authority: 0,
+            policy: ValidityPolicy::default(),
+            freeze_authority: admin,
+            paused: false,
+        }
+    }
+
+    #[test]
+    fn authority_revoker_and_admin_revoke_live_tokens() {
+        let admin = Pubkey::new_unique();
+        let revoker = Pubkey::new_unique();
+        let mut token = token();
+        token.revoker = Some(revoker);
+        for signer in [token.authority, revoker, admin] {
+            assert!(can_revoke(&token, &signer, &config(admin), NOW - 1).is_ok());
+        }
+    }
+
+    #[test]
+    fn anyone_revokes_soft_revoked_or_fully_expired_tokens() {
+        let config = config(Pubkey::new_unique());
+        let stranger = Pubkey::new_unique();
+        let mut token = token();
+        assert!(can_revoke(&token, &stranger, &config, NOW + EXPIRY_GRACE_SECS).is_ok());
+        token.revoked = true;
+        assert!(can_revoke(&token, &stranger, &config, NOW - 1).is_ok());
+    }
+
+    #[test]
+    fn strangers_cannot_revoke_live_tokens() {
+        let config = config(Pubkey::new_unique());
+        let mut token = token();
+        // A revoker set for someone else doesn't extend to other signers
+        token.revoker = Some(Pubkey::new_unique());
+        assert_eq!(
+            can_revoke(&token, &Pubkey::new_unique(), &config, NOW - 1).unwrap_err(),
+            SessionError::NotAuthorizedToRevoke.into()
+        );
+    }
+
+    #[test]
+    fn strangers_wait_out_the_grace_window() {
+        let config = config(Pubkey::new_unique());
+        assert_eq!(
+            can_revoke(&token(), &Pubkey::new_unique(), &config, NOW).unwrap_err(),
+            SessionError::TokenStillValid.into()
+        );
+    }
+
+    fn legacy() -> LegacySessionToken {
+        LegacySessionToken {
+            authority: Pubkey::new_unique(),
+            target_program: Pubkey::new_unique(),
+            session_signer: Pubkey::new_unique(),
+            valid_until: NOW,
+        }
+    }
+
+    // Account data of a version-0 token
+    fn legacy_data(legacy: &LegacySessionToken) -> Vec<u8> {
+        let mut data = SessionToken::DISCRIMINATOR.to_vec();
+        AnchorSerialize::serialize(legacy, &mut data).unwrap();
+        data
+    }
+
+    #[test]
+    fn legacy_token_reads_and_converts() {
+        let legacy = legacy();
+        let data = legacy_data(&legacy);
+        assert_eq!(data.len(), LegacySessionToken::LEN);
+
+        let token = SessionToken::from(LegacySessionToken::try_deserialize(&data).unwrap());
+        assert_eq!(token.authority, legacy.authority);
+        assert_eq!(token.target_program, legacy.target_program);
+        assert_eq!(token.session_signer, legacy.session_signer);
+        assert_eq!(token.valid_until, NOW);
+        assert_eq!(token.version, SessionToken::VERSION);
+        assert!(!token.revoked && token.revoker.is_none() && token.scope.is_none());
+        assert!(token.allowed_instructions.is_empty() && token.extra_target_programs.is_empty());
+    }
+
+    #[test]
+    fn current_layout_is_not_read_as_legacy() {
+        let mut data = legacy_data(&legacy());
+        data.resize(SessionToken::LEN, 0);
+        assert_eq!(
+            LegacySessionToken::try_deserialize(&data).err(),
+            Some(SessionError::NotLegacyToken.into())
+        );
+    }
+
+    #[test]
+    fn legacy_sized_account_of_another_type_is_rejected() {
+        let mut data = legacy_data(&legacy());
+        data[0] ^= 0xff;
+        assert_eq!(
+            LegacySessionToken::try_deserialize(&data).err(),
+            Some(SessionError::InvalidToken.into())
+        );
+    }
+
+    #[test]
+    fn migrated_token_is_counted_and_released() {
+        let legacy = legacy();
+        let mut count = AuthoritySessionCount {
+            authority: Pubkey::default(),
+            count: 0,
+        };
+        // What `migrate_session` does to a fresh count account
+        count.add(legacy.authority);
+        assert_eq!(count.authority, legacy.authority);
+        assert_eq!(count.count, 1);
+
+        // Closing the migrated token frees the slot it took
+        assert!(count.release().is_ok());
+        assert_eq!(count.count, 0);
+    }
+
+    #[test]
+    fn releasing_an_uncounted_token_is_refused() {
+        let mut count = AuthoritySessionCount {
+            authority: Pubkey::new_unique(),
+            count: 0,
+        };
+        assert_eq!(
+            count.release().unwrap_err(),
+            SessionError::SessionCountDrift.into()
+        );
+        assert_eq!(count.count, 0);
+    }
+
+    // Writable account `key` owned by `owner`
+    fn account<'a>(
+        key: &'a Pubkey,
+        owner: &'a Pubkey,
+        lamports: &'a mut u64,
+        data: &'a mut [u8],
+    ) -> AccountInfo<'a> {
+        AccountInfo::new(key, false, true, lamports, data, owner, false, 0)
+    }
+
+    // Account data of a signer lookup naming `session_token`
+    fn lookup_data(session_token: Pubkey) -> Vec<u8> {
+        let lookup = SessionSignerLookup {
+            session_token,
+            authority: Pubkey::new_unique(),
+            target_program: Pubkey::new_unique(),
+        };
+        let mut data = Vec::new();
+        lookup.try_serialize(&mut data).unwrap();
+        data
+    }
+
+    #[test]
+    fn closing_drops_the_index_entry_and_the_lookup() {
+        let (token, other) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let mut count = AuthoritySessionCount {
+            authority: Pubkey::new_unique(),
+            count: 2,
+        };
+        let mut index = SessionIndex {
+            authority: count.authority,
+            sessions: vec![token, other],
+        };
+        release_session(&mut count, Some(&mut index), &token).unwrap();
+        assert_eq!(count.count, 1);
+        assert_eq!(index.sessions, vec![other]);
+
+        let key = Pubkey::new_unique();
+        let (mut lamports, mut data) = (1, lookup_data(token));
+        let info = account(&key, &crate::ID, &mut lamports, &mut data);
+        assert!(lookup_to_close(&info, &token).unwrap().is_some());
+    }
+
+    #[test]
+    fn lookups_of_other_tokens_are_left_open() {
+        let token = Pubkey::new_unique();
+        // A lookup that moved on to a newer token of the same signer
+        let key = Pubkey::new_unique();
+        let (mut lamports, mut data) = (1, lookup_data(Pubkey::new_unique()));
+        let info = account(&key, &crate::ID, &mut lamports, &mut data);
+        assert!(lookup_to_close(&info, &token).unwrap().is_none());
+
+        // The system program standing in for a signer without a lookup
+        let system = system_program::ID;
+        let (mut lamports, mut data) = (1, Vec::new());
+        let info = account(&system, &system, &mut lamports, &mut data);
+        assert!(lookup_to_close(&info, &token).unwrap().is_none());
+    }
+
+    #[test]
+    fn uninitialized_config_falls_back_to_the_defaults() {
+        let key = Pubkey::new_unique();
+        let system = system_program::ID;
+        let (mut lamports, mut data) = (0, Vec::new());
+        let config =
+            SessionConfig::load(&account(&key, &system, &mut lamports, &mut data)).unwrap();
+        assert_eq!(
+            config.max_sessions_per_authority,
+            DEFAULT_MAX_SESSIONS_PER_AUTHORITY
+        );
+        assert_eq!(config.admin, Pubkey::default());
+        assert!(!config.paused);
+        // The defaults let a first session be created
+        let mut count = AuthoritySessionCount {
+            authority: Pubkey::default(),
+            count: 0,
+        };
+        assert!(track_new_session(&mut count, key, &config).is_ok());
+    }
+
+    #[test]
+    fn initialized_config_is_read_from_the_account() {
+        let admin = Pubkey::new_unique();
+        let mut stored = config(admin);
+        stored.max_sessions_per_authority = 3;
+        let mut data = Vec::new();
+        stored.try_serialize(&mut data).unwrap();
+
+        let key = Pubkey::new_unique();
+        let mut lamports = 1;
+        let config =
+            SessionConfig::load(&account(&key, &crate::ID, &mut lamports, &mut data)).unwrap();
+        assert_eq!(config.admin, admin);
+        assert_eq!(config.max_sessions_per_authority, 3);
+    }
+
+    // `token()` at its real PDA, with the key it lives at
+    fn token_at_pda() -> (SessionToken, Pubkey) {
+        let mut token = token();
+        let (pda, bump) = Pubkey::find_program_address(
+            &[
+                SessionToken::SEED_PREFIX.as_bytes(),
+                token.target_program.as_ref(),
+                token.session_signer.as_ref(),
+                token.authority.as_ref(),
+            ],
+            &crate::id(),
+        );
+        token.bump = bump;
+        (token, pda)
+    }
+
+    // `validate_keys` for the token's own keys, with `instruction` and `scope`
+    fn validate_own_keys(
+        token: &SessionToken,
+        address: &Pubkey,
+        instruction: Option<[u8; 8]>,
+        scope: Option<Pubkey>,
+    ) -> Result<()> {
+        token.validate_keys(
+            address,
+            &token.session_signer,
+            &token.authority,
+            &token.target_program,
+            instruction,
+            scope,
+        )
+    }
+
+    #[test]
+    fn strict_validation_names_the_mismatched_key() {
+        let (token, address) = token_at_pda();
+        let other = Pubkey::new_unique();
+        let keys = (
+            &token.session_signer,
+            &token.authority,
+            &token.target_program,
+        );
+        let cases = [
+            ((keys.0, keys.1, &other), SessionError::WrongProgram),
+            ((&other, keys.1, keys.2), SessionError::WrongSigner),
+            ((keys.0, &other, keys.2), SessionError::InvalidPda),
+        ];
+        for ((signer, authority, program), expected) in cases {
+            assert_eq!(
+                token
+                    .validate_keys(&address, signer, authority, program, None, None)
+                    .unwrap_err(),
+                expected.into()
+            );
+        }
+        assert_eq!(
+            validate_own_keys(&token, &other, None, None).unwrap_err(),
+            SessionError::InvalidPda.into()
+        );
+    }
+
+    // Puts a valid token into one unusable state
+    type Restriction = fn(&mut SessionToken);
+
+    #[test]
+    fn strict_validation_names_the_unusable_state() {
+        let (token, address) = token_at_pda();
+        let cases: [(Restriction, SessionError); 4] = [
+            (|token| token.revoked = true, SessionError::TokenRevoked),
+            (
+                |token| {
+                    token.max_spend_lamports = 10;
+                    token.spent_lamports = 10;
+                },
+                SessionError::SpendCapExceeded,
+            ),
+            (
+                |token| token.allowed_instructions = vec![[1; 8]],
+                SessionError::InstructionNotAllowed,
+            ),
+            (
+                |token| token.scope = Some(Pubkey::new_unique()),
+                SessionError::WrongScope,
+            ),
+        ];
+        for (restrict, expected) in cases {
+            let mut token = token.clone();
+            restrict(&mut token);
+            assert_eq!(
+                validate_own_keys(&token, &address, Some([2; 8]), None).unwrap_err(),
+                expected.into()
+            );
+        }
+    }
+
+    #[test]
+    fn default_policy_is_consistent() {
+        assert!(ValidityPolicy::default().is_consistent());
+        let tight = ValidityPolicy {
+            min_validity_secs: 1,
+            max_validity_secs: 1,
+            default_validity_secs: 1,
+            ..ValidityPolicy::default()
+        };
+        assert!(tight.is_consistent());
+    }
+
+    #[test]
+    fn out_of_order_or_unbounded_policies_are_inconsistent() {
+        let base = ValidityPolicy::default();
+        let policies = [
+            ValidityPolicy {
+                min_validity_secs: 0,
+                ..base
+            },
+            ValidityPolicy {
+                min_validity_secs: base.default_validity_secs + 1,
+                ..base
+            },
+            ValidityPolicy {
+                default_validity_secs: base.max_validity_secs + 1,
+                ..base
+            },
+            ValidityPolicy {
+                max_validity_secs: MAX_VALIDITY_SECS + 1,
+                ..base
+            },
+        ];
+        for policy in policies {
+            assert!(!policy.is_consistent());
+        }
+    }
+
+    // Sponsor of `program` with a 1000-lamport daily budget, 400 spent today
+    fn sponsor(program: Pubkey) -> SponsorConfig {
+        SponsorConfig {
+            sponsor: Pubkey::new_unique(),
+            target_programs: vec![program],
+            daily_budget_lamports: 1_000,
+            day: NOW.div_euclid(SPONSOR_BUDGET_PERIOD_SECS),
+            spent_lamports: 400,
+        }
+    }
+
+    #[test]
+    fn charge_spends_up_to_the_daily_budget() {
+        let program = Pubkey::new_unique();
+        let mut sponsor = sponsor(program);
+        sponsor.charge(&program, 600, NOW).unwrap();
+        assert_eq!(sponsor.spent_lamports, 1_000);
+        assert_eq!(
+            sponsor.charge(&program, 1, NOW).unwrap_err(),
+            SessionError::SponsorBudgetExceeded.into()
+        );
+        assert_eq!(sponsor.spent_lamports, 1_000);
+    }
+
+    #[test]
+    fn budget_resets_on_a_new_day() {
+        let program = Pubkey::new_unique();
+        let mut sponsor = sponsor(program);
+        sponsor
+            .charge(&program, 1_000, NOW + SPONSOR_BUDGET_PERIOD_SECS)
+            .unwrap();
+        assert_eq!(sponsor.spent_lamports, 1_000);
+        assert_eq!(sponsor.day, NOW.div_euclid(SPONSOR_BUDGET_PERIOD_SECS) + 1);
+    }
+
+    #[test]
+    fn charge_rejects_unsponsored_programs_and_overflow() {
+        let program = Pubkey::new_unique();
+        let mut sponsor = sponsor(program);
+        assert_eq!(
+            sponsor.charge(&Pubkey::new_unique(), 1, NOW).unwrap_err(),
+            SessionError::ProgramNotSponsored.into()
+        );
+        assert_eq!(
+            sponsor.charge(&program, u64::MAX, NOW).unwrap_err(),
+            SessionError::SponsorBudgetExceeded.into()
+        );
+        assert_eq!(sponsor.spent_lamports, 400);
+    }
+
+    #[test]
+    fn payers_without_a_sponsor_config_only_pay_for_themselves() {
+        let (payer, program) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let (key, system) = (Pubkey::new_unique(), system_program::ID);
+        let (mut lamports, mut data) = (0, Vec::new());
+        let info = account(&key, &system, &mut lamports, &mut data);
+        assert_eq!(
+            charge_sponsor(&info, &payer, &Pubkey::new_unique(), &program, 1, NOW).unwrap_err(),
+            SessionError::PayerNotSponsor.into()
+        );
+        assert!(charge_sponsor(&info, &payer, &payer, &program, 1, NOW).is_ok());
+    }
+
+    #[test]
+    fn sponsored_creation_is_charged_to_the_stored_config() {
+        let program = Pubkey::new_unique();
+        let mut data = vec![0; SponsorConfig::LEN];
+        sponsor(program).try_serialize(&mut &mut data[..]).unwrap();
+        let (key, mut lamports) = (Pubkey::new_unique(), 1);
+        let info = account(&key, &crate::ID, &mut lamports, &mut data);
+
+        let payer = Pubkey::new_unique();
+        charge_sponsor(&info, &payer, &Pubkey::new_unique(), &program, 600, NOW).unwrap();
+        let stored = SponsorConfig::try_deserialize(&mut &info.try_borrow_data().unwrap()[..]);
+        assert_eq!(stored.unwrap().spent_lamports, 1_000);
+    }
+
+    #[test]
+    fn payer_authority_and_session_signer_can_all_differ() {
+        let (authority, signer, program) = (
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        );
+        let policy = ValidityPolicy::default();
+        let mut data = vec![0; SponsorConfig::LEN];
+        sponsor(program).try_serialize(&mut &mut data[..]).unwrap();
+        let (payer, mut lamports) = (Pubkey::new_unique(), 1);
+        let info = account(&payer, &crate::ID, &mut lamports, &mut data);
+
+        let top_up = payer_top_up_lamports(&payer, &signer, true, Some(500), &policy).unwrap();
+        assert_eq!(top_up, 500);
+        // The payer's budget covers it, while the token and its count belong to the authority
+        charge_sponsor(&info, &payer, &authority, &program, top_up, NOW).unwrap();
+        let stored = SponsorConfig::try_deserialize(&mut &info.try_borrow_data().unwrap()[..]);
+        assert_eq!(stored.unwrap().spent_lamports, 900);
+        let mut count = AuthoritySessionCount {
+            authority: Pubkey::default(),
+            count: 0,
+        };
+        track_new_session(&mut count, authority, &SessionConfig::default()).unwrap();
+        assert_eq!(count.authority, authority);
+    }
+
+    #[test]
+    fn session_signer_cannot_pay_its_own_top_up() {
+        let signer = Pubkey::new_unique();
+        let policy = ValidityPolicy::default();
+        assert_eq!(
+            payer_top_up_lamports(&signer, &signer, true, None, &policy).unwrap_err(),
+            SessionError::PayerIsSessionSigner.into()
+        );
+        // Without a top-up it only pays the rent
+        assert_eq!(
+            payer_top_up_lamports(&signer, &signer, false, None, &policy).unwrap(),
+            0
+        );
+    }
 }
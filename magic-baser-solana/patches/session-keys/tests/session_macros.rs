@@ -0,0 +1,233 @@
+// Behaviour of handlers wrapped by `#[session_auth_or]` and `#[session_guard]`
+// The handlers run against real account infos: a session token at its PDA for this program,
+// and the clock syscall stubbed to `NOW`.
+use std::sync::Once;
+
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::entrypoint::SUCCESS;
+use anchor_lang::solana_program::program_stubs::{set_syscall_stubs, SyscallStubs};
+use anchor_lang::system_program;
+use session_keys::{
+    session_auth_or, session_guard, Session, SessionError, SessionToken, EXPIRY_GRACE_SECS,
+};
+
+declare_id!("Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS");
+
+const NOW: i64 = 1_700_000_000;
+
+// What the wrapped handlers return once their body runs
+const PLAYED: u8 = 7;
+
+#[error_code]
+pub enum GameError {
+    #[msg("Signer is not the player")]
+    NotPlayer,
+    #[msg("A session is required")]
+    SessionRequired,
+}
+
+#[derive(Session)]
+pub struct Play<'info> {
+    #[session(signer = signer, authority = authority)]
+    pub session_token: Option<Account<'info, SessionToken>>,
+    pub signer: Signer<'info>,
+    pub authority: Pubkey,
+    pub player: Pubkey,
+}
+
+// Stand-in for `Context`: the macros only reach the accounts through `ctx.accounts`
+pub struct Ctx<'a, 'info> {
+    pub accounts: &'a mut Play<'info>,
+}
+
+#[session_auth_or(ctx.accounts.player == ctx.accounts.signer.key(), GameError::NotPlayer)]
+fn play(ctx: Ctx) -> Result<u8> {
+    Ok(PLAYED)
+}
+
+#[session_guard]
+fn guarded(ctx: Ctx) -> Result<u8> {
+    Ok(PLAYED)
+}
+
+#[session_guard(GameError::SessionRequired)]
+fn guarded_or(ctx: Ctx) -> Result<u8> {
+    Ok(PLAYED)
+}
+
+struct ClockAtNow;
+
+impl SyscallStubs for ClockAtNow {
+    fn sol_get_clock_sysvar(&self, var_addr: *mut u8) -> u64 {
+        let clock = Clock {
+            unix_timestamp: NOW,
+            ..Clock::default()
+        };
+        unsafe { *(var_addr as *mut Clock) = clock };
+        SUCCESS
+    }
+}
+
+// Accounts for one handler call; the session token is stored at its PDA
+struct Fixture {
+    authority: Pubkey,
+    signer: Pubkey,
+    token: Pubkey,
+    token_data: Vec<u8>,
+    lamports: [u64; 2],
+}
+
+impl Fixture {
+    fn new(valid_until: i64, revoked: bool) -> Self {
+        static CLOCK: Once = Once::new();
+        CLOCK.call_once(|| {
+            set_syscall_stubs(Box::new(ClockAtNow));
+        });
+
+        let (authority, signer) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let (token, bump) = Pubkey::find_program_address(
+            &[
+                SessionToken::SEED_PREFIX.as_bytes(),
+                crate::id().as_ref(),
+                signer.as_ref(),
+                authority.as_ref(),
+            ],
+            &session_keys::id(),
+        );
+        let mut session_token =
+            SessionToken::new(authority, crate::id(), signer, valid_until, NOW - 60, bump);
+        session_token.revoked = revoked;
+        let mut token_data = vec![0; SessionToken::LEN];
+        session_token
+            .try_serialize(&mut &mut token_data[..])
+            .unwrap();
+
+        Fixture {
+            authority,
+            signer,
+            token,
+            token_data,
+            lamports: [1, 1],
+        }
+    }
+
+    fn live() -> Self {
+        Fixture::new(NOW + 3_600, false)
+    }
+
+    // Call `handler` signed by the session signer, on behalf of `player`
+    fn call(
+        &mut self,
+        handler: fn(Ctx) -> Result<u8>,
+        with_token: bool,
+        player: Pubkey,
+    ) -> Result<u8> {
+        let [token_lamports, signer_lamports] = &mut self.lamports;
+        let owner = session_keys::id();
+        let token_info = AccountInfo::new(
+            &self.token,
+            false,
+            false,
+            token_lamports,
+            &mut self.token_data,
+            &owner,
+            false,
+            0,
+        );
+        let (mut signer_data, system) = ([0u8; 0], system_program::ID);
+        let signer_info = AccountInfo::new(
+            &self.signer,
+            true,
+            false,
+            signer_lamports,
+            &mut signer_data,
+            &system,
+            false,
+            0,
+        );
+
+        let mut accounts = Play {
+            session_token: with_token.then(|| Account::try_from(&token_info).unwrap()),
+            signer: Signer::try_from(&signer_info).unwrap(),
+            authority: self.authority,
+            player,
+        };
+        handler(Ctx {
+            accounts: &mut accounts,
+        })
+    }
+}
+
+#[test]
+fn auth_or_without_a_token_runs_when_the_condition_holds() {
+    let mut fixture = Fixture::live();
+    let player = fixture.signer;
+    assert_eq!(fixture.call(play, false, player).unwrap(), PLAYED);
+}
+
+#[test]
+fn auth_or_without_a_token_fails_with_the_given_error() {
+    let mut fixture = Fixture::live();
+    assert_eq!(
+        fixture.call(play, false, Pubkey::new_unique()).unwrap_err(),
+        GameError::NotPlayer.into()
+    );
+}
+
+#[test]
+fn auth_or_with_a_valid_token_skips_the_condition() {
+    let mut fixture = Fixture::live();
+    assert_eq!(
+        fixture.call(play, true, Pubkey::new_unique()).unwrap(),
+        PLAYED
+    );
+}
+
+#[test]
+fn auth_or_refuses_an_unusable_token_even_when_the_condition_holds() {
+    let mut fixture = Fixture::new(NOW - EXPIRY_GRACE_SECS - 1, false);
+    let player = fixture.signer;
+    assert_eq!(
+        fixture.call(play, true, player).unwrap_err(),
+        SessionError::SessionExpired.into()
+    );
+
+    let mut fixture = Fixture::new(NOW + 3_600, true);
+    let player = fixture.signer;
+    assert_eq!(
+        fixture.call(play, true, player).unwrap_err(),
+        SessionError::InvalidToken.into()
+    );
+}
+
+#[test]
+fn guard_runs_the_body_with_a_valid_token() {
+    let mut fixture = Fixture::live();
+    let player = fixture.signer;
+    assert_eq!(fixture.call(guarded, true, player).unwrap(), PLAYED);
+    assert_eq!(fixture.call(guarded_or, true, player).unwrap(), PLAYED);
+}
+
+#[test]
+fn guard_without_a_token_fails_with_no_token() {
+    let mut fixture = Fixture::live();
+    let player = fixture.signer;
+    assert_eq!(
+        fixture.call(guarded_or, false, player).unwrap_err(),
+        SessionError::NoToken.into()
+    );
+}
+
+#[test]
+fn guard_fails_a_dead_token_with_the_given_error() {
+    let mut fixture = Fixture::new(NOW + 3_600, true);
+    let player = fixture.signer;
+    assert_eq!(
+        fixture.call(guarded, true, player).unwrap_err(),
+        SessionError::InvalidToken.into()
+    );
+    assert_eq!(
+        fixture.call(guarded_or, true, player).unwrap_err(),
+        GameError::SessionRequired.into()
+    );
+}
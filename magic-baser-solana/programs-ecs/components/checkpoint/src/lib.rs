@@ -0,0 +1,57 @@
+use bolt_lang::*;
+
+declare_id!("8FcfQz4YjqtDbP4LaoWyMj5h5NGjgKLfagR6bVbiyFuc");
+
+/// Checkpoint component - persists on L1
+/// Last snapshot of a run's GameSession, taken every `CHECKPOINT_INTERVAL_WAVES` waves by the
+/// `checkpoint` system, so a run can be resumed or settled if the Ephemeral Rollup drops it
+/// Lives on the session entity next to GameSession
+#[component]
+#[derive(Default)]
+pub struct Checkpoint {
+    /// GameSession account the snapshot was taken from
+    pub session: Option<Pubkey>,
+    /// Wallet of the player running the snapshotted session
+    pub player: Option<Pubkey>,
+    /// `started_at` of the snapshotted run, telling runs on the same session entity apart
+    pub started_at: i64,
+    /// Selected character ID
+    #[max_len(20)]
+    pub character_id: String,
+    /// Wave reached at the snapshot
+    pub wave: u8,
+    /// Health points at the snapshot
+    pub hp: u16,
+    /// Maximum health points at the snapshot
+    pub max_hp: u16,
+    /// Level at the snapshot
+    pub level: u8,
+    /// Experience points at the snapshot
+    pub xp: u32,
+    /// Gold earned so far
+    pub gold_earned: u32,
+    /// Time survived in seconds
+    pub time_survived: u32,
+    /// Kills so far
+    pub kills: u32,
+    /// Revives used so far
+    pub revives_used: u8,
+    /// Stats update tick the snapshot reflects
+    pub tick: u64,
+    /// Unix timestamp the snapshot was taken
+    pub saved_at: i64,
+}
+
+/// Expected `INIT_SPACE` of the Checkpoint layout, Bolt metadata included
+pub const CHECKPOINT_SPACE: usize = 170;
+
+const _: () = assert!(Checkpoint::INIT_SPACE == CHECKPOINT_SPACE);
+
+pub use game_rules::waves::{checkpoint_due, CHECKPOINT_INTERVAL_WAVES};
+
+impl Checkpoint {
+    /// True if this snapshot belongs to the run on `session` that started at `started_at`
+    pub fn is_for_run(&self, session: &Pubkey, started_at: i64) -> bool {
+        self.session == Some(*session) && self.started_at == started_at
+    }
+}
@@ -0,0 +1,140 @@
+use bolt_lang::anchor_lang::solana_program::pubkey;
+use bolt_lang::*;
+use game_rules::features;
+
+declare_id!("Ad7EUG4NMhEk8xwjksLYpStGhi61wt3fsdVuHDtrARxk");
+
+/// Config component - persists on L1
+/// World-wide game settings, stored on a single entity seeded "config"
+#[component]
+#[derive(Default)]
+pub struct Config {
+    /// Admin allowed to change the config (claimed by the first `set-config` call)
+    pub admin: Option<Pubkey>,
+    /// Destination for all SOL fees collected by systems
+    pub treasury: Option<Pubkey>,
+    /// Minimum seconds between `submit-score` calls on one leaderboard entry (0 = no limit)
+    pub submit_interval_secs: u32,
+    /// HP at or below which a player counts as dead (0 = dead only at zero HP)
+    pub death_hp_threshold: u16,
+    /// Runtime behavior toggles, `game_rules::features::FEATURE_*` bits
+    pub feature_flags: u32,
+    /// Free revives granted on a player's first `start_game`
+    pub starter_revives: u8,
+    /// Oracle that commits to and reveals the secrets behind randomness requests (unset =
+    /// randomness disabled)
+    pub vrf_oracle: Option<Pubkey>,
+    /// Basis points of a run's gold pickups converted to bonus gold at its end (0 = none)
+    pub pickup_gold_rate_bps: u16,
+}
+
+/// Expected `INIT_SPACE` of the Config layout, Bolt metadata included
+pub const CONFIG_SPACE: usize = 144;
+
+const _: () = assert!(Config::INIT_SPACE == CONFIG_SPACE);
+
+#[error_code]
+pub enum ConfigError {
+    #[msg("Treasury has not been configured")]
+    TreasuryNotSet,
+    #[msg("Fee destination does not match the configured treasury")]
+    WrongTreasury,
+    #[msg("Config is not the world's config entity")]
+    NotCanonicalConfig,
+}
+
+/// Bolt World program the game's entities are registered with
+pub const WORLD_PROGRAM_ID: Pubkey = pubkey!("WorLD15A7CrDwLcLy4fRqtaTb9fbd8o8iqiEMUDse2n");
+
+/// Seed of the world-wide config entity (no wallet prefix)
+pub const CONFIG_ENTITY_SEED: &[u8] = b"config";
+
+/// World the game runs in: 2421 for devnet builds (`--features config/devnet`), else the
+/// localnet world 2
+#[cfg(feature = "devnet")]
+pub const GAME_WORLD_ID: u64 = 2421;
+#[cfg(not(feature = "devnet"))]
+pub const GAME_WORLD_ID: u64 = 2;
+
+/// Config component on the `CONFIG_ENTITY_SEED` entity of `world_id`
+/// Derived like the client's `FindEntityPda` / `FindComponentPda`
+pub fn canonical_config_address(world_id: u64) -> Pubkey {
+    let (entity, _) = Pubkey::find_program_address(
+        &[b"entity", &world_id.to_be_bytes(), &[0; 8], CONFIG_ENTITY_SEED],
+        &WORLD_PROGRAM_ID,
+    );
+    Pubkey::find_program_address(&[b"", entity.as_ref()], &crate::ID).0
+}
+
+/// Check that `key` is the game world's Config
+/// Anyone can put a Config on an entity of their own and claim its admin, so systems reading
+/// the treasury, limits or flags only trust this one
+pub fn require_canonical_config(key: &Pubkey) -> Result<()> {
+    require_keys_eq!(
+        *key,
+        canonical_config_address(GAME_WORLD_ID),
+        ConfigError::NotCanonicalConfig
+    );
+    Ok(())
+}
+
+pub use game_rules::features::{
+    DEFAULT_FEATURE_FLAGS, FEATURE_ANTI_CHEAT, FEATURE_AUTO_XP, FEATURE_GOLD_PICKUPS,
+    FEATURE_SERVER_TIME,
+};
+
+impl Config {
+    /// True if a `FEATURE_*` flag is switched on
+    pub fn is_enabled(&self, flag: u32) -> bool {
+        features::is_enabled(self.feature_flags, flag)
+    }
+
+    /// Check that a fee destination is exactly the configured treasury
+    pub fn require_treasury(&self, destination: &Pubkey) -> Result<()> {
+        let treasury = self.treasury.ok_or(ConfigError::TreasuryNotSet)?;
+        require_keys_eq!(treasury, *destination, ConfigError::WrongTreasury);
+        Ok(())
+    }
+
+    /// True if a leaderboard entry last updated at `last_update` may be resubmitted at `now`
+    pub fn submit_allowed(&self, last_update: i64, now: i64) -> bool {
+        now.saturating_sub(last_update) >= self.submit_interval_secs as i64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn canonical_config_depends_on_world() {
+        assert_eq!(canonical_config_address(2), canonical_config_address(2));
+        assert_ne!(canonical_config_address(2), canonical_config_address(2421));
+    }
+
+    #[test]
+    fn only_the_canonical_config_is_accepted() {
+        assert!(require_canonical_config(&canonical_config_address(GAME_WORLD_ID)).is_ok());
+        assert_eq!(
+            require_canonical_config(&canonical_config_address(GAME_WORLD_ID + 1)).unwrap_err(),
+            ConfigError::NotCanonicalConfig.into()
+        );
+        assert!(require_canonical_config(&Pubkey::new_unique()).is_err());
+    }
+
+    #[test]
+    fn treasury_must_be_set_and_match() {
+        let treasury = Pubkey::new_unique();
+        let mut config = Config::default();
+        assert_eq!(
+            config.require_treasury(&treasury).unwrap_err(),
+            ConfigError::TreasuryNotSet.into()
+        );
+        config.treasury = Some(treasury);
+        assert!(config.require_treasury(&treasury).is_ok());
+        assert_eq!(
+            config.require_treasury(&Pubkey::new_unique()).unwrap_err(),
+            ConfigError::WrongTreasury.into()
+        );
+    }
+}
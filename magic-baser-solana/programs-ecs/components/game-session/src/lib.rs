@@ -1,4 +1,8 @@
 use bolt_lang::*;
+use game_rules::{
+    anticheat, bosses, buffs, economy, features, loadout, progression, randomness,
+};
+use session_keys::SessionToken;
 
 declare_id!("9zbUFw8u3XzzNRA3TDQsGG2AkEuu2AQBXFYPxAZuWhTo");
 
@@ -8,9 +12,9 @@ declare_id!("9zbUFw8u3XzzNRA3TDQsGG2AkEuu2AQBXFYPxAZuWhTo");
 #[component(delegate)]
 #[derive(Default)]
 pub struct GameSession {
-    /// Reference to Player entity (owner of this session)
+    /// Wallet of the player running this session (set by `start_game`)
     pub player: Option<Pubkey>,
-    /// Selected character ID for this session
+    /// Selected character ID for this session (at most `CHARACTER_ID_MAX_LEN` bytes)
     #[max_len(20)]
     pub character_id: String,
     /// Current health points
@@ -35,4 +39,521 @@ pub struct GameSession {
     pub started_at: i64,
     /// Number of kills in this session
     pub kills: u32,
-}
\ No newline at end of file
+    /// Revives used in this session (charges and coin-funded)
+    pub revives_used: u8,
+    /// Unix timestamp of the last accepted stats update
+    pub last_update_at: i64,
+    /// XP the run's kills (with combo) have earned so far, computed on-chain
+    pub xp_allowance: u32,
+    /// Difficulty picked at start (0 = normal)
+    pub difficulty: u8,
+    /// HP at or below which the player counts as dead (copied from Config at start)
+    pub death_hp_threshold: u16,
+    /// `gold_earned` when the current wave began, for per-wave gold events
+    pub wave_start_gold: u32,
+    /// Config feature flags at start, so ER-side systems don't read the L1 config
+    pub feature_flags: u32,
+    /// `ANOMALY_*` bits already reported for this run, so each anomaly is emitted once
+    pub anomalies_reported: u8,
+    /// `SUSPICIOUS_*` bits for implausible stats updates this run (the values were clamped)
+    pub suspicious_flags: u8,
+    /// Sequence number of the last accepted stats update (0 right after `start_game`)
+    pub tick: u64,
+    /// Unix timestamp the run was paused at, 0 while running
+    pub paused_at: i64,
+    /// Total seconds the run has spent paused, left out of server-measured `time_survived`
+    pub paused_secs: u32,
+    /// Weapon ids picked this run (`game_rules::loadout::KNOWN_WEAPONS`, 0 = empty slot)
+    pub weapons: [u8; 6],
+    /// Level of the weapon in the same slot of `weapons`
+    pub weapon_levels: [u8; 6],
+    /// Passive ids picked this run (0 = empty slot)
+    pub passives: [u8; 6],
+    /// Level of the passive in the same slot of `passives`
+    pub passive_levels: [u8; 6],
+    /// Boss of the current encounter (`boss_active`) or the last one, 0 before any boss
+    pub boss_id: u8,
+    /// Remaining HP of the current boss
+    pub boss_hp: u32,
+    /// True while a boss is alive
+    pub boss_active: bool,
+    /// Unix timestamp of the boss spawn or the last accepted `damage_boss`, for its damage cap
+    pub boss_damaged_at: i64,
+    /// Highest boss id killed this run; bosses come in order, so every lower one died too
+    pub bosses_defeated: u8,
+    /// Kill streak tracked on-chain; the client's combo display reads it
+    pub combo_count: u32,
+    /// Unix timestamp the combo runs out without another kill (0 = no combo)
+    pub combo_expires_at: i64,
+    /// Part of `gold_earned` that came from the combo multiplier rather than the client
+    pub combo_bonus_gold: u32,
+    /// Damage the player dealt this run
+    pub damage_dealt: u32,
+    /// Damage the player took this run
+    pub damage_taken: u32,
+    /// HP the player healed this run
+    pub damage_healed: u32,
+    /// Buff ids in each of the `game_rules::buffs::BUFF_SLOTS` slots (0 = empty)
+    pub buff_ids: [u8; 8],
+    /// Effect strength of the buff in the same slot, negative for debuffs
+    pub buff_magnitudes: [i16; 8],
+    /// Unix timestamp the buff in the same slot runs out
+    pub buff_expires_at: [i64; 8],
+    /// Unix timestamp of the run start or the last `collect_gold`, for its earning ceiling
+    pub gold_collected_at: i64,
+    /// Randomness oracle copied from Config at start (None = randomness disabled for the run)
+    pub vrf_oracle: Option<Pubkey>,
+    /// Nonce of the latest randomness request (0 = none yet)
+    pub randomness_nonce: u64,
+    /// True from `request_randomness` until the oracle reveals its secret
+    pub randomness_pending: bool,
+    /// Fulfilled randomness not yet used by a roll (all zero = none)
+    pub pending_randomness: [u8; 32],
+    /// Save slot this session's entity was derived for (`game_rules::seeds::session_seed_suffix`)
+    pub slot: u8,
+    /// Gold pickups credited through `collect_gold` this run
+    pub pickups_collected: u32,
+    /// Basis points of `pickups_collected` paid out as bonus gold at run end (copied from Config
+    /// at start)
+    pub pickup_gold_rate_bps: u16,
+    /// Oracle's commitment (hash) to the secret it reveals for the next request (all zero = none)
+    pub randomness_commitment: [u8; 32],
+    /// Player's seed for the open or latest request, mixed with the oracle's secret
+    pub randomness_client_seed: [u8; 32],
+    /// `ROLL_*` event the open or latest request is for; only that event can use its value
+    pub randomness_event: u8,
+}
+
+/// `level` is above what `xp` reaches (kept, never lowered)
+pub const ANOMALY_LEVEL_ABOVE_XP: u8 = 1 << 0;
+
+/// Reported HP above `max_hp`
+pub const SUSPICIOUS_HP_OVER_MAX: u8 = 1 << 0;
+/// Reported wave below the current one
+pub const SUSPICIOUS_WAVE_DECREASE: u8 = 1 << 1;
+/// More kills than the time since the last update, or the enemies spawned so far, allow
+pub const SUSPICIOUS_KILL_RATE: u8 = 1 << 2;
+/// More gold than the time since the last update allows
+pub const SUSPICIOUS_GOLD_RATE: u8 = 1 << 3;
+/// More XP than the time since the last update allows
+pub const SUSPICIOUS_XP_RATE: u8 = 1 << 4;
+/// Reported wave ahead of what the run's played time allows
+pub const SUSPICIOUS_WAVE_RATE: u8 = 1 << 5;
+
+/// Longest character id that fits the `character_id` allocation
+pub const CHARACTER_ID_MAX_LEN: usize = 20;
+
+/// Expected `INIT_SPACE` of the layout above, including Bolt's trailing metadata (32 bytes)
+/// Live accounts keep the allocation they were created with, so a field change must bump
+/// this deliberately (and come with a migration) instead of silently outgrowing old accounts
+pub const GAME_SESSION_SPACE: usize = 476;
+
+const _: () = assert!(GameSession::INIT_SPACE == GAME_SESSION_SPACE);
+
+#[error_code]
+pub enum GameSessionError {
+    #[msg("Signer is neither the player nor a session key issued by the player")]
+    NotAuthorized,
+    #[msg("Unknown weapon or passive")]
+    UnknownItem,
+    #[msg("Item is already at its maximum level")]
+    ItemMaxLevel,
+    #[msg("No free loadout slot for a new item")]
+    LoadoutFull,
+    #[msg("Run's level does not allow another upgrade")]
+    NoUpgradeAvailable,
+    #[msg("Buff id, magnitude or duration out of bounds")]
+    InvalidBuff,
+    #[msg("Every buff slot holds another active buff")]
+    BuffSlotsFull,
+    #[msg("No fulfilled randomness to roll with")]
+    NoRandomness,
+    #[msg("Fulfilled randomness was requested for another event")]
+    WrongRollEvent,
+}
+
+pub use game_rules::characters::hp_regen_per_sec;
+pub use game_rules::economy::COIN_REVIVE_BASE_COST;
+pub use game_rules::loadout::{ITEM_PASSIVE, ITEM_WEAPON};
+pub use game_rules::randomness::{LUCKY_REVIVE_CHANCE_PCT, ROLL_CHEST, ROLL_ELITE, ROLL_REVIVE};
+pub use game_rules::seeds::{session_seed_suffix, MAX_SESSION_SLOTS};
+pub use game_rules::time::{duration_parts, format_duration};
+
+impl GameSession {
+    /// Largest HP increase a stats update may report after `elapsed` seconds
+    /// One extra second of allowance covers ticks that land inside the same second
+    pub fn max_hp_gain(&self, elapsed: i64) -> u16 {
+        anticheat::max_hp_gain(&self.character_id, elapsed)
+    }
+
+    /// True if a `game_rules::features::FEATURE_*` flag was on when the run started
+    pub fn is_enabled(&self, flag: u32) -> bool {
+        features::is_enabled(self.feature_flags, flag)
+    }
+
+    /// Mark an anomaly as reported; returns false if it already was this run
+    pub fn report_anomaly(&mut self, field_code: u8) -> bool {
+        let first = self.anomalies_reported & field_code == 0;
+        self.anomalies_reported |= field_code;
+        first
+    }
+
+    /// HP restored by a revive, kept above the death threshold
+    pub fn revive_hp(&self) -> u16 {
+        anticheat::revive_hp(self.max_hp, self.death_hp_threshold)
+    }
+
+    /// Bring a dead run back: clears `is_dead`, restores `revive_hp` and counts the revive
+    /// The only way out of death, which `update_stats` otherwise keeps set
+    pub fn revive(&mut self) {
+        self.is_dead = false;
+        self.hp = self.revive_hp();
+        self.revives_used = self.revives_used.saturating_add(1);
+    }
+
+    /// Gold needed for the next coin-funded revive
+    pub fn coin_revive_cost(&self) -> u32 {
+        economy::coin_revive_cost(self.revives_used)
+    }
+
+    /// True once `start_game` has run for this session (it stamps `started_at` from the clock)
+    pub fn has_started(&self) -> bool {
+        self.started_at != 0
+    }
+
+    /// Seed of the run's wave composition (`game_rules::waves::enemies_for_wave`): its start time
+    pub fn wave_seed(&self) -> u64 {
+        self.started_at as u64
+    }
+
+    /// Sum of all weapon and passive levels picked this run
+    pub fn item_levels(&self) -> u32 {
+        self.weapon_levels
+            .iter()
+            .chain(self.passive_levels.iter())
+            .map(|&level| level as u32)
+            .sum()
+    }
+
+    /// Add item `id` of `kind` (`ITEM_WEAPON` / `ITEM_PASSIVE`) at level 1, or level it up if
+    /// already picked; returns the item's new level
+    /// Each pick must be backed by a level-up (see `loadout::upgrade_allowed`)
+    pub fn apply_upgrade(&mut self, kind: u8, id: u8) -> Result<u8> {
+        require!(loadout::is_known_item(kind, id), GameSessionError::UnknownItem);
+        require!(
+            loadout::upgrade_allowed(self.level, self.item_levels()),
+            GameSessionError::NoUpgradeAvailable
+        );
+
+        let (ids, levels) = if kind == ITEM_PASSIVE {
+            (&mut self.passives, &mut self.passive_levels)
+        } else {
+            (&mut self.weapons, &mut self.weapon_levels)
+        };
+        let slot = loadout::loadout_slot(ids, id).ok_or(GameSessionError::LoadoutFull)?;
+        require!(
+            levels[slot] < loadout::max_item_level(kind),
+            GameSessionError::ItemMaxLevel
+        );
+
+        ids[slot] = id;
+        levels[slot] += 1;
+        Ok(levels[slot])
+    }
+
+    /// Spawn the run's next boss once `wave` reaches its boss wave, if no boss is alive
+    /// Bosses spawn in order, so skipping waves can't skip a boss. Returns true if one spawned
+    pub fn spawn_boss(&mut self, wave: u8, now: i64) -> bool {
+        match bosses::next_boss(self.bosses_defeated, wave) {
+            Some(boss_id) if !self.boss_active => {
+                self.boss_id = boss_id;
+                self.boss_hp = bosses::boss_max_hp(boss_id);
+                self.boss_active = true;
+                self.boss_damaged_at = now;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Largest boss damage a `damage_boss` call may report at `now`
+    pub fn max_boss_damage(&self, now: i64) -> u32 {
+        anticheat::max_boss_damage(now.saturating_sub(self.boss_damaged_at))
+    }
+
+    /// Combo multiplying this update's gains, then extended by its `new_kills` at `now`
+    /// Gains use the combo that was live before the update, like the client's pickups
+    pub fn advance_combo(&mut self, new_kills: u32, now: i64) -> u32 {
+        let active = progression::active_combo(self.combo_count, self.combo_expires_at, now);
+        (self.combo_count, self.combo_expires_at) =
+            progression::advance_combo(self.combo_count, self.combo_expires_at, new_kills, now);
+        active
+    }
+
+    /// Largest pickup `collect_gold` may credit at `now`, before the combo multiplier
+    /// The client reports `wave` through `update_stats`, so the rate uses it only as far as the
+    /// run's played time allows
+    pub fn max_gold_pickup(&self, now: i64) -> u32 {
+        let wave = self.wave.min(self.max_wave(now));
+        anticheat::max_gold_gain(wave, now.saturating_sub(self.gold_collected_at))
+    }
+
+    /// Credit `amount` gold from `pickups` pickups at `now`, scaled by the live combo like
+    /// `update_stats` gains; returns the gold actually credited
+    pub fn collect_gold(&mut self, amount: u32, pickups: u16, now: i64) -> u32 {
+        let combo = progression::active_combo(self.combo_count, self.combo_expires_at, now);
+        let credited = progression::combo_scaled(amount, combo);
+        self.combo_bonus_gold = self
+            .combo_bonus_gold
+            .saturating_add(credited.saturating_sub(amount));
+        self.gold_earned = self.gold_earned.saturating_add(credited);
+        self.gold_collected_at = now;
+        self.pickups_collected = self.pickups_collected.saturating_add(pickups as u32);
+        credited
+    }
+
+    /// Bonus gold the run's pickups convert to at its end
+    pub fn pickup_bonus_gold(&self) -> u64 {
+        economy::pickup_bonus_gold(self.pickups_collected, self.pickup_gold_rate_bps)
+    }
+
+    /// `gold_earned` as the client counts it, without the combo bonus
+    pub fn raw_gold(&self) -> u32 {
+        self.gold_earned.saturating_sub(self.combo_bonus_gold)
+    }
+
+    /// Start buff `id` for `duration_secs` from `now`, refreshing it if already active
+    /// Returns the slot it occupies
+    pub fn apply_buff(
+        &mut self,
+        id: u8,
+        magnitude: i16,
+        duration_secs: u32,
+        now: i64,
+    ) -> Result<usize> {
+        require!(
+            buffs::valid_buff(id, magnitude, duration_secs),
+            GameSessionError::InvalidBuff
+        );
+        let slot = buffs::buff_slot(&self.buff_ids, &self.buff_expires_at, id, now)
+            .ok_or(GameSessionError::BuffSlotsFull)?;
+
+        self.buff_ids[slot] = id;
+        self.buff_magnitudes[slot] = magnitude;
+        self.buff_expires_at[slot] = now.saturating_add(duration_secs as i64);
+        Ok(slot)
+    }
+
+    /// Clear buff slots that ran out by `now`
+    pub fn expire_buffs(&mut self, now: i64) {
+        let slots = self
+            .buff_ids
+            .iter_mut()
+            .zip(self.buff_magnitudes.iter_mut())
+            .zip(self.buff_expires_at.iter_mut());
+        for ((id, magnitude), expires_at) in slots {
+            if *id != 0 && now >= *expires_at {
+                (*id, *magnitude, *expires_at) = (0, 0, 0);
+            }
+        }
+    }
+
+    /// Push back the combo and buffs that were live when the run paused by `secs`
+    /// Called by `resume_game` while `paused_at` still holds the pause start
+    pub fn extend_timers(&mut self, secs: i64) {
+        if self.combo_expires_at > self.paused_at {
+            self.combo_expires_at = self.combo_expires_at.saturating_add(secs);
+        }
+        for (id, expires_at) in self.buff_ids.iter().zip(self.buff_expires_at.iter_mut()) {
+            if *id != 0 && *expires_at > self.paused_at {
+                *expires_at = expires_at.saturating_add(secs);
+            }
+        }
+    }
+
+    /// Whether fulfilled randomness requested for `event` is waiting to be rolled
+    pub fn has_roll(&self, event: u8) -> bool {
+        self.pending_randomness != [0; 32] && self.randomness_event == event
+    }
+
+    /// Roll `0..range` for `event` (`ROLL_*`) from the fulfilled randomness, using it up
+    /// Only the event named in the request can use it, so a player who sees the value can't
+    /// spend it on whichever event it favours; request and fulfill a fresh value for the next
+    /// roll. `use_revive` rolls `ROLL_REVIVE` here
+    pub fn take_roll(&mut self, event: u8, range: u32) -> Result<u32> {
+        require!(
+            self.pending_randomness != [0; 32],
+            GameSessionError::NoRandomness
+        );
+        require!(
+            self.randomness_event == event,
+            GameSessionError::WrongRollEvent
+        );
+        let value = randomness::roll(&self.pending_randomness, event, range);
+        self.pending_randomness = [0; 32];
+        Ok(value)
+    }
+
+    /// True while the run is paused
+    pub fn is_paused(&self) -> bool {
+        self.paused_at != 0
+    }
+
+    /// Highest wave the run can have reached by `now`, from its played time
+    pub fn max_wave(&self, now: i64) -> u8 {
+        anticheat::max_wave_at(self.played_secs(now))
+    }
+
+    /// Seconds of play since `started_at`, paused time excluded
+    pub fn played_secs(&self, now: i64) -> u32 {
+        now.saturating_sub(self.started_at)
+            .saturating_sub(self.paused_secs as i64)
+            .clamp(0, u32::MAX as i64) as u32
+    }
+
+    /// Check that `signer` may act on this session from `system`: the player's own wallet, or
+    /// the session signer of a live `session_keys` token the player issued for `system`
+    /// The token is the system's first remaining account; a token bound to one account must be
+    /// bound to this GameSession (`session_key`). Only tokens of our session-keys fork are
+    /// parsed, since its layout differs from the upstream program's
+    pub fn authorize_signer(
+        &self,
+        session_key: &Pubkey,
+        signer: &Pubkey,
+        session_token: Option<&AccountInfo>,
+        system: &Pubkey,
+    ) -> Result<()> {
+        let player = self.player.ok_or(GameSessionError::NotAuthorized)?;
+        if *signer == player {
+            return Ok(());
+        }
+
+        let session_token = session_token.ok_or(GameSessionError::NotAuthorized)?;
+        require_keys_eq!(
+            *session_token.owner,
+            session_keys::ID,
+            GameSessionError::NotAuthorized
+        );
+        let token = SessionToken::try_deserialize(&mut &session_token.data.borrow()[..])?;
+        token.validate_keys(
+            session_token.key,
+            signer,
+            &player,
+            system,
+            None,
+            Some(*session_key),
+        )
+    }
+
+    /// True if `started_at` lies ahead of `now`
+    /// `start_game` stamps it from the clock, so this only trips on tampered or restored state
+    pub fn started_in_future(&self, now: i64) -> bool {
+        anticheat::started_in_future(self.started_at, now)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bolt_lang::anchor_lang::solana_program::pubkey;
+
+    /// Upstream gpl_session program, whose tokens use the upstream layout
+    const UPSTREAM_SESSION_KEYS_ID: Pubkey = pubkey!("KeyspM2ssCJbqUhQ4k7sveSiY4WjnYsrXkC8oDbwde5");
+
+    #[test]
+    fn session_keys_is_our_fork() {
+        assert_ne!(session_keys::ID, UPSTREAM_SESSION_KEYS_ID);
+    }
+
+    #[test]
+    fn tokens_of_other_programs_are_rejected() {
+        let player = Pubkey::new_unique();
+        let session = GameSession {
+            player: Some(player),
+            ..Default::default()
+        };
+        let (key, owner) = (Pubkey::new_unique(), UPSTREAM_SESSION_KEYS_ID);
+        let (mut lamports, mut data) = (1_000_000, vec![0u8; 256]);
+        let token = AccountInfo::new(&key, false, false, &mut lamports, &mut data, &owner, false, 0);
+        assert_eq!(
+            session
+                .authorize_signer(&Pubkey::new_unique(), &Pubkey::new_unique(), Some(&token), &crate::ID)
+                .unwrap_err(),
+            GameSessionError::NotAuthorized.into()
+        );
+        // The player's own wallet needs no token
+        assert!(session
+            .authorize_signer(&Pubkey::new_unique(), &player, None, &crate::ID)
+            .is_ok());
+    }
+
+    #[test]
+    fn max_filled_session_fits_its_allocation() {
+        let session = GameSession {
+            player: Some(Pubkey::new_unique()),
+            character_id: "x".repeat(20),
+            vrf_oracle: Some(Pubkey::new_unique()),
+            ..Default::default()
+        };
+        let mut data = Vec::new();
+        AnchorSerialize::serialize(&session, &mut data).unwrap();
+        assert_eq!(data.len(), GAME_SESSION_SPACE);
+    }
+
+    #[test]
+    fn revive_clears_death_above_the_threshold() {
+        let mut session = GameSession {
+            max_hp: 100,
+            death_hp_threshold: 10,
+            ..Default::default()
+        };
+        session.is_dead = anticheat::resolve_is_dead(false, false, 0, session.death_hp_threshold);
+        assert!(session.is_dead);
+
+        session.revive();
+        assert!(!session.is_dead);
+        assert_eq!(session.hp, 50);
+        assert_eq!(session.revives_used, 1);
+        assert!(!anticheat::resolve_is_dead(
+            session.is_dead,
+            false,
+            session.hp,
+            session.death_hp_threshold
+        ));
+    }
+
+    #[test]
+    fn time_survived_formats_without_floats() {
+        for (secs, shown) in [(0, "0:00"), (59, "0:59"), (60, "1:00"), (3_599, "59:59")] {
+            assert_eq!(format_duration(secs), shown);
+        }
+        assert_eq!(duration_parts(u32::MAX), (71_582_788, 15));
+        assert_eq!(format_duration(u32::MAX), "1193046:28:15");
+    }
+
+    #[test]
+    fn gold_pickup_rate_ignores_a_wave_ahead_of_the_clock() {
+        let now = 1_700_000_000;
+        let session = GameSession {
+            wave: u8::MAX,
+            started_at: now - 60,
+            gold_collected_at: now - 1,
+            ..Default::default()
+        };
+        assert_eq!(session.max_gold_pickup(now), anticheat::max_gold_gain(3, 1));
+    }
+
+    #[test]
+    fn wave_jump_spawns_the_bosses_in_order() {
+        let mut session = GameSession::default();
+        // A run that skips from wave 4 to past boss 2's wave meets boss 1 first
+        assert!(session.spawn_boss(12, 0));
+        assert_eq!(session.boss_id, 1);
+        assert!(!session.spawn_boss(13, 0));
+
+        (session.boss_active, session.bosses_defeated) = (false, 1);
+        assert!(session.spawn_boss(13, 0));
+        assert_eq!(session.boss_id, 2);
+        assert_eq!(session.boss_hp, bosses::boss_max_hp(2));
+    }
+}
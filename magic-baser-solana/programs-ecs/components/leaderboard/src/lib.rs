@@ -25,4 +25,157 @@ pub struct LeaderboardEntry {
     /// Character ID used (for avatar display in leaderboard)
     #[max_len(20)]
     pub character_id: String,
-}
\ No newline at end of file
+    /// Map/environment the entry's runs were played on (0 = default map)
+    pub environment_id: u8,
+    /// Most kills in a single run
+    pub best_kills: u32,
+    /// Best single-run score (`game_rules::scoring::compute_score`); primary ranking key
+    pub best_score: u64,
+    /// `KNOWN_CHARACTERS` index of the player's last character (`u8::MAX` = unknown)
+    pub character_index: u8,
+}
+
+/// Expected `INIT_SPACE` of the LeaderboardEntry layout, Bolt metadata included
+pub const LEADERBOARD_ENTRY_SPACE: usize = 152;
+
+const _: () = assert!(LeaderboardEntry::INIT_SPACE == LEADERBOARD_ENTRY_SPACE);
+
+impl LeaderboardEntry {
+    /// Promote a run's kill count if it beats the stored best
+    pub fn record_kills(&mut self, kills: u32) {
+        self.best_kills = self.best_kills.max(kills);
+    }
+
+    /// Promote a run's score if it beats the stored best
+    pub fn record_score(&mut self, score: u64) {
+        self.best_score = self.best_score.max(score);
+    }
+}
+
+/// Canonical per-map filter for leaderboard queries
+pub fn matches_environment(entry: &LeaderboardEntry, env: u8) -> bool {
+    entry.environment_id == env
+}
+
+/// Canonical per-character filter for leaderboard queries
+pub fn matches_character(entry: &LeaderboardEntry, character_index: u8) -> bool {
+    entry.character_index == character_index
+}
+
+/// Canonical leaderboard order, best first: score, wave, time, gold, then the entry's own
+/// address (bytes). The address tie-break makes the order total, so paged fetches never shuffle
+/// equal entries. The app's `compareLeaderboardEntries` mirrors it
+pub fn compare_entries(
+    a_key: &Pubkey,
+    a: &LeaderboardEntry,
+    b_key: &Pubkey,
+    b: &LeaderboardEntry,
+) -> core::cmp::Ordering {
+    b.best_score
+        .cmp(&a.best_score)
+        .then(b.best_wave.cmp(&a.best_wave))
+        .then(b.best_time.cmp(&a.best_time))
+        .then(b.total_gold.cmp(&a.total_gold))
+        .then(a_key.to_bytes().cmp(&b_key.to_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::cmp::Ordering;
+
+    #[test]
+    fn max_filled_entry_fits_its_allocation() {
+        let entry = LeaderboardEntry {
+            player: Some(Pubkey::new_unique()),
+            name: "x".repeat(20),
+            character_id: "x".repeat(20),
+            ..Default::default()
+        };
+        let mut data = Vec::new();
+        AnchorSerialize::serialize(&entry, &mut data).unwrap();
+        assert_eq!(data.len(), LEADERBOARD_ENTRY_SPACE);
+    }
+
+    #[test]
+    fn better_stats_rank_first() {
+        let (a_key, b_key) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let low = LeaderboardEntry {
+            best_score: 10,
+            best_wave: 9,
+            ..Default::default()
+        };
+        let high = LeaderboardEntry {
+            best_score: 11,
+            ..Default::default()
+        };
+        assert_eq!(compare_entries(&a_key, &high, &b_key, &low), Ordering::Less);
+        assert_eq!(compare_entries(&a_key, &low, &b_key, &high), Ordering::Greater);
+    }
+
+    #[test]
+    fn equal_stats_are_ordered_by_entry_address() {
+        let entry = LeaderboardEntry {
+            best_score: 10,
+            best_wave: 3,
+            ..Default::default()
+        };
+        let mut keys: Vec<Pubkey> = (0..5).map(|_| Pubkey::new_unique()).rev().collect();
+        let mut reversed = keys.clone();
+        reversed.reverse();
+        for order in [&mut keys, &mut reversed] {
+            order.sort_by(|a, b| compare_entries(a, &entry, b, &entry));
+        }
+        assert_eq!(keys, reversed);
+        assert!(keys.windows(2).all(|pair| pair[0].to_bytes() < pair[1].to_bytes()));
+        assert_eq!(compare_entries(&keys[0], &entry, &keys[0], &entry), Ordering::Equal);
+    }
+
+    #[test]
+    fn environment_filter_keeps_only_that_map() {
+        let entries: Vec<LeaderboardEntry> = [0, 1, 0, 2]
+            .into_iter()
+            .map(|environment_id| LeaderboardEntry {
+                environment_id,
+                ..Default::default()
+            })
+            .collect();
+        let on_default = entries.iter().filter(|entry| matches_environment(entry, 0)).count();
+        assert_eq!(on_default, 2);
+        assert!(matches_environment(&entries[3], 2));
+        assert!(!matches_environment(&entries[3], 1));
+    }
+
+    #[test]
+    fn character_filter_keeps_only_that_character() {
+        let entry = LeaderboardEntry {
+            character_index: 3,
+            ..Default::default()
+        };
+        assert!(matches_character(&entry, 3));
+        assert!(!matches_character(&entry, 0));
+        assert!(!matches_character(&entry, u8::MAX));
+    }
+
+    #[test]
+    fn record_kills_keeps_the_best_run() {
+        let mut entry = LeaderboardEntry::default();
+        entry.record_kills(40);
+        assert_eq!(entry.best_kills, 40);
+        entry.record_kills(12);
+        assert_eq!(entry.best_kills, 40);
+        entry.record_kills(41);
+        assert_eq!(entry.best_kills, 41);
+    }
+
+    #[test]
+    fn record_score_keeps_the_best_run() {
+        let mut entry = LeaderboardEntry::default();
+        entry.record_score(5_000);
+        assert_eq!(entry.best_score, 5_000);
+        entry.record_score(4_999);
+        assert_eq!(entry.best_score, 5_000);
+        entry.record_score(u64::MAX);
+        assert_eq!(entry.best_score, u64::MAX);
+    }
+}
@@ -0,0 +1,95 @@
+use bolt_lang::*;
+
+declare_id!("7aSvn5uavbTqJjR55dX1aejtDTngUxS3Xr6Tpqcm84mW");
+
+/// PlayerExtras component - persists on L1, on the player entity next to Player
+/// Holds the player fields added after launch: live Player accounts keep the size they were
+/// created with and can't be reallocated from a system, so the Player layout stays frozen
+#[component]
+#[derive(Default)]
+pub struct PlayerExtras {
+    /// Client-defined preferences blob (UI, controls); never interpreted on-chain
+    pub settings: [u8; 32],
+    /// Timestamp of the last run that improved `best_wave` or `best_time` (0 = never)
+    pub best_updated_at: i64,
+    /// Set once the first-game starter revives have been granted
+    pub starter_revives_granted: bool,
+}
+
+/// Expected `INIT_SPACE` of the PlayerExtras layout, Bolt metadata included
+pub const PLAYER_EXTRAS_SPACE: usize = 73;
+
+const _: () = assert!(PlayerExtras::INIT_SPACE == PLAYER_EXTRAS_SPACE);
+
+#[error_code]
+pub enum PlayerExtrasError {
+    #[msg("Player entity must be passed as an extra account")]
+    MissingEntity,
+    #[msg("Player and PlayerExtras are not on the same entity")]
+    WrongEntity,
+}
+
+/// Address of `component_program`'s component on `entity`, as `InitializeComponent` creates it
+pub fn component_address(entity: &Pubkey, component_program: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"", entity.as_ref()], component_program).0
+}
+
+/// Check that `player` and `extras` are the Player and PlayerExtras of `entity`
+/// Systems get them as separate accounts, so a fresh PlayerExtras could otherwise stand in for
+/// the player's own (and grant the starter revives again)
+pub fn require_player_entity(
+    entity: Option<&Pubkey>,
+    player: &Pubkey,
+    extras: &Pubkey,
+) -> Result<()> {
+    let entity = entity.ok_or(PlayerExtrasError::MissingEntity)?;
+    require_keys_eq!(
+        component_address(entity, &player::ID),
+        *player,
+        PlayerExtrasError::WrongEntity
+    );
+    require_keys_eq!(
+        component_address(entity, &crate::ID),
+        *extras,
+        PlayerExtrasError::WrongEntity
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn components_of_the_same_entity_pass() {
+        let entity = Pubkey::new_unique();
+        let player = component_address(&entity, &player::ID);
+        let extras = component_address(&entity, &crate::ID);
+        assert!(require_player_entity(Some(&entity), &player, &extras).is_ok());
+    }
+
+    #[test]
+    fn extras_of_another_entity_are_rejected() {
+        let entity = Pubkey::new_unique();
+        let player = component_address(&entity, &player::ID);
+        let other = component_address(&Pubkey::new_unique(), &crate::ID);
+        assert_eq!(
+            require_player_entity(Some(&entity), &player, &other).unwrap_err(),
+            PlayerExtrasError::WrongEntity.into()
+        );
+        assert_eq!(
+            require_player_entity(None, &player, &other).unwrap_err(),
+            PlayerExtrasError::MissingEntity.into()
+        );
+    }
+
+    #[test]
+    fn fresh_extras_hold_the_pre_migration_defaults() {
+        let extras = PlayerExtras::default();
+        assert_eq!(extras.settings, [0; 32]);
+        assert_eq!(extras.best_updated_at, 0);
+        // A migrated veteran has games_played > 0, so start_game never grants the starter
+        // revives against this unset flag
+        assert!(!extras.starter_revives_granted);
+    }
+}
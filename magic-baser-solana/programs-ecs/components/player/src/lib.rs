@@ -9,7 +9,7 @@ declare_id!("6URqfQrK5GTfc9HuyT43B2CLx38ZR4uu9nAhXdDmoy7M");
 pub struct Player {
     /// Wallet authority for this player
     pub authority: Option<Pubkey>,
-    /// Player display name (max 20 chars)
+    /// Player display name (at most `PLAYER_NAME_MAX_LEN` bytes)
     #[max_len(20)]
     pub name: String,
     /// List of owned character IDs ["imelda", "antonio", ...]
@@ -30,4 +30,117 @@ pub struct Player {
     /// Last character ID used (for leaderboard display)
     #[max_len(20)]
     pub last_character_id: String,
-}
\ No newline at end of file
+}
+
+/// Longest display name that fits the `name` allocation
+pub const PLAYER_NAME_MAX_LEN: usize = 20;
+
+/// Expected `INIT_SPACE` of the Player layout, Bolt metadata included
+/// Live accounts keep the size they were created with, so the layout is frozen; new player
+/// fields go in the `player-extras` component
+pub const PLAYER_SPACE: usize = 343;
+
+const _: () = assert!(Player::INIT_SPACE == PLAYER_SPACE);
+
+pub use game_rules::characters::{
+    add_owned_character, format_character_list, is_known_character, normalize_character_list,
+    parse_character_list, KNOWN_CHARACTERS, STARTER_CHARACTER,
+};
+
+/// Capacity of the `owned_characters` allocation
+pub const OWNED_CHARACTERS_MAX_LEN: usize = 200;
+
+#[error_code]
+pub enum PlayerError {
+    #[msg("Player is already initialized; created_at cannot change")]
+    CreatedAtImmutable,
+}
+
+impl Player {
+    /// Stamp the account's creation time; only ever allowed on a fresh (zero) `created_at`
+    /// Every init or migration path goes through here so account age can't be falsified
+    pub fn init_created_at(&mut self, now: i64) -> Result<()> {
+        require!(self.created_at == 0, PlayerError::CreatedAtImmutable);
+        self.created_at = now;
+        Ok(())
+    }
+
+    /// Owned character ids parsed from the `owned_characters` JSON string
+    pub fn owned_character_ids(&self) -> Vec<&str> {
+        parse_character_list(&self.owned_characters)
+    }
+
+    /// Add a character to `owned_characters` without outgrowing its allocation
+    /// Returns false (and changes nothing) if it is already owned, unknown, or wouldn't fit
+    pub fn unlock_character(&mut self, id: &str) -> bool {
+        if !is_known_character(id) || self.owned_character_ids().contains(&id) {
+            return false;
+        }
+        let updated = add_owned_character(&self.owned_characters, id);
+        if updated.len() > OWNED_CHARACTERS_MAX_LEN {
+            return false;
+        }
+        self.owned_characters = updated;
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn player(owned_characters: &str) -> Player {
+        Player {
+            owned_characters: owned_characters.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn unlock_adds_a_known_character_in_canonical_form() {
+        let mut player = player(r#"["antonio","imelda","antonio"]"#);
+        assert!(player.unlock_character("vitalis"));
+        assert_eq!(player.owned_characters, r#"["imelda","antonio","vitalis"]"#);
+    }
+
+    #[test]
+    fn unlock_rejects_unknown_and_owned_characters() {
+        let mut player = player(r#"["imelda","antonio"]"#);
+        assert!(!player.unlock_character("bogus"));
+        assert!(!player.unlock_character("antonio"));
+        assert_eq!(player.owned_characters, r#"["imelda","antonio"]"#);
+    }
+
+    #[test]
+    fn every_character_fits_the_allocation() {
+        assert!(format_character_list(&KNOWN_CHARACTERS).len() <= OWNED_CHARACTERS_MAX_LEN);
+    }
+
+    #[test]
+    fn max_filled_player_fits_its_allocation() {
+        let player = Player {
+            authority: Some(Pubkey::new_unique()),
+            name: "x".repeat(PLAYER_NAME_MAX_LEN),
+            owned_characters: "x".repeat(OWNED_CHARACTERS_MAX_LEN),
+            last_character_id: "x".repeat(20),
+            ..Default::default()
+        };
+        let mut data = Vec::new();
+        AnchorSerialize::serialize(&player, &mut data).unwrap();
+        assert_eq!(data.len(), PLAYER_SPACE);
+    }
+
+    #[test]
+    fn created_at_is_set_once_and_never_overwritten() {
+        let mut player = Player::default();
+        player.init_created_at(1_700_000_000).unwrap();
+        assert_eq!(player.created_at, 1_700_000_000);
+
+        // A re-run init, or any later path, is refused and leaves the original stamp
+        assert_eq!(
+            player.init_created_at(1_800_000_000).unwrap_err(),
+            PlayerError::CreatedAtImmutable.into()
+        );
+        assert_eq!(player.created_at, 1_700_000_000);
+    }
+}
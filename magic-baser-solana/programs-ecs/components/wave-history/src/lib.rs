@@ -0,0 +1,106 @@
+use bolt_lang::*;
+
+declare_id!("A3Y1nnUGN8L2qnENirtMwhWEF52yY5W5WtdrvwLf7oGA");
+
+/// Waves a WaveHistory keeps; older ones are overwritten
+pub const WAVE_HISTORY_LEN: usize = 10;
+
+/// WaveHistory component - delegated to Ephemeral Rollup (ER) with the GameSession
+/// Ring buffer of the run's last `WAVE_HISTORY_LEN` finished waves, appended by `update_stats`
+/// Each entry holds the run totals when that wave ended; consecutive entries give per-wave deltas
+/// Lives on the session entity next to GameSession
+#[component(delegate)]
+#[derive(Default)]
+pub struct WaveHistory {
+    /// `started_at` of the run the entries belong to; a new run clears the buffer
+    pub started_at: i64,
+    /// Slot the next finished wave is written to
+    pub head: u8,
+    /// Filled slots, at most `WAVE_HISTORY_LEN`
+    pub len: u8,
+    /// Wave number of each entry
+    pub waves: [u8; WAVE_HISTORY_LEN],
+    /// Run kills when the wave ended
+    pub kills: [u32; WAVE_HISTORY_LEN],
+    /// Run gold when the wave ended
+    pub gold: [u32; WAVE_HISTORY_LEN],
+    /// Run time survived (seconds) when the wave ended
+    pub time: [u32; WAVE_HISTORY_LEN],
+}
+
+/// Expected `INIT_SPACE` of the WaveHistory layout, Bolt metadata included
+pub const WAVE_HISTORY_SPACE: usize = 172;
+
+const _: () = assert!(WaveHistory::INIT_SPACE == WAVE_HISTORY_SPACE);
+
+impl WaveHistory {
+    /// Record a finished wave, clearing entries left over from an earlier run first
+    pub fn push(&mut self, started_at: i64, wave: u8, kills: u32, gold: u32, time: u32) {
+        // Field by field: the component also carries Bolt's metadata, which must survive
+        if self.started_at != started_at {
+            self.started_at = started_at;
+            self.head = 0;
+            self.len = 0;
+        }
+
+        let slot = self.head as usize;
+        self.waves[slot] = wave;
+        self.kills[slot] = kills;
+        self.gold[slot] = gold;
+        self.time[slot] = time;
+        self.head = ((slot + 1) % WAVE_HISTORY_LEN) as u8;
+        self.len = (self.len as usize + 1).min(WAVE_HISTORY_LEN) as u8;
+    }
+
+    /// Entries as `(wave, kills, gold, time)`, oldest first
+    pub fn entries(&self) -> impl Iterator<Item = (u8, u32, u32, u32)> + '_ {
+        let start = self.head as usize + WAVE_HISTORY_LEN - self.len as usize;
+        (start..start + self.len as usize).map(move |i| {
+            let slot = i % WAVE_HISTORY_LEN;
+            (self.waves[slot], self.kills[slot], self.gold[slot], self.time[slot])
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STARTED_AT: i64 = 1_700_000_000;
+
+    fn history_through(last_wave: u8) -> WaveHistory {
+        let mut history = WaveHistory::default();
+        for wave in 1..=last_wave {
+            history.push(STARTED_AT, wave, wave as u32 * 10, wave as u32 * 100, wave as u32 * 60);
+        }
+        history
+    }
+
+    #[test]
+    fn entries_come_back_oldest_first() {
+        let history = history_through(3);
+        let entries: Vec<_> = history.entries().collect();
+        assert_eq!(entries, [(1, 10, 100, 60), (2, 20, 200, 120), (3, 30, 300, 180)]);
+    }
+
+    #[test]
+    fn full_buffer_overwrites_the_oldest_waves() {
+        let last_wave = WAVE_HISTORY_LEN as u8 + 3;
+        let history = history_through(last_wave);
+        assert_eq!(history.len as usize, WAVE_HISTORY_LEN);
+        assert_eq!(history.head, 3);
+
+        let waves: Vec<u8> = history.entries().map(|(wave, ..)| wave).collect();
+        let expected: Vec<u8> = (4..=last_wave).collect();
+        assert_eq!(waves, expected);
+        assert_eq!(history.entries().last(), Some((last_wave, 130, 1_300, 780)));
+    }
+
+    #[test]
+    fn new_run_clears_the_previous_entries() {
+        let mut history = history_through(WAVE_HISTORY_LEN as u8 + 3);
+        history.push(STARTED_AT + 1, 1, 5, 50, 30);
+        assert_eq!(history.len, 1);
+        assert_eq!(history.entries().collect::<Vec<_>>(), [(1, 5, 50, 30)]);
+    }
+}
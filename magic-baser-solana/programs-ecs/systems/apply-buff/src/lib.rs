@@ -0,0 +1,78 @@
+use bolt_lang::*;
+use game_session::GameSession;
+
+declare_id!("F39UJ5cSstzJJNdfAgoEAsqtWMLJ4DCgbw7NisTg5Nz1");
+
+#[error_code]
+pub enum ApplyBuffError {
+    #[msg("Invalid arguments")]
+    InvalidArguments,
+    #[msg("Session not active")]
+    SessionNotActive,
+    #[msg("Player is dead")]
+    PlayerDead,
+    #[msg("Session is paused")]
+    SessionPaused,
+}
+
+/// Emitted when a buff starts or is refreshed
+#[event]
+pub struct BuffApplied {
+    pub session: Pubkey,
+    pub id: u8,
+    pub magnitude: i16,
+    pub expires_at: i64,
+}
+
+/// Args: id(1) + magnitude(2, signed) + duration_secs(4)
+pub const APPLY_BUFF_ARGS_LEN: usize = 7;
+
+#[system]
+pub mod apply_buff {
+    /// Start a timed buff or debuff (consumable, shrine) in one of the run's buff slots
+    /// Runs on the ER; re-applying an active buff refreshes it, and `update_stats` clears
+    /// buffs once they run out
+    pub fn execute(ctx: Context<Components>, args: Vec<u8>) -> Result<Components> {
+        require!(
+            args.len() == APPLY_BUFF_ARGS_LEN,
+            ApplyBuffError::InvalidArguments
+        );
+        let id = args[0];
+        let magnitude = i16::from_le_bytes([args[1], args[2]]);
+        let duration_secs = u32::from_le_bytes([args[3], args[4], args[5], args[6]]);
+
+        let session_key = ctx.accounts.game_session.key();
+        let signer = ctx.accounts.authority.key();
+        let session = &mut ctx.accounts.game_session;
+
+        require!(session.is_active, ApplyBuffError::SessionNotActive);
+        require!(!session.is_dead, ApplyBuffError::PlayerDead);
+        require!(!session.is_paused(), ApplyBuffError::SessionPaused);
+
+        // The player's wallet or the player's session token for this system
+        session.authorize_signer(
+            &session_key,
+            &signer,
+            ctx.remaining_accounts.first(),
+            &crate::ID,
+        )?;
+
+        let now = Clock::get()?.unix_timestamp;
+        session.expire_buffs(now);
+        let slot = session.apply_buff(id, magnitude, duration_secs, now)?;
+
+        emit!(BuffApplied {
+            session: session_key,
+            id,
+            magnitude,
+            expires_at: session.buff_expires_at[slot],
+        });
+
+        Ok(ctx.accounts)
+    }
+
+    #[system_input]
+    pub struct Components {
+        pub game_session: GameSession,
+    }
+}
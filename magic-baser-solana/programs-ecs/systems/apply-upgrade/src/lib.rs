@@ -0,0 +1,74 @@
+use bolt_lang::*;
+use game_session::GameSession;
+
+declare_id!("FAxMNd8jLSdC7CJyxC5iFEpVVNfw9L6npWG77PgMJGqH");
+
+#[error_code]
+pub enum ApplyUpgradeError {
+    #[msg("Invalid arguments")]
+    InvalidArguments,
+    #[msg("Session not active")]
+    SessionNotActive,
+    #[msg("Player is dead")]
+    PlayerDead,
+    #[msg("Session is paused")]
+    SessionPaused,
+}
+
+/// Emitted for every accepted pick, for item-based achievements
+#[event]
+pub struct UpgradeApplied {
+    pub session: Pubkey,
+    /// `ITEM_WEAPON` or `ITEM_PASSIVE`
+    pub kind: u8,
+    pub id: u8,
+    pub level: u8,
+}
+
+/// Args: kind(1) + id(1)
+pub const APPLY_UPGRADE_ARGS_LEN: usize = 2;
+
+#[system]
+pub mod apply_upgrade {
+    /// Record a weapon or passive picked on level-up (the starting weapon is the first pick)
+    /// Runs on the ER next to `update_stats`; picks beyond what the run's level allows fail
+    pub fn execute(ctx: Context<Components>, args: Vec<u8>) -> Result<Components> {
+        require!(
+            args.len() == APPLY_UPGRADE_ARGS_LEN,
+            ApplyUpgradeError::InvalidArguments
+        );
+        let (kind, id) = (args[0], args[1]);
+
+        let session_key = ctx.accounts.game_session.key();
+        let signer = ctx.accounts.authority.key();
+        let session = &mut ctx.accounts.game_session;
+
+        require!(session.is_active, ApplyUpgradeError::SessionNotActive);
+        require!(!session.is_dead, ApplyUpgradeError::PlayerDead);
+        require!(!session.is_paused(), ApplyUpgradeError::SessionPaused);
+
+        // The player's wallet or the player's session token for this system
+        session.authorize_signer(
+            &session_key,
+            &signer,
+            ctx.remaining_accounts.first(),
+            &crate::ID,
+        )?;
+
+        let level = session.apply_upgrade(kind, id)?;
+
+        emit!(UpgradeApplied {
+            session: session_key,
+            kind,
+            id,
+            level,
+        });
+
+        Ok(ctx.accounts)
+    }
+
+    #[system_input]
+    pub struct Components {
+        pub game_session: GameSession,
+    }
+}
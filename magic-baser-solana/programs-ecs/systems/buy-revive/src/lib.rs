@@ -0,0 +1,54 @@
+use bolt_lang::*;
+use config::{require_canonical_config, Config};
+use player::Player;
+
+declare_id!("C4oHNbtesAkYcWC2qtepcjMQLUvBtk8quohPBtWvZaTf");
+
+/// Price of one revive charge (0.01 SOL)
+pub const REVIVE_PRICE_LAMPORTS: u64 = 10_000_000;
+
+#[error_code]
+pub enum BuyReviveError {
+    #[msg("Treasury and system program must be passed as extra accounts")]
+    MissingAccounts,
+    #[msg("Revive limit reached")]
+    TooManyRevives,
+}
+
+#[system]
+pub mod buy_revive {
+    /// Buy one revive charge, paying SOL into the configured treasury
+    /// Extra accounts: [treasury (writable), system_program]
+    pub fn execute(ctx: Context<Components>, _args: Vec<u8>) -> Result<Components> {
+        let treasury = ctx.remaining_accounts.first().ok_or(BuyReviveError::MissingAccounts)?;
+        let system_program = ctx.remaining_accounts.get(1).ok_or(BuyReviveError::MissingAccounts)?;
+
+        // Fees only ever go to the world config's treasury
+        require_canonical_config(&ctx.accounts.config.key())?;
+        ctx.accounts.config.require_treasury(treasury.key)?;
+
+        let player = &mut ctx.accounts.player;
+        require!(player.revives < u8::MAX, BuyReviveError::TooManyRevives);
+
+        anchor_lang::system_program::transfer(
+            CpiContext::new(
+                system_program.clone(),
+                anchor_lang::system_program::Transfer {
+                    from: ctx.accounts.authority.to_account_info(),
+                    to: treasury.clone(),
+                },
+            ),
+            REVIVE_PRICE_LAMPORTS,
+        )?;
+
+        player.revives += 1;
+
+        Ok(ctx.accounts)
+    }
+
+    #[system_input]
+    pub struct Components {
+        pub player: Player,
+        pub config: Config,
+    }
+}
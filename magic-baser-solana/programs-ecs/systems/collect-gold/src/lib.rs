@@ -0,0 +1,126 @@
+use bolt_lang::*;
+use game_rules::features::FEATURE_GOLD_PICKUPS;
+use game_session::GameSession;
+
+declare_id!("8JYA9dkoC37RU2TfDMYiSUt6dUAVQPSiNg5Uc6UHrXDw");
+
+#[error_code]
+pub enum CollectGoldError {
+    #[msg("Invalid arguments")]
+    InvalidArguments,
+    #[msg("Session not active")]
+    SessionNotActive,
+    #[msg("Player is dead")]
+    PlayerDead,
+    #[msg("Session is paused")]
+    SessionPaused,
+    #[msg("Gold pickups are not enabled for this run")]
+    GoldPickupsDisabled,
+    #[msg("Gold exceeds what the time since the last pickup allows at this wave")]
+    GoldRateExceeded,
+}
+
+/// Args: amount(4), gold picked up since the last call, + optional pickups(2), how many pickups
+/// it came from (one if omitted); every pickup is worth at least one gold
+pub const COLLECT_GOLD_ARGS_LEN: usize = 4;
+pub const COLLECT_GOLD_ARGS_MAX_LEN: usize = COLLECT_GOLD_ARGS_LEN + 2;
+
+/// Amount and pickup count from `args`; more pickups than gold can't be genuine
+pub fn parse_collect_args(args: &[u8]) -> Result<(u32, u16)> {
+    require!(
+        matches!(args.len(), COLLECT_GOLD_ARGS_LEN | COLLECT_GOLD_ARGS_MAX_LEN),
+        CollectGoldError::InvalidArguments
+    );
+    let amount = u32::from_le_bytes(args[..COLLECT_GOLD_ARGS_LEN].try_into().unwrap());
+    let pickups = match args.get(COLLECT_GOLD_ARGS_LEN..) {
+        Some(pickups) if !pickups.is_empty() => u16::from_le_bytes(pickups.try_into().unwrap()),
+        _ => u16::from(amount > 0),
+    };
+    require!(pickups as u32 <= amount, CollectGoldError::InvalidArguments);
+    Ok((amount, pickups))
+}
+
+/// A live, unpaused run started with `FEATURE_GOLD_PICKUPS` on
+pub fn require_can_collect(session: &GameSession) -> Result<()> {
+    require!(session.is_active, CollectGoldError::SessionNotActive);
+    require!(!session.is_dead, CollectGoldError::PlayerDead);
+    require!(!session.is_paused(), CollectGoldError::SessionPaused);
+    require!(session.is_enabled(FEATURE_GOLD_PICKUPS), CollectGoldError::GoldPickupsDisabled);
+    Ok(())
+}
+
+#[system]
+pub mod collect_gold {
+    /// Credit gold picked up since the last call
+    /// Runs on the ER for runs started with `FEATURE_GOLD_PICKUPS`, where `update_stats` no
+    /// longer moves gold. The amount is capped per second by wave, so a modified client can't
+    /// inflate the gold that feeds the persistent economy
+    pub fn execute(ctx: Context<Components>, args: Vec<u8>) -> Result<Components> {
+        let (amount, pickups) = parse_collect_args(&args)?;
+
+        let session_key = ctx.accounts.game_session.key();
+        let signer = ctx.accounts.authority.key();
+        let session = &mut ctx.accounts.game_session;
+
+        require_can_collect(session)?;
+
+        // The player's wallet or the player's session token for this system
+        session.authorize_signer(
+            &session_key,
+            &signer,
+            ctx.remaining_accounts.first(),
+            &crate::ID,
+        )?;
+
+        let now = Clock::get()?.unix_timestamp;
+        require!(
+            amount <= session.max_gold_pickup(now),
+            CollectGoldError::GoldRateExceeded
+        );
+        session.collect_gold(amount, pickups, now);
+
+        Ok(ctx.accounts)
+    }
+
+    #[system_input]
+    pub struct Components {
+        pub game_session: GameSession,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(amount: u32, pickups: Option<u16>) -> Vec<u8> {
+        let mut args = amount.to_le_bytes().to_vec();
+        if let Some(pickups) = pickups {
+            args.extend_from_slice(&pickups.to_le_bytes());
+        }
+        args
+    }
+
+    #[test]
+    fn pickups_default_to_one_per_call() {
+        assert_eq!(parse_collect_args(&args(25, None)).unwrap(), (25, 1));
+        assert_eq!(parse_collect_args(&args(0, None)).unwrap(), (0, 0));
+        assert_eq!(parse_collect_args(&args(25, Some(5))).unwrap(), (25, 5));
+    }
+
+    #[test]
+    fn more_pickups_than_gold_or_bad_length_is_rejected() {
+        let invalid: Error = CollectGoldError::InvalidArguments.into();
+        assert_eq!(parse_collect_args(&args(4, Some(5))).unwrap_err(), invalid);
+        assert_eq!(parse_collect_args(&[1, 0, 0]).unwrap_err(), invalid);
+        assert_eq!(parse_collect_args(&[1, 0, 0, 0, 1]).unwrap_err(), invalid);
+    }
+
+    #[test]
+    fn collected_pickups_are_counted() {
+        let mut session = GameSession::default();
+        session.collect_gold(25, 5, 10);
+        session.collect_gold(3, 1, 11);
+        assert_eq!(session.pickups_collected, 6);
+        assert_eq!(session.gold_earned, 28);
+    }
+}
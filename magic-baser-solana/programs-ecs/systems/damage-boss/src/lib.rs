@@ -0,0 +1,86 @@
+use bolt_lang::*;
+use game_session::GameSession;
+
+declare_id!("J5HHNtcWZQgUhjxdAkRd6tiWWwXHi68UNRp3Rvpv2pR9");
+
+#[error_code]
+pub enum DamageBossError {
+    #[msg("Invalid arguments")]
+    InvalidArguments,
+    #[msg("Session not active")]
+    SessionNotActive,
+    #[msg("Player is dead")]
+    PlayerDead,
+    #[msg("Session is paused")]
+    SessionPaused,
+    #[msg("No boss is alive")]
+    NoActiveBoss,
+    #[msg("Damage exceeds what the time since the last hit allows")]
+    DamageTooHigh,
+}
+
+/// Emitted when a boss's HP reaches zero
+#[event]
+pub struct BossDefeated {
+    pub session: Pubkey,
+    pub boss_id: u8,
+    pub wave: u8,
+}
+
+/// Args: damage(4)
+pub const DAMAGE_BOSS_ARGS_LEN: usize = 4;
+
+#[system]
+pub mod damage_boss {
+    /// Apply damage dealt to the run's boss since the last call
+    /// Runs on the ER; damage is capped by the time since the spawn or last hit, so a kill takes
+    /// at least as long as the strongest loadout needs. A kill unlocks `defeat_boss` rewards
+    pub fn execute(ctx: Context<Components>, args: Vec<u8>) -> Result<Components> {
+        let damage = <[u8; DAMAGE_BOSS_ARGS_LEN]>::try_from(args.as_slice())
+            .map(u32::from_le_bytes)
+            .map_err(|_| DamageBossError::InvalidArguments)?;
+
+        let session_key = ctx.accounts.game_session.key();
+        let signer = ctx.accounts.authority.key();
+        let session = &mut ctx.accounts.game_session;
+
+        require!(session.is_active, DamageBossError::SessionNotActive);
+        require!(!session.is_dead, DamageBossError::PlayerDead);
+        require!(!session.is_paused(), DamageBossError::SessionPaused);
+        require!(session.boss_active, DamageBossError::NoActiveBoss);
+
+        // The player's wallet or the player's session token for this system
+        session.authorize_signer(
+            &session_key,
+            &signer,
+            ctx.remaining_accounts.first(),
+            &crate::ID,
+        )?;
+
+        let now = Clock::get()?.unix_timestamp;
+        require!(
+            damage <= session.max_boss_damage(now),
+            DamageBossError::DamageTooHigh
+        );
+
+        session.boss_hp = session.boss_hp.saturating_sub(damage);
+        session.boss_damaged_at = now;
+
+        if session.boss_hp == 0 {
+            session.boss_active = false;
+            session.bosses_defeated = session.bosses_defeated.max(session.boss_id);
+            emit!(BossDefeated {
+                session: session_key,
+                boss_id: session.boss_id,
+                wave: session.wave,
+            });
+        }
+
+        Ok(ctx.accounts)
+    }
+
+    #[system_input]
+    pub struct Components {
+        pub game_session: GameSession,
+    }
+}
@@ -0,0 +1,138 @@
+use bolt_lang::*;
+use game_rules::bosses::boss_reward_character;
+use game_rules::characters::character_index;
+use game_session::GameSession;
+use player::Player;
+
+declare_id!("BvBwP8igSi9zLBQTWAkA4FpmutCxwtNJuCPC2jvUJbDQ");
+
+#[error_code]
+pub enum DefeatBossError {
+    #[msg("Invalid arguments")]
+    InvalidArguments,
+    #[msg("Session not active")]
+    SessionNotActive,
+    #[msg("Signer does not own this player and session")]
+    NotAuthority,
+    #[msg("Run has not defeated this boss")]
+    BossNotDefeated,
+}
+
+/// Emitted when a boss defeat unlocks a character for the first time
+#[event]
+pub struct CharacterUnlocked {
+    pub player: Pubkey,
+    pub boss_id: u8,
+    pub character_index: u8,
+}
+
+/// Args: boss_id(1), 1-based
+pub const DEFEAT_BOSS_ARGS_LEN: usize = 1;
+
+#[system]
+pub mod defeat_boss {
+    /// Reward a boss defeat by unlocking its character, if not already owned
+    /// Repeat defeats (or bosses without a reward) leave the player unchanged
+    pub fn execute(ctx: Context<Components>, args: Vec<u8>) -> Result<Components> {
+        require!(args.len() == DEFEAT_BOSS_ARGS_LEN, DefeatBossError::InvalidArguments);
+        let boss_id = args[0];
+        require!(boss_id > 0, DefeatBossError::InvalidArguments);
+
+        let authority = ctx.accounts.authority.key();
+        let session = &ctx.accounts.game_session;
+        let player = &mut ctx.accounts.player;
+
+        if let Some(character_index) = reward_boss(player, session, authority, boss_id)? {
+            emit!(CharacterUnlocked {
+                player: authority,
+                boss_id,
+                character_index,
+            });
+        }
+
+        Ok(ctx.accounts)
+    }
+
+    #[system_input]
+    pub struct Components {
+        pub game_session: GameSession,
+        pub player: Player,
+    }
+}
+
+/// Unlock `boss_id`'s character for a boss the signer's run has killed
+/// Returns the unlocked character's index on a first unlock; repeat kills and bosses without a
+/// reward change nothing
+pub fn reward_boss(
+    player: &mut Player,
+    session: &GameSession,
+    authority: Pubkey,
+    boss_id: u8,
+) -> Result<Option<u8>> {
+    require!(
+        player.authority == Some(authority) && session.player == Some(authority),
+        DefeatBossError::NotAuthority
+    );
+    require!(session.is_active, DefeatBossError::SessionNotActive);
+    // The boss must have been killed through `damage_boss` this run
+    require!(
+        session.bosses_defeated >= boss_id,
+        DefeatBossError::BossNotDefeated
+    );
+
+    Ok(boss_reward_character(boss_id)
+        .filter(|reward| player.unlock_character(reward))
+        .map(character_index))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn authority() -> Pubkey {
+        Pubkey::new_from_array([7; 32])
+    }
+
+    fn player() -> Player {
+        Player {
+            authority: Some(authority()),
+            owned_characters: r#"["imelda"]"#.to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn session(bosses_defeated: u8) -> GameSession {
+        GameSession {
+            player: Some(authority()),
+            is_active: true,
+            bosses_defeated,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn first_kill_unlocks_and_repeat_kill_does_not() {
+        let mut player = player();
+        let reward = boss_reward_character(1).unwrap();
+        assert_eq!(
+            reward_boss(&mut player, &session(1), authority(), 1).unwrap(),
+            Some(character_index(reward))
+        );
+        assert!(player.owned_character_ids().contains(&reward));
+
+        let owned = player.owned_characters.clone();
+        assert_eq!(reward_boss(&mut player, &session(1), authority(), 1).unwrap(), None);
+        assert_eq!(player.owned_characters, owned);
+    }
+
+    #[test]
+    fn bosses_the_run_has_not_killed_are_refused() {
+        let mut player = player();
+        // Only boss 1 is down, so boss 2's reward stays locked
+        assert_eq!(
+            reward_boss(&mut player, &session(1), authority(), 2).unwrap_err(),
+            DefeatBossError::BossNotDefeated.into()
+        );
+        assert_eq!(player.owned_characters, r#"["imelda"]"#);
+    }
+}
@@ -0,0 +1,178 @@
+use bolt_lang::*;
+use end_game::{game_ended, record_run};
+use game_rules::scoring::compute_score;
+use game_session::GameSession;
+use leaderboard::LeaderboardEntry;
+use player::Player;
+use player_extras::{require_player_entity, PlayerExtras};
+use sync_leaderboard::sync_leaderboard;
+
+declare_id!("J3SiCaD1qTe6VVUf51tvQtXoZgN7w1aYg8mQGLxyAF65");
+
+#[error_code]
+pub enum EndGameAndSubmitError {
+    #[msg("Leaderboard entry belongs to a different player")]
+    WrongLeaderboard,
+}
+
+/// `end_game`'s `record_run`, then promote the run to the player's own leaderboard entry
+/// Bests only ever move up (`record_kills` / `record_score`) and the cached player fields are
+/// resynced from the updated player
+pub fn end_and_submit(
+    session: &mut GameSession,
+    player: &mut Player,
+    extras: &mut PlayerExtras,
+    leaderboard: &mut LeaderboardEntry,
+    authority: &Pubkey,
+    now: i64,
+) -> Result<()> {
+    require!(
+        leaderboard.player == player.authority,
+        EndGameAndSubmitError::WrongLeaderboard
+    );
+    record_run(session, player, extras, authority, now)?;
+
+    leaderboard.record_kills(session.kills);
+    leaderboard.record_score(compute_score(
+        session.wave,
+        session.time_survived,
+        session.kills,
+    ));
+    sync_leaderboard(player, leaderboard);
+    leaderboard.updated_at = now;
+    Ok(())
+}
+
+#[system]
+pub mod end_game_and_submit {
+    /// End game session, update player stats and promote them to the leaderboard
+    /// Same rules as `end_game`, without needing a separate `submit_score`
+    /// Extra accounts: [player entity]
+    pub fn execute(ctx: Context<Components>, _args: Vec<u8>) -> Result<Components> {
+        let clock = Clock::get()?;
+
+        require_player_entity(
+            ctx.remaining_accounts.first().map(|entity| entity.key),
+            &ctx.accounts.player.key(),
+            &ctx.accounts.player_extras.key(),
+        )?;
+        end_and_submit(
+            &mut ctx.accounts.game_session,
+            &mut ctx.accounts.player,
+            &mut ctx.accounts.player_extras,
+            &mut ctx.accounts.leaderboard,
+            &ctx.accounts.authority.key(),
+            clock.unix_timestamp,
+        )?;
+
+        emit!(game_ended(&ctx.accounts.game_session, &ctx.accounts.player));
+
+        Ok(ctx.accounts)
+    }
+
+    #[system_input]
+    pub struct Components {
+        pub game_session: GameSession,
+        pub player: Player,
+        pub leaderboard: LeaderboardEntry,
+        pub player_extras: PlayerExtras,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOW: i64 = 1_700_000_000;
+
+    fn authority() -> Pubkey {
+        Pubkey::new_from_array([7; 32])
+    }
+
+    fn session(wave: u8, kills: u32) -> GameSession {
+        GameSession {
+            player: Some(authority()),
+            character_id: String::from("antonio"),
+            is_active: true,
+            started_at: NOW - 120,
+            time_survived: 120,
+            wave,
+            kills,
+            gold_earned: 200,
+            ..Default::default()
+        }
+    }
+
+    fn player() -> Player {
+        Player {
+            authority: Some(authority()),
+            name: String::from("ada"),
+            ..Default::default()
+        }
+    }
+
+    fn leaderboard() -> LeaderboardEntry {
+        LeaderboardEntry {
+            player: Some(authority()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn ending_the_run_promotes_it_to_the_leaderboard() {
+        let (mut player, mut entry) = (player(), leaderboard());
+        end_and_submit(
+            &mut session(4, 30),
+            &mut player,
+            &mut PlayerExtras::default(),
+            &mut entry,
+            &authority(),
+            NOW,
+        )
+        .unwrap();
+        assert_eq!(entry.best_score, compute_score(4, 120, 30));
+        assert_eq!((entry.best_wave, entry.best_time, entry.best_kills), (4, 120, 30));
+        assert_eq!((entry.total_gold, entry.games_played), (200, 1));
+        assert_eq!(entry.name, "ada");
+        assert_eq!(entry.updated_at, NOW);
+    }
+
+    #[test]
+    fn a_weaker_run_keeps_the_leaderboard_bests() {
+        let (mut player, mut entry) = (player(), leaderboard());
+        for (wave, kills) in [(6, 50), (2, 5)] {
+            end_and_submit(
+                &mut session(wave, kills),
+                &mut player,
+                &mut PlayerExtras::default(),
+                &mut entry,
+                &authority(),
+                NOW,
+            )
+            .unwrap();
+        }
+        assert_eq!(entry.best_score, compute_score(6, 120, 50));
+        assert_eq!((entry.best_wave, entry.best_kills), (6, 50));
+        assert_eq!((entry.total_gold, entry.games_played), (400, 2));
+    }
+
+    #[test]
+    fn another_players_entry_is_rejected() {
+        let mut session = session(4, 30);
+        let mut entry = LeaderboardEntry {
+            player: Some(Pubkey::new_unique()),
+            ..Default::default()
+        };
+        let err = end_and_submit(
+            &mut session,
+            &mut player(),
+            &mut PlayerExtras::default(),
+            &mut entry,
+            &authority(),
+            NOW,
+        )
+        .unwrap_err();
+        assert_eq!(err, EndGameAndSubmitError::WrongLeaderboard.into());
+        assert!(session.is_active);
+    }
+}
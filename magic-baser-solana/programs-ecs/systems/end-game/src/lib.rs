@@ -1,45 +1,335 @@
 use bolt_lang::*;
+use game_rules::characters::{character_index, day_of, featured_character};
+use game_rules::economy::featured_bonus_gold;
+use game_rules::scoring::{completion_reason, compute_score, counts_as_game};
 use game_session::GameSession;
 use player::Player;
+use player_extras::{require_player_entity, PlayerExtras};
 
 declare_id!("9ytUaZtMR4NGUPTdJbmpbX8hhpmME8muwUGXZVSq8reY");
 
+#[error_code]
+pub enum EndGameError {
+    #[msg("Session start time is in the future")]
+    StartedInFuture,
+    #[msg("Session not active")]
+    SessionNotActive,
+    #[msg("Signer does not own this player and session")]
+    NotAuthority,
+}
+
+/// Full summary of a finished run, so indexers don't have to diff accounts around `end_game`
+#[event]
+pub struct GameEnded {
+    pub player: Pubkey,
+    pub character_index: u8,
+    pub wave: u8,
+    pub time_survived: u32,
+    pub gold_earned: u32,
+    pub kills: u32,
+    pub score: u64,
+    pub damage_dealt: u32,
+    pub damage_taken: u32,
+    pub damage_healed: u32,
+    /// `COMPLETION_DIED` or `COMPLETION_QUIT` from `game_rules::scoring`
+    pub completion_reason: u8,
+}
+
 #[system]
 pub mod end_game {
     /// End game session and update player stats
+    /// Extra accounts: [player entity]
     pub fn execute(ctx: Context<Components>, _args: Vec<u8>) -> Result<Components> {
-        let session = &mut ctx.accounts.game_session;
-        let player = &mut ctx.accounts.player;
+        let clock = Clock::get()?;
+
+        require_player_entity(
+            ctx.remaining_accounts.first().map(|entity| entity.key),
+            &ctx.accounts.player.key(),
+            &ctx.accounts.player_extras.key(),
+        )?;
+        record_run(
+            &mut ctx.accounts.game_session,
+            &mut ctx.accounts.player,
+            &mut ctx.accounts.player_extras,
+            &ctx.accounts.authority.key(),
+            clock.unix_timestamp,
+        )?;
+
+        emit!(game_ended(&ctx.accounts.game_session, &ctx.accounts.player));
+
+        Ok(ctx.accounts)
+    }
+
+    #[system_input]
+    pub struct Components {
+        pub game_session: GameSession,
+        pub player: Player,
+        pub player_extras: PlayerExtras,
+    }
+}
+
+/// Promote a finished run's stats to the player and close the session
+/// Shared with `end-game-and-submit` so both paths apply identical rules
+/// `authority` is the signing wallet; it must own both the player and the session
+pub fn record_run(
+    session: &mut GameSession,
+    player: &mut Player,
+    extras: &mut PlayerExtras,
+    authority: &Pubkey,
+    now: i64,
+) -> Result<()> {
+    // Only the player's wallet ends its own run, and only into its own Player
+    require!(
+        player.authority == Some(*authority) && session.player == player.authority,
+        EndGameError::NotAuthority
+    );
+
+    // A run is recorded once; ending it again would re-credit its gold and bonuses
+    require!(session.is_active, EndGameError::SessionNotActive);
+
+    // Reject sessions whose start time was fabricated ahead of the clock
+    require!(!session.started_in_future(now), EndGameError::StartedInFuture);
+
+    // Update player stats from session
+    // Update best wave if this run was better
+    let mut improved = false;
+    if session.wave > player.best_wave {
+        player.best_wave = session.wave;
+        improved = true;
+    }
+
+    // Update best time if this run was better
+    if session.time_survived > player.best_time {
+        player.best_time = session.time_survived;
+        improved = true;
+    }
+
+    // Ties don't count as a new record
+    if improved {
+        extras.best_updated_at = now;
+    }
+
+    // Add gold earned to total
+    player.total_gold = player.total_gold.saturating_add(session.gold_earned as u64);
+
+    // Featured character of the run's start day earns bonus gold
+    if character_index(&session.character_id) == featured_character(day_of(session.started_at)) {
+        player.total_gold = player
+            .total_gold
+            .saturating_add(featured_bonus_gold(session.gold_earned));
+    }
+
+    // Pickups convert to bonus gold at the rate the run started with (0 = none)
+    player.total_gold = player.total_gold.saturating_add(session.pickup_bonus_gold());
+
+    // Increment games played, skipping trivial abandons (bests above still apply)
+    if counts_as_game(session.time_survived, session.wave) {
+        player.games_played = player.games_played.saturating_add(1);
+    }
+
+    // Store character ID for leaderboard
+    player.last_character_id = session.character_id.clone();
+
+    // Mark session as inactive
+    session.is_active = false;
+
+    Ok(())
+}
+
+/// Event describing the run `record_run` just closed
+pub fn game_ended(session: &GameSession, player: &Player) -> GameEnded {
+    GameEnded {
+        player: player.authority.unwrap_or_default(),
+        character_index: character_index(&session.character_id),
+        wave: session.wave,
+        time_survived: session.time_survived,
+        gold_earned: session.gold_earned,
+        kills: session.kills,
+        score: compute_score(session.wave, session.time_survived, session.kills),
+        damage_dealt: session.damage_dealt,
+        damage_taken: session.damage_taken,
+        damage_healed: session.damage_healed,
+        completion_reason: completion_reason(session.is_dead),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use game_rules::characters::KNOWN_CHARACTERS;
+
+    const NOW: i64 = 1_700_000_000;
+
+    fn authority() -> Pubkey {
+        Pubkey::new_from_array([7; 32])
+    }
 
-        // Update player stats from session
-        // Update best wave if this run was better
-        if session.wave > player.best_wave {
-            player.best_wave = session.wave;
+    fn session() -> GameSession {
+        GameSession {
+            player: Some(authority()),
+            character_id: String::from("imelda"),
+            is_active: true,
+            started_at: NOW - 120,
+            time_survived: 120,
+            wave: 4,
+            gold_earned: 200,
+            ..Default::default()
         }
+    }
 
-        // Update best time if this run was better
-        if session.time_survived > player.best_time {
-            player.best_time = session.time_survived;
+    fn player() -> Player {
+        Player {
+            authority: Some(authority()),
+            ..Default::default()
         }
+    }
 
-        // Add gold earned to total
-        player.total_gold = player.total_gold.saturating_add(session.gold_earned as u64);
+    /// `record_run` signed by the player's wallet
+    fn record(
+        session: &mut GameSession,
+        player: &mut Player,
+        extras: &mut PlayerExtras,
+    ) -> Result<()> {
+        record_run(session, player, extras, &authority(), NOW)
+    }
 
-        // Increment games played
-        player.games_played = player.games_played.saturating_add(1);
+    #[test]
+    fn records_an_active_run_once() {
+        let mut session = session();
+        let mut player = player();
+        record(&mut session, &mut player, &mut PlayerExtras::default()).unwrap();
+        assert!(!session.is_active);
+        assert_eq!((player.best_wave, player.best_time, player.games_played), (4, 120, 1));
 
-        // Store character ID for leaderboard
-        player.last_character_id = session.character_id.clone();
+        let (total_gold, games_played) = (player.total_gold, player.games_played);
+        assert_eq!(
+            record(&mut session, &mut player, &mut PlayerExtras::default()).unwrap_err(),
+            EndGameError::SessionNotActive.into()
+        );
+        assert_eq!((player.total_gold, player.games_played), (total_gold, games_played));
+    }
 
-        // Mark session as inactive
-        session.is_active = false;
+    #[test]
+    fn other_wallets_cannot_end_the_run() {
+        let mut session = session();
+        let mut player = player();
+        let err = record_run(
+            &mut session,
+            &mut player,
+            &mut PlayerExtras::default(),
+            &Pubkey::new_unique(),
+            NOW,
+        )
+        .unwrap_err();
+        assert_eq!(err, EndGameError::NotAuthority.into());
+        assert!(session.is_active);
+        assert_eq!(player.total_gold, 0);
+    }
 
-        Ok(ctx.accounts)
+    #[test]
+    fn run_cannot_be_credited_to_another_player() {
+        let mut session = session();
+        session.player = Some(Pubkey::new_unique());
+        let mut player = player();
+        assert_eq!(
+            record(&mut session, &mut player, &mut PlayerExtras::default()).unwrap_err(),
+            EndGameError::NotAuthority.into()
+        );
+        assert!(session.is_active);
+        assert_eq!(player.games_played, 0);
     }
 
-    #[system_input]
-    pub struct Components {
-        pub game_session: GameSession,
-        pub player: Player,
+    #[test]
+    fn featured_bonus_is_credited_once() {
+        let mut session = session();
+        let featured = featured_character(day_of(session.started_at));
+        session.character_id = String::from(KNOWN_CHARACTERS[featured as usize]);
+        let mut player = player();
+
+        record(&mut session, &mut player, &mut PlayerExtras::default()).unwrap();
+        assert_eq!(player.total_gold, 200 + featured_bonus_gold(200));
+
+        assert!(record(&mut session, &mut player, &mut PlayerExtras::default()).is_err());
+        assert_eq!(player.total_gold, 200 + featured_bonus_gold(200));
+    }
+
+    #[test]
+    fn pickups_convert_at_the_run_rate() {
+        let mut session = session();
+        session.pickups_collected = 40;
+        session.pickup_gold_rate_bps = 2_500;
+        let mut player = player();
+        record(&mut session, &mut player, &mut PlayerExtras::default()).unwrap();
+        assert_eq!(player.total_gold, 200 + 10);
+    }
+
+    #[test]
+    fn pickups_convert_to_nothing_by_default() {
+        let mut session = session();
+        session.pickups_collected = 40;
+        let mut player = player();
+        record(&mut session, &mut player, &mut PlayerExtras::default()).unwrap();
+        assert_eq!(player.total_gold, 200);
+    }
+
+    #[test]
+    fn rejects_a_start_in_the_future() {
+        let mut session = session();
+        session.started_at = NOW + 1;
+        assert_eq!(
+            record(&mut session, &mut player(), &mut PlayerExtras::default()).unwrap_err(),
+            EndGameError::StartedInFuture.into()
+        );
+        assert!(session.is_active);
+    }
+
+    #[test]
+    fn game_ended_summarizes_the_closed_run() {
+        use game_rules::scoring::{COMPLETION_DIED, COMPLETION_QUIT};
+
+        let mut session = session();
+        session.character_id = String::from("antonio");
+        session.kills = 30;
+        (session.damage_dealt, session.damage_taken, session.damage_healed) = (900, 80, 25);
+        session.is_dead = true;
+        let mut player = player();
+        record(&mut session, &mut player, &mut PlayerExtras::default()).unwrap();
+
+        let event = game_ended(&session, &player);
+        assert_eq!(event.player, authority());
+        assert_eq!(event.character_index, character_index("antonio"));
+        assert_eq!((event.wave, event.time_survived), (4, 120));
+        assert_eq!((event.gold_earned, event.kills), (200, 30));
+        assert_eq!(event.score, compute_score(4, 120, 30));
+        assert_eq!(
+            (event.damage_dealt, event.damage_taken, event.damage_healed),
+            (900, 80, 25)
+        );
+        assert_eq!(event.completion_reason, COMPLETION_DIED);
+
+        session.is_dead = false;
+        assert_eq!(game_ended(&session, &player).completion_reason, COMPLETION_QUIT);
+    }
+
+    #[test]
+    fn best_updated_at_moves_only_on_a_new_record() {
+        let mut player = player();
+        let mut extras = PlayerExtras::default();
+        record(&mut session(), &mut player, &mut extras).unwrap();
+        assert_eq!(extras.best_updated_at, NOW);
+
+        // Tying both bests, or beating neither, leaves the stamp alone
+        extras.best_updated_at = NOW - 500;
+        record(&mut session(), &mut player, &mut extras).unwrap();
+        let mut worse = session();
+        (worse.wave, worse.time_survived) = (2, 60);
+        record(&mut worse, &mut player, &mut extras).unwrap();
+        assert_eq!(extras.best_updated_at, NOW - 500);
+
+        // Beating either one is a new record
+        let mut longer = session();
+        longer.time_survived = 121;
+        record(&mut longer, &mut player, &mut extras).unwrap();
+        assert_eq!(extras.best_updated_at, NOW);
     }
-}
\ No newline at end of file
+}
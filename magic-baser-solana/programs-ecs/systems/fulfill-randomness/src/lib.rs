@@ -0,0 +1,213 @@
+use bolt_lang::anchor_lang::solana_program::hash::hash;
+use bolt_lang::*;
+use game_rules::randomness::reveal_preimage;
+use game_session::GameSession;
+
+declare_id!("EKpUp44opDNVYZSvLX6E76tLyusihpUWJd6W6YQcpW3");
+
+#[error_code]
+pub enum FulfillRandomnessError {
+    #[msg("Invalid arguments")]
+    InvalidArguments,
+    #[msg("No randomness oracle is configured for this run")]
+    RandomnessDisabled,
+    #[msg("Signer is not the run's randomness oracle")]
+    NotOracle,
+    #[msg("No randomness request is waiting")]
+    NoPendingRequest,
+    #[msg("The oracle has already committed to a secret")]
+    AlreadyCommitted,
+    #[msg("Revealed secret does not match the oracle's commitment")]
+    CommitmentMismatch,
+}
+
+/// Emitted once a request's randomness is stored
+#[event]
+pub struct RandomnessFulfilled {
+    pub session: Pubkey,
+    pub nonce: u64,
+    pub randomness: [u8; 32],
+}
+
+/// Args to commit to the run's first secret: next_commitment(32)
+pub const COMMIT_ARGS_LEN: usize = 32;
+/// Args to answer the open request: secret(32) + next_commitment(32)
+pub const REVEAL_ARGS_LEN: usize = 64;
+
+/// Apply the oracle's `args` to `session`: reveal the committed secret for the open request
+/// and/or commit to the next one. Returns the request's randomness when one was fulfilled
+/// Each secret is committed (as its hash) before the player's seed for the request that uses
+/// it is known, and the randomness is the hash of both, so neither side can pick the outcome
+pub fn apply_oracle_args(
+    session: &mut GameSession,
+    session_key: &Pubkey,
+    args: &[u8],
+) -> Result<Option<[u8; 32]>> {
+    let (secret, next_commitment) = match args.len() {
+        COMMIT_ARGS_LEN => (None, &args[..]),
+        REVEAL_ARGS_LEN => (Some(&args[..32]), &args[32..]),
+        _ => return err!(FulfillRandomnessError::InvalidArguments),
+    };
+    require!(
+        next_commitment != [0u8; 32],
+        FulfillRandomnessError::InvalidArguments
+    );
+
+    let randomness = match secret {
+        // Only the first commitment comes on its own; later ones ride on a reveal, before the
+        // next request exists
+        None => {
+            require!(
+                session.randomness_commitment == [0; 32],
+                FulfillRandomnessError::AlreadyCommitted
+            );
+            None
+        }
+        Some(secret) => {
+            require!(
+                session.randomness_pending,
+                FulfillRandomnessError::NoPendingRequest
+            );
+            require!(
+                hash(secret).to_bytes() == session.randomness_commitment,
+                FulfillRandomnessError::CommitmentMismatch
+            );
+            let preimage = reveal_preimage(
+                secret.try_into().unwrap(),
+                &session.randomness_client_seed,
+                &session_key.to_bytes(),
+                session.randomness_nonce,
+            );
+            let randomness = hash(&preimage).to_bytes();
+            session.randomness_pending = false;
+            session.pending_randomness = randomness;
+            Some(randomness)
+        }
+    };
+
+    session.randomness_commitment = next_commitment.try_into().unwrap();
+    Ok(randomness)
+}
+
+#[system]
+pub mod fulfill_randomness {
+    /// Oracle-only: reveal the secret for the open randomness request and commit to the next
+    /// one, or commit to the run's first secret (see `apply_oracle_args`)
+    pub fn execute(ctx: Context<Components>, args: Vec<u8>) -> Result<Components> {
+        let session_key = ctx.accounts.game_session.key();
+        let session = &mut ctx.accounts.game_session;
+
+        let oracle = session
+            .vrf_oracle
+            .ok_or(FulfillRandomnessError::RandomnessDisabled)?;
+        require_keys_eq!(
+            ctx.accounts.authority.key(),
+            oracle,
+            FulfillRandomnessError::NotOracle
+        );
+
+        if let Some(randomness) = apply_oracle_args(session, &session_key, &args)? {
+            emit!(RandomnessFulfilled {
+                session: session_key,
+                nonce: session.randomness_nonce,
+                randomness,
+            });
+        }
+
+        Ok(ctx.accounts)
+    }
+
+    #[system_input]
+    pub struct Components {
+        pub game_session: GameSession,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use game_session::ROLL_REVIVE;
+
+    const SECRET: [u8; 32] = [5; 32];
+    const NEXT_SECRET: [u8; 32] = [6; 32];
+
+    fn commitment(secret: &[u8; 32]) -> Vec<u8> {
+        hash(secret).to_bytes().to_vec()
+    }
+
+    fn reveal(secret: &[u8; 32], next: &[u8; 32]) -> Vec<u8> {
+        [secret.to_vec(), commitment(next)].concat()
+    }
+
+    /// Session with the oracle committed to `SECRET` and a request open for `ROLL_REVIVE`
+    fn requested(session_key: &Pubkey) -> GameSession {
+        let mut session = GameSession::default();
+        apply_oracle_args(&mut session, session_key, &commitment(&SECRET)).unwrap();
+        session.randomness_nonce = 1;
+        session.randomness_pending = true;
+        session.randomness_client_seed = [9; 32];
+        session.randomness_event = ROLL_REVIVE;
+        session
+    }
+
+    #[test]
+    fn reveal_fulfills_the_request_and_commits_the_next_secret() {
+        let key = Pubkey::new_unique();
+        let mut session = requested(&key);
+        let randomness = apply_oracle_args(&mut session, &key, &reveal(&SECRET, &NEXT_SECRET))
+            .unwrap()
+            .unwrap();
+
+        let expected = hash(&reveal_preimage(&SECRET, &[9; 32], &key.to_bytes(), 1)).to_bytes();
+        assert_eq!(randomness, expected);
+        assert_eq!(session.pending_randomness, expected);
+        assert!(!session.randomness_pending);
+        assert_eq!(session.randomness_commitment.to_vec(), commitment(&NEXT_SECRET));
+        assert!(session.take_roll(ROLL_REVIVE, 100).unwrap() < 100);
+    }
+
+    #[test]
+    fn player_seed_changes_the_outcome() {
+        let key = Pubkey::new_unique();
+        let mut first = requested(&key);
+        let mut second = requested(&key);
+        second.randomness_client_seed = [10; 32];
+        let args = reveal(&SECRET, &NEXT_SECRET);
+        assert_ne!(
+            apply_oracle_args(&mut first, &key, &args).unwrap(),
+            apply_oracle_args(&mut second, &key, &args).unwrap()
+        );
+    }
+
+    #[test]
+    fn secret_other_than_the_committed_one_is_rejected() {
+        let key = Pubkey::new_unique();
+        let mut session = requested(&key);
+        assert_eq!(
+            apply_oracle_args(&mut session, &key, &reveal(&NEXT_SECRET, &SECRET)).unwrap_err(),
+            FulfillRandomnessError::CommitmentMismatch.into()
+        );
+        assert!(session.randomness_pending);
+    }
+
+    #[test]
+    fn commitment_cannot_be_swapped_outside_a_reveal() {
+        let key = Pubkey::new_unique();
+        let mut session = requested(&key);
+        assert_eq!(
+            apply_oracle_args(&mut session, &key, &commitment(&NEXT_SECRET)).unwrap_err(),
+            FulfillRandomnessError::AlreadyCommitted.into()
+        );
+    }
+
+    #[test]
+    fn reveal_without_a_request_is_rejected() {
+        let key = Pubkey::new_unique();
+        let mut session = requested(&key);
+        session.randomness_pending = false;
+        assert_eq!(
+            apply_oracle_args(&mut session, &key, &reveal(&SECRET, &NEXT_SECRET)).unwrap_err(),
+            FulfillRandomnessError::NoPendingRequest.into()
+        );
+    }
+}
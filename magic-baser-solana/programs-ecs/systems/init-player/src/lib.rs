@@ -1,6 +1,7 @@
 use bolt_lang::*;
-use player::Player;
+use player::{Player, PLAYER_NAME_MAX_LEN};
 use leaderboard::LeaderboardEntry;
+use magic_baser_solana::verify_name_record;
 
 declare_id!("GLR24FCjCRLcEJN37gGcZh9KBnKtM4rKRHdAFchNwprj");
 
@@ -8,6 +9,8 @@ declare_id!("GLR24FCjCRLcEJN37gGcZh9KBnKtM4rKRHdAFchNwprj");
 pub enum InitPlayerError {
     #[msg("Invalid arguments")]
     InvalidArguments,
+    #[msg("Name registry record must be passed as an extra account")]
+    MissingNameRecord,
 }
 
 /// Arguments for initializing a player
@@ -20,18 +23,29 @@ pub struct InitPlayerArgs {
 pub mod init_player {
     /// Initialize a new player account
     /// Creates Player component and LeaderboardEntry component for the wallet
+    /// Extra accounts: [name registry record], which must be claimed by the signer
     pub fn execute(ctx: Context<Components>, args: Vec<u8>) -> Result<Components> {
         let args: InitPlayerArgs = InitPlayerArgs::try_from_slice(&args)
             .map_err(|_| InitPlayerError::InvalidArguments)?;
 
         // Validate name length
-        require!(args.name.len() <= 20, InitPlayerError::InvalidArguments);
+        require!(args.name.len() <= PLAYER_NAME_MAX_LEN, InitPlayerError::InvalidArguments);
         require!(!args.name.is_empty(), InitPlayerError::InvalidArguments);
 
+        // Enforce name uniqueness: the signer must hold the name's registry record
+        let name_record = ctx
+            .remaining_accounts
+            .first()
+            .ok_or(InitPlayerError::MissingNameRecord)?;
+        verify_name_record(name_record, &args.name, &ctx.accounts.authority.key())?;
+
         let player = &mut ctx.accounts.player;
         let leaderboard = &mut ctx.accounts.leaderboard;
         let clock = Clock::get()?;
 
+        // Re-running init on an existing player would reset it, so it fails here first
+        player.init_created_at(clock.unix_timestamp)?;
+
         // Initialize Player component
         player.authority = Some(ctx.accounts.authority.key());
         player.name = args.name.clone();
@@ -41,7 +55,6 @@ pub mod init_player {
         player.games_played = 0;
         player.best_time = 0;
         player.best_wave = 0;
-        player.created_at = clock.unix_timestamp;
 
         // Initialize LeaderboardEntry component
         leaderboard.player = Some(ctx.accounts.authority.key());
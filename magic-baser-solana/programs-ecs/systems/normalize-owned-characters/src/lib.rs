@@ -0,0 +1,35 @@
+use bolt_lang::*;
+use player::{normalize_character_list, Player};
+
+declare_id!("2AaBkCRYnMLLtwyGb5VXQ6vZnvqgXBKP1A57KQ4bJeSQ");
+
+#[error_code]
+pub enum NormalizeOwnedCharactersError {
+    #[msg("Signer is not the player authority")]
+    NotAuthority,
+}
+
+#[system]
+pub mod normalize_owned_characters {
+    /// Maintenance pass over `owned_characters`
+    /// Drops duplicates and unknown ids left by buggy clients and rewrites the canonical JSON.
+    /// The component allocation is fixed, so the shorter string frees capacity for future unlocks
+    pub fn execute(ctx: Context<Components>, _args: Vec<u8>) -> Result<Components> {
+        let player = &mut ctx.accounts.player;
+
+        // Only the wallet that owns the player may rewrite its characters
+        require!(
+            player.authority == Some(ctx.accounts.authority.key()),
+            NormalizeOwnedCharactersError::NotAuthority
+        );
+
+        player.owned_characters = normalize_character_list(&player.owned_characters);
+
+        Ok(ctx.accounts)
+    }
+
+    #[system_input]
+    pub struct Components {
+        pub player: Player,
+    }
+}
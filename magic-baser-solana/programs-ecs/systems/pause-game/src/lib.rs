@@ -0,0 +1,44 @@
+use bolt_lang::*;
+use game_session::GameSession;
+
+declare_id!("ChGd7gWMY1ysNjj2FcdUer3tiHwqVvwCDQfV4Qq4zk9K");
+
+#[error_code]
+pub enum PauseGameError {
+    #[msg("Session is not active")]
+    SessionNotActive,
+    #[msg("Session is already paused")]
+    AlreadyPaused,
+}
+
+#[system]
+pub mod pause_game {
+    /// Pause the run's clock, e.g. when the player alt-tabs or loses connectivity
+    /// Paused time is left out of `time_survived` and `update_stats` is rejected until
+    /// `resume_game`
+    pub fn execute(ctx: Context<Components>, _args: Vec<u8>) -> Result<Components> {
+        let session_key = ctx.accounts.game_session.key();
+        let signer = ctx.accounts.authority.key();
+        let session = &mut ctx.accounts.game_session;
+
+        require!(session.is_active, PauseGameError::SessionNotActive);
+        require!(!session.is_paused(), PauseGameError::AlreadyPaused);
+
+        // The player's wallet or the player's session token for this system
+        session.authorize_signer(
+            &session_key,
+            &signer,
+            ctx.remaining_accounts.first(),
+            &crate::ID,
+        )?;
+
+        session.paused_at = Clock::get()?.unix_timestamp.max(1);
+
+        Ok(ctx.accounts)
+    }
+
+    #[system_input]
+    pub struct Components {
+        pub game_session: GameSession,
+    }
+}
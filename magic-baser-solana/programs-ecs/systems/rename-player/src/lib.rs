@@ -0,0 +1,89 @@
+use bolt_lang::*;
+use leaderboard::LeaderboardEntry;
+use magic_baser_solana::{is_own_name_record, name_hash, verify_name_record};
+use player::{Player, PLAYER_NAME_MAX_LEN};
+use sync_leaderboard::sync_leaderboard;
+
+declare_id!("7H4t6D4ZhMMq9ydqzbbpr4PTBjm55ZErdFbS5vCWVWVd");
+
+#[error_code]
+pub enum RenamePlayerError {
+    #[msg("Invalid arguments")]
+    InvalidArguments,
+    #[msg("Signer is not the player authority")]
+    NotAuthority,
+    #[msg("Leaderboard entry belongs to a different player")]
+    WrongLeaderboard,
+    #[msg("Name registry accounts must be passed as extra accounts")]
+    MissingNameRecord,
+    #[msg("Account is not the name registry program")]
+    WrongRegistryProgram,
+}
+
+/// Arguments for renaming a player
+#[derive(Clone, AnchorSerialize, AnchorDeserialize)]
+pub struct RenamePlayerArgs {
+    pub name: String,
+}
+
+#[system]
+pub mod rename_player {
+    /// Change the player's display name on both the Player and LeaderboardEntry
+    /// Extra accounts: [new name's registry record (claimed by the signer), old name's record,
+    /// registry program]; the old record is released into the new one
+    pub fn execute(ctx: Context<Components>, args: Vec<u8>) -> Result<Components> {
+        let args: RenamePlayerArgs = RenamePlayerArgs::try_from_slice(&args)
+            .map_err(|_| RenamePlayerError::InvalidArguments)?;
+
+        // Validate name length
+        require!(args.name.len() <= PLAYER_NAME_MAX_LEN, RenamePlayerError::InvalidArguments);
+        require!(!args.name.is_empty(), RenamePlayerError::InvalidArguments);
+
+        let authority = ctx.accounts.authority.key();
+        require!(
+            ctx.accounts.player.authority == Some(authority),
+            RenamePlayerError::NotAuthority
+        );
+        require!(
+            ctx.accounts.leaderboard.player == Some(authority),
+            RenamePlayerError::WrongLeaderboard
+        );
+
+        // Enforce name uniqueness: the signer must hold the new name's registry record
+        let [new_record, old_record, registry, ..] = ctx.remaining_accounts else {
+            return err!(RenamePlayerError::MissingNameRecord);
+        };
+        verify_name_record(new_record, &args.name, &authority)?;
+
+        // Free the old name, unless it's the same name (recased) or was never registered
+        let old_name = &ctx.accounts.player.name;
+        if name_hash(old_name) != name_hash(&args.name)
+            && is_own_name_record(old_record, old_name, &authority)?
+        {
+            require_keys_eq!(
+                *registry.key,
+                magic_baser_solana::ID,
+                RenamePlayerError::WrongRegistryProgram
+            );
+            magic_baser_solana::cpi::release_name_into(CpiContext::new(
+                registry.clone(),
+                magic_baser_solana::cpi::accounts::ReleaseNameInto {
+                    name_record: old_record.clone(),
+                    new_record: new_record.clone(),
+                    authority: ctx.accounts.authority.to_account_info(),
+                },
+            ))?;
+        }
+
+        ctx.accounts.player.name = args.name;
+        sync_leaderboard(&ctx.accounts.player, &mut ctx.accounts.leaderboard);
+
+        Ok(ctx.accounts)
+    }
+
+    #[system_input]
+    pub struct Components {
+        pub player: Player,
+        pub leaderboard: LeaderboardEntry,
+    }
+}
@@ -0,0 +1,127 @@
+use bolt_lang::*;
+use game_session::GameSession;
+
+declare_id!("2mQpdFAVn6QbD4VkSz4ZaBy9qPsB65w3cLWKoiqaCGDt");
+
+#[error_code]
+pub enum RequestRandomnessError {
+    #[msg("Invalid arguments")]
+    InvalidArguments,
+    #[msg("Session not active")]
+    SessionNotActive,
+    #[msg("No randomness oracle is configured for this run")]
+    RandomnessDisabled,
+    #[msg("The oracle has not committed to a secret yet")]
+    NoCommitment,
+    #[msg("A randomness request is already waiting for the oracle")]
+    RequestPending,
+    #[msg("Fulfilled randomness has not been rolled yet")]
+    RandomnessUnused,
+}
+
+/// Picked up by the randomness oracle, which answers with `fulfill_randomness`
+#[event]
+pub struct RandomnessRequested {
+    pub session: Pubkey,
+    pub oracle: Pubkey,
+    pub nonce: u64,
+    pub event: u8,
+}
+
+/// Args: event(1), a `ROLL_*` id, + client_seed(32), picked by the player's client
+pub const REQUEST_RANDOMNESS_ARGS_LEN: usize = 33;
+
+/// Event and client seed from `args`
+pub fn parse_request_args(args: &[u8]) -> Result<(u8, [u8; 32])> {
+    require!(
+        args.len() == REQUEST_RANDOMNESS_ARGS_LEN,
+        RequestRandomnessError::InvalidArguments
+    );
+    require!(
+        game_rules::randomness::is_roll_event(args[0]),
+        RequestRandomnessError::InvalidArguments
+    );
+    Ok((args[0], args[1..].try_into().unwrap()))
+}
+
+#[system]
+pub mod request_randomness {
+    /// Ask the run's oracle for a fresh random value for one event (chest drop, elite spawn,
+    /// revive chance)
+    /// The oracle has already committed to its secret, so it can't pick the outcome after seeing
+    /// the player's seed. A new request waits until the last value has been rolled, so an
+    /// unwanted outcome can't be thrown away and re-requested
+    pub fn execute(ctx: Context<Components>, args: Vec<u8>) -> Result<Components> {
+        let (event, client_seed) = parse_request_args(&args)?;
+
+        let session_key = ctx.accounts.game_session.key();
+        let signer = ctx.accounts.authority.key();
+        let session = &mut ctx.accounts.game_session;
+
+        require!(session.is_active, RequestRandomnessError::SessionNotActive);
+        let oracle = session
+            .vrf_oracle
+            .ok_or(RequestRandomnessError::RandomnessDisabled)?;
+        require!(
+            session.randomness_commitment != [0; 32],
+            RequestRandomnessError::NoCommitment
+        );
+        require!(
+            !session.randomness_pending,
+            RequestRandomnessError::RequestPending
+        );
+        require!(
+            session.pending_randomness == [0; 32],
+            RequestRandomnessError::RandomnessUnused
+        );
+
+        // The player's wallet or the player's session token for this system
+        session.authorize_signer(
+            &session_key,
+            &signer,
+            ctx.remaining_accounts.first(),
+            &crate::ID,
+        )?;
+
+        session.randomness_nonce = session.randomness_nonce.saturating_add(1);
+        session.randomness_pending = true;
+        session.randomness_client_seed = client_seed;
+        session.randomness_event = event;
+
+        emit!(RandomnessRequested {
+            session: session_key,
+            oracle,
+            nonce: session.randomness_nonce,
+            event,
+        });
+
+        Ok(ctx.accounts)
+    }
+
+    #[system_input]
+    pub struct Components {
+        pub game_session: GameSession,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use game_session::{ROLL_CHEST, ROLL_REVIVE};
+
+    #[test]
+    fn event_and_seed_are_read() {
+        let mut args = vec![ROLL_REVIVE];
+        args.extend_from_slice(&[9u8; 32]);
+        assert_eq!(parse_request_args(&args).unwrap(), (ROLL_REVIVE, [9u8; 32]));
+    }
+
+    #[test]
+    fn unknown_event_or_bad_length_is_rejected() {
+        let invalid: Error = RequestRandomnessError::InvalidArguments.into();
+        let mut args = vec![ROLL_REVIVE + 1];
+        args.extend_from_slice(&[9u8; 32]);
+        assert_eq!(parse_request_args(&args).unwrap_err(), invalid);
+        assert_eq!(parse_request_args(&[ROLL_CHEST]).unwrap_err(), invalid);
+    }
+}
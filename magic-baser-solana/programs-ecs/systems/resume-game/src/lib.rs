@@ -0,0 +1,56 @@
+use bolt_lang::*;
+use game_session::GameSession;
+
+declare_id!("4XaSSaqwvdqvgz6tEexdRET85zG8VvgWreWSYowMmksj");
+
+#[error_code]
+pub enum ResumeGameError {
+    #[msg("Session is not active")]
+    SessionNotActive,
+    #[msg("Session is not paused")]
+    NotPaused,
+}
+
+#[system]
+pub mod resume_game {
+    /// Resume a paused run, crediting the pause to `paused_secs`
+    pub fn execute(ctx: Context<Components>, _args: Vec<u8>) -> Result<Components> {
+        let session_key = ctx.accounts.game_session.key();
+        let signer = ctx.accounts.authority.key();
+        let session = &mut ctx.accounts.game_session;
+
+        require!(session.is_active, ResumeGameError::SessionNotActive);
+        require!(session.is_paused(), ResumeGameError::NotPaused);
+
+        // The player's wallet or the player's session token for this system
+        session.authorize_signer(
+            &session_key,
+            &signer,
+            ctx.remaining_accounts.first(),
+            &crate::ID,
+        )?;
+
+        let now = Clock::get()?.unix_timestamp;
+        let paused = now
+            .saturating_sub(session.paused_at)
+            .clamp(0, u32::MAX as i64) as u32;
+        session.paused_secs = session.paused_secs.saturating_add(paused);
+
+        // A combo or buff still live when the run paused keeps the time it had left
+        session.extend_timers(paused as i64);
+        session.paused_at = 0;
+
+        // Restart the update windows so the pause earns no HP regen, rate, boss damage or gold
+        // allowance
+        session.last_update_at = now;
+        session.boss_damaged_at = now;
+        session.gold_collected_at = now;
+
+        Ok(ctx.accounts)
+    }
+
+    #[system_input]
+    pub struct Components {
+        pub game_session: GameSession,
+    }
+}
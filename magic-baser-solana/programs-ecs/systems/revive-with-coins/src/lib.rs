@@ -0,0 +1,88 @@
+use bolt_lang::*;
+use game_session::GameSession;
+
+declare_id!("Chp1CUE3ohnpZ62WhvrMsbY98dDNDTggThzUuZHNXCp5");
+
+#[error_code]
+pub enum ReviveWithCoinsError {
+    #[msg("Session not active")]
+    SessionNotActive,
+    #[msg("Player is not dead")]
+    NotDead,
+    #[msg("Not enough gold for a revive")]
+    InsufficientCoins,
+}
+
+#[system]
+pub mod revive_with_coins {
+    /// Revive by spending gold earned in this run instead of a persistent revive charge
+    /// Cost doubles with every revive already used in the session
+    pub fn execute(ctx: Context<Components>, _args: Vec<u8>) -> Result<Components> {
+        revive_with_gold(&mut ctx.accounts.game_session)?;
+
+        Ok(ctx.accounts)
+    }
+
+    #[system_input]
+    pub struct Components {
+        pub game_session: GameSession,
+    }
+}
+
+/// Pay the run's next coin revive out of its gold and bring the player back
+pub fn revive_with_gold(session: &mut GameSession) -> Result<()> {
+    // Verify session is active but player is dead
+    require!(session.is_active, ReviveWithCoinsError::SessionNotActive);
+    require!(session.is_dead, ReviveWithCoinsError::NotDead);
+
+    // Verify the run has enough gold to pay for it
+    let cost = session.coin_revive_cost();
+    require!(session.gold_earned >= cost, ReviveWithCoinsError::InsufficientCoins);
+
+    session.gold_earned -= cost;
+
+    // Restore player to alive state with 50% HP (never at or below the death threshold)
+    session.revive();
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dead_session(gold_earned: u32) -> GameSession {
+        GameSession {
+            is_active: true,
+            is_dead: true,
+            max_hp: 100,
+            gold_earned,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn paid_revive_spends_the_cost_and_revives() {
+        let mut session = dead_session(1_000);
+        let cost = session.coin_revive_cost();
+        revive_with_gold(&mut session).unwrap();
+        assert_eq!(session.gold_earned, 1_000 - cost);
+        assert!(!session.is_dead);
+        assert_eq!(session.hp, session.revive_hp());
+        assert_eq!(session.revives_used, 1);
+        // The next one costs more
+        assert!(session.coin_revive_cost() > cost);
+    }
+
+    #[test]
+    fn revive_without_enough_gold_is_refused() {
+        let mut session = dead_session(0);
+        let cost = session.coin_revive_cost();
+        session.gold_earned = cost - 1;
+        assert_eq!(
+            revive_with_gold(&mut session).unwrap_err(),
+            ReviveWithCoinsError::InsufficientCoins.into()
+        );
+        assert!(session.is_dead);
+        assert_eq!((session.gold_earned, session.revives_used), (cost - 1, 0));
+    }
+}
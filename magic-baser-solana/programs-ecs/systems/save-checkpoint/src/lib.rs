@@ -0,0 +1,71 @@
+use bolt_lang::*;
+use checkpoint::{checkpoint_due, Checkpoint};
+use game_session::GameSession;
+
+declare_id!("CSqMCDFFkPNVCNJAscQrSz974HksDpughSbqaWYXSL3m");
+
+#[error_code]
+pub enum SaveCheckpointError {
+    #[msg("Session is not active")]
+    SessionNotActive,
+    #[msg("Run has not advanced enough waves since its last checkpoint")]
+    CheckpointNotDue,
+}
+
+#[system]
+pub mod save_checkpoint {
+    /// Snapshot the run into its Checkpoint every `CHECKPOINT_INTERVAL_WAVES` waves
+    /// This is an L1 transaction, sent after the ER has committed the session, so a dropped
+    /// rollup or failed delegation loses at most the waves since the last checkpoint
+    pub fn execute(ctx: Context<Components>, _args: Vec<u8>) -> Result<Components> {
+        let session_key = ctx.accounts.game_session.key();
+        let signer = ctx.accounts.authority.key();
+        let session = &ctx.accounts.game_session;
+        let checkpoint = &mut ctx.accounts.checkpoint;
+
+        require!(session.is_active, SaveCheckpointError::SessionNotActive);
+
+        // The player's wallet or the player's session token for this system
+        session.authorize_signer(
+            &session_key,
+            &signer,
+            ctx.remaining_accounts.first(),
+            &crate::ID,
+        )?;
+
+        // A snapshot of an earlier run on this entity doesn't count toward the interval
+        let last_wave = if checkpoint.is_for_run(&session_key, session.started_at) {
+            checkpoint.wave
+        } else {
+            0
+        };
+        require!(
+            checkpoint_due(last_wave, session.wave),
+            SaveCheckpointError::CheckpointNotDue
+        );
+
+        checkpoint.session = Some(session_key);
+        checkpoint.player = session.player;
+        checkpoint.started_at = session.started_at;
+        checkpoint.character_id = session.character_id.clone();
+        checkpoint.wave = session.wave;
+        checkpoint.hp = session.hp;
+        checkpoint.max_hp = session.max_hp;
+        checkpoint.level = session.level;
+        checkpoint.xp = session.xp;
+        checkpoint.gold_earned = session.gold_earned;
+        checkpoint.time_survived = session.time_survived;
+        checkpoint.kills = session.kills;
+        checkpoint.revives_used = session.revives_used;
+        checkpoint.tick = session.tick;
+        checkpoint.saved_at = Clock::get()?.unix_timestamp;
+
+        Ok(ctx.accounts)
+    }
+
+    #[system_input]
+    pub struct Components {
+        pub game_session: GameSession,
+        pub checkpoint: Checkpoint,
+    }
+}
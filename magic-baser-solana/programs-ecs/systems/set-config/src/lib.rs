@@ -0,0 +1,86 @@
+use bolt_lang::*;
+use config::{Config, DEFAULT_FEATURE_FLAGS};
+
+declare_id!("CDZvQ4XQ2hWtwMzFhZBEX5MD1X4Ke7hzN77GLcwwyApn");
+
+#[error_code]
+pub enum SetConfigError {
+    #[msg("Invalid arguments")]
+    InvalidArguments,
+    #[msg("Signer is not the config admin")]
+    NotAdmin,
+}
+
+/// Args: treasury_pubkey(32) + optional submit_interval_secs(4) + optional death_hp_threshold(2)
+/// + optional starter_revives(1) + optional vrf_oracle(32) + optional pickup_gold_rate_bps(2)
+/// Trailing fields keep their current value when omitted
+pub const SET_CONFIG_ARGS_LEN: usize = 32;
+
+pub const SUBMIT_INTERVAL_OFFSET: usize = SET_CONFIG_ARGS_LEN;
+pub const DEATH_HP_THRESHOLD_OFFSET: usize = SUBMIT_INTERVAL_OFFSET + 4;
+pub const STARTER_REVIVES_OFFSET: usize = DEATH_HP_THRESHOLD_OFFSET + 2;
+pub const VRF_ORACLE_OFFSET: usize = STARTER_REVIVES_OFFSET + 1;
+pub const PICKUP_GOLD_RATE_OFFSET: usize = VRF_ORACLE_OFFSET + 32;
+pub const SET_CONFIG_ARGS_MAX_LEN: usize = PICKUP_GOLD_RATE_OFFSET + 2;
+
+/// Highest pickup conversion rate: every pickup worth one bonus gold
+pub const MAX_PICKUP_GOLD_RATE_BPS: u16 = 10_000;
+
+#[system]
+pub mod set_config {
+    /// Update world-wide game settings
+    /// The first caller on a fresh Config becomes its admin, so run this right after creating it
+    pub fn execute(ctx: Context<Components>, args: Vec<u8>) -> Result<Components> {
+        let config = &mut ctx.accounts.config;
+        let signer = ctx.accounts.authority.key();
+
+        require!(
+            matches!(
+                args.len(),
+                SET_CONFIG_ARGS_LEN
+                    | DEATH_HP_THRESHOLD_OFFSET
+                    | STARTER_REVIVES_OFFSET
+                    | VRF_ORACLE_OFFSET
+                    | PICKUP_GOLD_RATE_OFFSET
+                    | SET_CONFIG_ARGS_MAX_LEN
+            ),
+            SetConfigError::InvalidArguments
+        );
+
+        match config.admin {
+            Some(admin) => require_keys_eq!(admin, signer, SetConfigError::NotAdmin),
+            None => {
+                config.admin = Some(signer);
+                config.feature_flags = DEFAULT_FEATURE_FLAGS;
+            }
+        }
+
+        let treasury_bytes: [u8; 32] = args[0..32].try_into().unwrap();
+        config.treasury = Some(Pubkey::new_from_array(treasury_bytes));
+
+        if let Some(interval) = args.get(SUBMIT_INTERVAL_OFFSET..DEATH_HP_THRESHOLD_OFFSET) {
+            config.submit_interval_secs = u32::from_le_bytes(interval.try_into().unwrap());
+        }
+        if let Some(threshold) = args.get(DEATH_HP_THRESHOLD_OFFSET..STARTER_REVIVES_OFFSET) {
+            config.death_hp_threshold = u16::from_le_bytes(threshold.try_into().unwrap());
+        }
+        if let Some(&starter_revives) = args.get(STARTER_REVIVES_OFFSET) {
+            config.starter_revives = starter_revives;
+        }
+        if let Some(oracle) = args.get(VRF_ORACLE_OFFSET..PICKUP_GOLD_RATE_OFFSET) {
+            config.vrf_oracle = Some(Pubkey::new_from_array(oracle.try_into().unwrap()));
+        }
+        if let Some(rate) = args.get(PICKUP_GOLD_RATE_OFFSET..SET_CONFIG_ARGS_MAX_LEN) {
+            let rate = u16::from_le_bytes(rate.try_into().unwrap());
+            require!(rate <= MAX_PICKUP_GOLD_RATE_BPS, SetConfigError::InvalidArguments);
+            config.pickup_gold_rate_bps = rate;
+        }
+
+        Ok(ctx.accounts)
+    }
+
+    #[system_input]
+    pub struct Components {
+        pub config: Config,
+    }
+}
@@ -0,0 +1,102 @@
+use bolt_lang::*;
+use config::Config;
+
+declare_id!("675LZyRxjufauoHP9WcuTiTWK1PeXwzki8HHarcjUWC5");
+
+#[error_code]
+pub enum SetFeatureFlagError {
+    #[msg("Invalid arguments")]
+    InvalidArguments,
+    #[msg("Signer is not the config admin")]
+    NotAdmin,
+}
+
+/// Args: flag(4, little-endian, `FEATURE_*` bits) + enabled(1)
+pub const SET_FEATURE_FLAG_ARGS_LEN: usize = 5;
+
+#[system]
+pub mod set_feature_flag {
+    /// Switch Config feature flags on or off without a redeploy
+    /// Sessions pick up the flags at `start_game`, so runs already in progress keep theirs
+    pub fn execute(ctx: Context<Components>, args: Vec<u8>) -> Result<Components> {
+        let config = &mut ctx.accounts.config;
+
+        require!(
+            config.admin == Some(ctx.accounts.authority.key()),
+            SetFeatureFlagError::NotAdmin
+        );
+
+        apply_feature_flag(config, &args)?;
+
+        Ok(ctx.accounts)
+    }
+
+    #[system_input]
+    pub struct Components {
+        pub config: Config,
+    }
+}
+
+/// Switch the flag in `args` on or off in the config
+pub fn apply_feature_flag(config: &mut Config, args: &[u8]) -> Result<()> {
+    require!(args.len() == SET_FEATURE_FLAG_ARGS_LEN, SetFeatureFlagError::InvalidArguments);
+
+    let flag = u32::from_le_bytes([args[0], args[1], args[2], args[3]]);
+    match args[4] {
+        0 => config.feature_flags &= !flag,
+        1 => config.feature_flags |= flag,
+        _ => return err!(SetFeatureFlagError::InvalidArguments),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use collect_gold::{require_can_collect, CollectGoldError};
+    use game_rules::features::{FEATURE_ANTI_CHEAT, FEATURE_GOLD_PICKUPS};
+    use game_session::GameSession;
+
+    fn args(flag: u32, enabled: u8) -> Vec<u8> {
+        let mut args = flag.to_le_bytes().to_vec();
+        args.push(enabled);
+        args
+    }
+
+    /// A run started under `config`, which copies its flags the way `start_game` does
+    fn started_run(config: &Config) -> GameSession {
+        GameSession { is_active: true, feature_flags: config.feature_flags, ..Default::default() }
+    }
+
+    #[test]
+    fn gold_pickups_flag_gates_collect_gold() {
+        let mut config = Config { feature_flags: FEATURE_ANTI_CHEAT, ..Default::default() };
+        assert_eq!(
+            require_can_collect(&started_run(&config)).unwrap_err(),
+            CollectGoldError::GoldPickupsDisabled.into()
+        );
+
+        apply_feature_flag(&mut config, &args(FEATURE_GOLD_PICKUPS, 1)).unwrap();
+        let run = started_run(&config);
+        require_can_collect(&run).unwrap();
+
+        // Switching it back off holds for new runs; the one in progress keeps its flags
+        apply_feature_flag(&mut config, &args(FEATURE_GOLD_PICKUPS, 0)).unwrap();
+        assert_eq!(config.feature_flags, FEATURE_ANTI_CHEAT);
+        assert_eq!(
+            require_can_collect(&started_run(&config)).unwrap_err(),
+            CollectGoldError::GoldPickupsDisabled.into()
+        );
+        require_can_collect(&run).unwrap();
+    }
+
+    #[test]
+    fn enabled_byte_other_than_zero_or_one_is_rejected() {
+        let mut config = Config::default();
+        assert_eq!(
+            apply_feature_flag(&mut config, &args(FEATURE_GOLD_PICKUPS, 2)).unwrap_err(),
+            SetFeatureFlagError::InvalidArguments.into()
+        );
+        assert_eq!(config.feature_flags, 0);
+    }
+}
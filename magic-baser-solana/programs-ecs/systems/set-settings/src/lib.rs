@@ -0,0 +1,79 @@
+use bolt_lang::*;
+use player::Player;
+use player_extras::{require_player_entity, PlayerExtras};
+
+declare_id!("6UDhJwpbRybfC9ZQomCxSHwECLZ6SAcrEvDMUjZdEad5");
+
+#[error_code]
+pub enum SetSettingsError {
+    #[msg("Settings payload is too large")]
+    SettingsTooLarge,
+    #[msg("Signer is not the player authority")]
+    NotAuthority,
+}
+
+#[system]
+pub mod set_settings {
+    /// Store the client's preferences blob on the player so it follows the wallet
+    /// Args are the raw settings bytes (up to 32); shorter payloads are zero-padded
+    /// Extra accounts: [player entity]
+    pub fn execute(ctx: Context<Components>, args: Vec<u8>) -> Result<Components> {
+        require!(
+            ctx.accounts.player.authority == Some(ctx.accounts.authority.key()),
+            SetSettingsError::NotAuthority
+        );
+        require_player_entity(
+            ctx.remaining_accounts.first().map(|entity| entity.key),
+            &ctx.accounts.player.key(),
+            &ctx.accounts.player_extras.key(),
+        )?;
+
+        write_settings(&mut ctx.accounts.player_extras, &args)?;
+
+        Ok(ctx.accounts)
+    }
+
+    #[system_input]
+    pub struct Components {
+        pub player: Player,
+        pub player_extras: PlayerExtras,
+    }
+}
+
+/// Replace the stored settings with `args`, zero-padding a shorter payload
+pub fn write_settings(extras: &mut PlayerExtras, args: &[u8]) -> Result<()> {
+    require!(args.len() <= extras.settings.len(), SetSettingsError::SettingsTooLarge);
+
+    let mut settings = [0u8; 32];
+    settings[..args.len()].copy_from_slice(args);
+    extras.settings = settings;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn written_settings_read_back_zero_padded() {
+        let mut extras = PlayerExtras::default();
+        write_settings(&mut extras, &[9; 32]).unwrap();
+        assert_eq!(extras.settings, [9; 32]);
+
+        // A shorter payload replaces the whole blob rather than its prefix
+        write_settings(&mut extras, &[1, 2, 3]).unwrap();
+        assert_eq!(extras.settings[..3], [1, 2, 3]);
+        assert_eq!(extras.settings[3..], [0; 29]);
+    }
+
+    #[test]
+    fn oversized_payload_is_rejected() {
+        let mut extras = PlayerExtras::default();
+        write_settings(&mut extras, &[1, 2, 3]).unwrap();
+        assert_eq!(
+            write_settings(&mut extras, &[9; 33]).unwrap_err(),
+            SetSettingsError::SettingsTooLarge.into()
+        );
+        assert_eq!(extras.settings[..4], [1, 2, 3, 0]);
+    }
+}
@@ -1,47 +1,109 @@
 use bolt_lang::*;
-use game_session::GameSession;
+use game_rules::characters::{character_base_hp, is_known_character, STARTER_CHARACTER};
+use game_rules::difficulty::{starting_max_hp, DIFFICULTY_NORMAL, MAX_DIFFICULTY};
+use config::{require_canonical_config, Config};
+use game_session::{GameSession, CHARACTER_ID_MAX_LEN, MAX_SESSION_SLOTS};
+use player::Player;
+use player_extras::{require_player_entity, PlayerExtras};
 
 declare_id!("5DeWBC5u2mWzZ46pSekwoDvknT18LKZpghY5yzT9iNR1");
 
-/// Character stats for different playable characters
-fn get_character_hp(character_id: &str) -> (u16, u16) {
-    match character_id {
-        "antonio" => (120, 120),
-        "pasqualina" => (80, 80),
-        "gennaro" => (110, 110),
-        "mortaccio" => (90, 90),
-        "vitalis" => (95, 95),
-        _ => (100, 100), // imelda default
+/// Args: character_id_len(4, little-endian) + character_id bytes + optional difficulty(1)
+/// + optional slot(1); empty args start the starter character on normal in slot 0
+pub const CHARACTER_ID_LEN_PREFIX: usize = 4;
+
+#[error_code]
+pub enum StartGameError {
+    #[msg("Unknown character id")]
+    UnknownCharacter,
+    #[msg("Signer is not the player authority")]
+    NotAuthority,
+    #[msg("Save slot out of range")]
+    InvalidSlot,
+    #[msg("Malformed start_game arguments")]
+    InvalidArgs,
+    #[msg("Game session belongs to another player")]
+    SessionNotOwned,
+    #[msg("Difficulty out of range")]
+    InvalidDifficulty,
+}
+
+/// Character id, difficulty and save slot from `args`
+/// Only empty args fall back to the starter character; a length prefix that is 0, longer than
+/// `CHARACTER_ID_MAX_LEN` or runs past the args is rejected instead of starting as imelda
+pub fn parse_start_args(args: &[u8]) -> Result<(String, u8, u8)> {
+    if args.is_empty() {
+        return Ok((String::from(STARTER_CHARACTER), 0, 0));
+    }
+    let (prefix, rest) = args
+        .split_first_chunk::<CHARACTER_ID_LEN_PREFIX>()
+        .ok_or(StartGameError::InvalidArgs)?;
+    let len = u32::from_le_bytes(*prefix) as usize;
+    require!(
+        len > 0 && len <= CHARACTER_ID_MAX_LEN && rest.len() >= len,
+        StartGameError::InvalidArgs
+    );
+    let (id, rest) = rest.split_at(len);
+
+    // Ids are matched case-insensitively ("Antonio" -> "antonio"); anything else is rejected
+    let character_id: String = id
+        .iter()
+        .filter(|b| b.is_ascii_alphanumeric())
+        .map(|b| b.to_ascii_lowercase() as char)
+        .collect();
+    require!(is_known_character(&character_id), StartGameError::UnknownCharacter);
+
+    // Optional difficulty byte right after the character id; absent means normal
+    let difficulty = rest.first().copied().unwrap_or(DIFFICULTY_NORMAL);
+    require!(difficulty <= MAX_DIFFICULTY, StartGameError::InvalidDifficulty);
+
+    // Optional save slot after the difficulty; absent means slot 0 (the original single
+    // session). The client derives the session entity from the same slot
+    let slot = rest.get(1).copied().unwrap_or(0);
+    require!(slot < MAX_SESSION_SLOTS, StartGameError::InvalidSlot);
+
+    Ok((character_id, difficulty, slot))
+}
+
+/// Bind `session` to `authority`, the wallet starting the run
+/// A fresh session (no player yet) is claimed; one already bound to another wallet is refused,
+/// so nobody can take over or reset a session `authorize_signer` would then accept them on
+pub fn claim_session(session: &mut GameSession, authority: Pubkey) -> Result<()> {
+    require!(
+        session.player.is_none() || session.player == Some(authority),
+        StartGameError::SessionNotOwned
+    );
+    session.player = Some(authority);
+    Ok(())
+}
+
+/// Give a brand-new player `starter_revives` free revives, once; returns whether they were
+/// granted. Players with a game behind them predate the grant and don't get it either
+pub fn grant_starter_revives(
+    player: &mut Player,
+    extras: &mut PlayerExtras,
+    starter_revives: u8,
+) -> bool {
+    if player.games_played != 0 || extras.starter_revives_granted {
+        return false;
     }
+    player.revives = player.revives.saturating_add(starter_revives);
+    extras.starter_revives_granted = true;
+    true
 }
 
 #[system]
 pub mod start_game {
     /// Start a new game session with selected character
+    /// Extra accounts: [player entity]
     pub fn execute(ctx: Context<Components>, args: Vec<u8>) -> Result<Components> {
         let session = &mut ctx.accounts.game_session;
         let clock = Clock::get()?;
 
-        // Parse character_id from args (simple format: 4 bytes len + string)
-        let character_id = if args.len() >= 4 {
-            let len = u32::from_le_bytes([args[0], args[1], args[2], args[3]]) as usize;
-            if len > 0 && len < 25 && args.len() >= 4 + len {
-                // Safe conversion
-                let mut id = String::new();
-                for &b in &args[4..4+len] {
-                    if b.is_ascii_alphanumeric() {
-                        id.push(b as char);
-                    }
-                }
-                id
-            } else {
-                String::from("imelda")
-            }
-        } else {
-            String::from("imelda")
-        };
+        let (character_id, difficulty, slot) = parse_start_args(&args)?;
 
-        let (hp, max_hp) = get_character_hp(&character_id);
+        let max_hp = starting_max_hp(character_base_hp(&character_id), difficulty);
+        let hp = max_hp;
 
         // Initialize game session
         session.character_id = character_id;
@@ -56,6 +118,66 @@ pub mod start_game {
         session.is_dead = false;
         session.started_at = clock.unix_timestamp;
         session.kills = 0;
+        session.revives_used = 0;
+        session.last_update_at = clock.unix_timestamp;
+        session.gold_collected_at = clock.unix_timestamp;
+        session.xp_allowance = 0;
+        session.difficulty = difficulty;
+        session.slot = slot;
+        session.wave_start_gold = 0;
+        session.anomalies_reported = 0;
+        session.suspicious_flags = 0;
+        session.tick = 0;
+        session.paused_at = 0;
+        session.paused_secs = 0;
+        session.weapons = [0; 6];
+        session.weapon_levels = [0; 6];
+        session.passives = [0; 6];
+        session.passive_levels = [0; 6];
+        session.boss_id = 0;
+        session.boss_hp = 0;
+        session.boss_active = false;
+        session.boss_damaged_at = 0;
+        session.bosses_defeated = 0;
+        session.combo_count = 0;
+        session.combo_expires_at = 0;
+        session.combo_bonus_gold = 0;
+        session.damage_dealt = 0;
+        session.damage_taken = 0;
+        session.damage_healed = 0;
+        session.buff_ids = [0; 8];
+        session.buff_magnitudes = [0; 8];
+        session.buff_expires_at = [0; 8];
+        // Snapshot so the ER-side update_stats never needs the L1 config; only the world
+        // config's rules, flags and oracle apply to a run
+        require_canonical_config(&ctx.accounts.config.key())?;
+        session.death_hp_threshold = ctx.accounts.config.death_hp_threshold;
+        session.feature_flags = ctx.accounts.config.feature_flags;
+        session.vrf_oracle = ctx.accounts.config.vrf_oracle;
+        session.pickup_gold_rate_bps = ctx.accounts.config.pickup_gold_rate_bps;
+        session.pickups_collected = 0;
+        session.randomness_nonce = 0;
+        session.randomness_pending = false;
+        session.pending_randomness = [0; 32];
+        session.randomness_commitment = [0; 32];
+        session.randomness_client_seed = [0; 32];
+        session.randomness_event = 0;
+
+        // Onboarding: a brand-new player gets the configured free revives, exactly once; the
+        // grant is tracked on the player's own PlayerExtras
+        require_player_entity(
+            ctx.remaining_accounts.first().map(|entity| entity.key),
+            &ctx.accounts.player.key(),
+            &ctx.accounts.player_extras.key(),
+        )?;
+        let player = &mut ctx.accounts.player;
+        let extras = &mut ctx.accounts.player_extras;
+        require!(
+            player.authority == Some(ctx.accounts.authority.key()),
+            StartGameError::NotAuthority
+        );
+        claim_session(session, ctx.accounts.authority.key())?;
+        grant_starter_revives(player, extras, ctx.accounts.config.starter_revives);
 
         Ok(ctx.accounts)
     }
@@ -63,5 +185,139 @@ pub mod start_game {
     #[system_input]
     pub struct Components {
         pub game_session: GameSession,
+        pub config: Config,
+        pub player: Player,
+        pub player_extras: PlayerExtras,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn start_args(id: &str, extra: &[u8]) -> Vec<u8> {
+        let mut args = (id.len() as u32).to_le_bytes().to_vec();
+        args.extend_from_slice(id.as_bytes());
+        args.extend_from_slice(extra);
+        args
+    }
+
+    #[test]
+    fn empty_args_start_the_starter() {
+        assert_eq!(
+            parse_start_args(&[]).unwrap(),
+            (String::from(STARTER_CHARACTER), 0, 0)
+        );
+    }
+
+    #[test]
+    fn parses_id_difficulty_and_slot() {
+        assert_eq!(
+            parse_start_args(&start_args("Antonio", &[])).unwrap(),
+            (String::from("antonio"), 0, 0)
+        );
+        assert_eq!(
+            parse_start_args(&start_args("vitalis", &[MAX_DIFFICULTY, 3])).unwrap(),
+            (String::from("vitalis"), MAX_DIFFICULTY, 3)
+        );
+    }
+
+    #[test]
+    fn out_of_range_difficulty_is_rejected() {
+        assert_eq!(
+            parse_start_args(&start_args("vitalis", &[MAX_DIFFICULTY + 1])).unwrap_err(),
+            StartGameError::InvalidDifficulty.into()
+        );
+    }
+
+    #[test]
+    fn max_hp_drops_with_difficulty() {
+        use game_rules::difficulty::{DIFFICULTY_HARD, DIFFICULTY_NIGHTMARE};
+
+        let base = character_base_hp("antonio");
+        let max_hp = |difficulty| {
+            let (id, difficulty, _) =
+                parse_start_args(&start_args("antonio", &[difficulty])).unwrap();
+            starting_max_hp(character_base_hp(&id), difficulty)
+        };
+        assert_eq!(max_hp(DIFFICULTY_NORMAL), base);
+        assert_eq!(max_hp(DIFFICULTY_HARD), base - 20);
+        assert_eq!(max_hp(DIFFICULTY_NIGHTMARE), base - 40);
+    }
+
+    #[test]
+    fn malformed_prefix_is_rejected() {
+        let invalid: Error = StartGameError::InvalidArgs.into();
+        assert_eq!(parse_start_args(&[1, 0]).unwrap_err(), invalid);
+        assert_eq!(parse_start_args(&0u32.to_le_bytes()).unwrap_err(), invalid);
+        let mut long = start_args("imelda", &[]);
+        long[..4].copy_from_slice(&21u32.to_le_bytes());
+        long.resize(4 + 21, b'a');
+        assert_eq!(parse_start_args(&long).unwrap_err(), invalid);
+        let short = start_args("antonio", &[]);
+        assert_eq!(parse_start_args(&short[..short.len() - 1]).unwrap_err(), invalid);
+    }
+
+    #[test]
+    fn unknown_character_and_slot_are_rejected() {
+        assert_eq!(
+            parse_start_args(&start_args("nobody", &[])).unwrap_err(),
+            StartGameError::UnknownCharacter.into()
+        );
+        assert_eq!(
+            parse_start_args(&start_args("imelda", &[0, MAX_SESSION_SLOTS])).unwrap_err(),
+            StartGameError::InvalidSlot.into()
+        );
+    }
+
+    #[test]
+    fn fresh_or_own_session_is_claimed() {
+        let authority = Pubkey::new_unique();
+        let mut session = GameSession::default();
+        claim_session(&mut session, authority).unwrap();
+        assert_eq!(session.player, Some(authority));
+        claim_session(&mut session, authority).unwrap();
+        assert_eq!(session.player, Some(authority));
+    }
+
+    #[test]
+    fn another_players_session_is_rejected() {
+        let owner = Pubkey::new_unique();
+        let mut session = GameSession {
+            player: Some(owner),
+            ..Default::default()
+        };
+        assert_eq!(
+            claim_session(&mut session, Pubkey::new_unique()).unwrap_err(),
+            StartGameError::SessionNotOwned.into()
+        );
+        assert_eq!(session.player, Some(owner));
+    }
+
+    #[test]
+    fn configured_starter_revives_are_granted() {
+        let mut player = Player { revives: 1, ..Default::default() };
+        let mut extras = PlayerExtras::default();
+        assert!(grant_starter_revives(&mut player, &mut extras, 3));
+        assert_eq!(player.revives, 4);
+        assert!(extras.starter_revives_granted);
+    }
+
+    #[test]
+    fn starter_revives_are_not_granted_on_a_second_game() {
+        let mut player = Player::default();
+        let mut extras = PlayerExtras::default();
+        assert!(grant_starter_revives(&mut player, &mut extras, 3));
+
+        // Restarting before the first run was ended still counts as a second game
+        assert!(!grant_starter_revives(&mut player, &mut extras, 3));
+        player.games_played = 1;
+        assert!(!grant_starter_revives(&mut player, &mut extras, 3));
+        assert_eq!(player.revives, 3);
+
+        // Nor to a veteran whose extras were only just created
+        let mut veteran = Player { games_played: 5, ..Default::default() };
+        assert!(!grant_starter_revives(&mut veteran, &mut PlayerExtras::default(), 3));
+        assert_eq!(veteran.revives, 0);
     }
 }
@@ -1,36 +1,98 @@
 use bolt_lang::*;
+use config::{require_canonical_config, Config};
+use game_rules::characters::character_index;
 use leaderboard::LeaderboardEntry;
+use player::Player;
 
 declare_id!("6did5KX3mcbi58jUQ85ZtTV5ahCD71pfFSF96cu73g2A");
 
 /// Args: player_pubkey(32) + best_time(4) + best_wave(1) + total_gold(8) + games_played(4) = 49 bytes minimum
+/// Optional trailing environment_id(1); older clients omit it and submit to the default map
+/// The stats fields are kept for older clients but not trusted: the entry takes them from the
+/// Player. A trailing character_index(1) from older clients is ignored too, as the index comes
+/// from the player's `last_character_id`
+pub const SUBMIT_SCORE_MIN_LEN: usize = 49;
+
+pub const PLAYER_OFFSET: usize = 0;
+pub const BEST_TIME_OFFSET: usize = PLAYER_OFFSET + 32;
+pub const BEST_WAVE_OFFSET: usize = BEST_TIME_OFFSET + 4;
+pub const TOTAL_GOLD_OFFSET: usize = BEST_WAVE_OFFSET + 1;
+pub const GAMES_PLAYED_OFFSET: usize = TOTAL_GOLD_OFFSET + 8;
+pub const ENVIRONMENT_ID_OFFSET: usize = GAMES_PLAYED_OFFSET + 4;
+
+const _: () = assert!(ENVIRONMENT_ID_OFFSET == SUBMIT_SCORE_MIN_LEN);
+
+#[error_code]
+pub enum SubmitScoreError {
+    #[msg("Submitted again before the configured interval")]
+    SubmitTooSoon,
+    #[msg("Submitted player does not match the Player component")]
+    PlayerMismatch,
+    #[msg("Signer is not the player authority")]
+    NotAuthority,
+}
+
+/// Promote `player`'s stats to its leaderboard entry, signed by `authority` at `now`
+/// Only the player's own wallet may submit, at most once per `config.submit_interval_secs`
+/// (so `updated_at` can't be churned for recency tie-breaks). Args shorter than
+/// `SUBMIT_SCORE_MIN_LEN` are a no-op, as before
+pub fn apply_submission(
+    leaderboard: &mut LeaderboardEntry,
+    player: &Player,
+    config: &Config,
+    authority: &Pubkey,
+    args: &[u8],
+    now: i64,
+) -> Result<()> {
+    if args.len() < SUBMIT_SCORE_MIN_LEN {
+        return Ok(());
+    }
+
+    require!(
+        player.authority == Some(*authority),
+        SubmitScoreError::NotAuthority
+    );
+    require!(
+        config.submit_allowed(leaderboard.updated_at, now),
+        SubmitScoreError::SubmitTooSoon
+    );
+
+    // Read player pubkey (32 bytes); it must be the Player whose stats are recorded
+    let player_bytes: [u8; 32] = args[PLAYER_OFFSET..BEST_TIME_OFFSET].try_into().unwrap();
+    let submitted = Some(Pubkey::new_from_array(player_bytes));
+    require!(submitted == player.authority, SubmitScoreError::PlayerMismatch);
+    leaderboard.player = submitted;
+
+    // Numeric stats come from the Player, which only `end_game` promotes runs into
+    leaderboard.best_time = player.best_time;
+    leaderboard.best_wave = player.best_wave;
+    leaderboard.total_gold = player.total_gold;
+    leaderboard.games_played = player.games_played;
+    leaderboard.environment_id = args.get(ENVIRONMENT_ID_OFFSET).copied().unwrap_or(0);
+    leaderboard.character_index = character_index(&player.last_character_id);
+    leaderboard.updated_at = now;
+
+    // Note: name and character_id must be set during initialization
+    // We don't modify strings here to avoid memory allocation issues
+    Ok(())
+}
+
 #[system]
 pub mod submit_score {
     /// Update leaderboard with numeric stats only (strings set via init)
     pub fn execute(ctx: Context<Components>, args: Vec<u8>) -> Result<Components> {
-        let leaderboard = &mut ctx.accounts.leaderboard;
         let clock = Clock::get()?;
 
-        if args.len() < 49 {
-            return Ok(ctx.accounts);
-        }
-
-        // Read player pubkey (32 bytes)
-        let player_bytes: [u8; 32] = args[0..32].try_into().unwrap();
-        leaderboard.player = Some(Pubkey::new_from_array(player_bytes));
-
-        // Read numeric stats only
-        leaderboard.best_time = u32::from_le_bytes([args[32], args[33], args[34], args[35]]);
-        leaderboard.best_wave = args[36];
-        leaderboard.total_gold = u64::from_le_bytes([
-            args[37], args[38], args[39], args[40],
-            args[41], args[42], args[43], args[44]
-        ]);
-        leaderboard.games_played = u32::from_le_bytes([args[45], args[46], args[47], args[48]]);
-        leaderboard.updated_at = clock.unix_timestamp;
-
-        // Note: name and character_id must be set during initialization
-        // We don't modify strings here to avoid memory allocation issues
+        // The submit interval comes from the world config, not one the caller set up
+        require_canonical_config(&ctx.accounts.config.key())?;
+        apply_submission(
+            &mut ctx.accounts.leaderboard,
+            &ctx.accounts.player,
+            &ctx.accounts.config,
+            &ctx.accounts.authority.key(),
+            &args,
+            clock.unix_timestamp,
+        )?;
 
         Ok(ctx.accounts)
     }
@@ -38,5 +100,111 @@ pub mod submit_score {
     #[system_input]
     pub struct Components {
         pub leaderboard: LeaderboardEntry,
+        pub config: Config,
+        pub player: Player,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOW: i64 = 1_700_000_000;
+
+    fn player() -> Player {
+        Player {
+            authority: Some(Pubkey::new_unique()),
+            best_time: 300,
+            best_wave: 9,
+            total_gold: 1_500,
+            games_played: 12,
+            last_character_id: String::from("antonio"),
+            ..Default::default()
+        }
+    }
+
+    fn config() -> Config {
+        Config {
+            submit_interval_secs: 60,
+            ..Default::default()
+        }
+    }
+
+    /// Args for `player` claiming wave 255 and a huge gold total, on map 2
+    fn args(player: &Player) -> Vec<u8> {
+        let mut args = player.authority.unwrap().to_bytes().to_vec();
+        args.extend_from_slice(&u32::MAX.to_le_bytes());
+        args.push(u8::MAX);
+        args.extend_from_slice(&u64::MAX.to_le_bytes());
+        args.extend_from_slice(&u32::MAX.to_le_bytes());
+        args.push(2);
+        args
+    }
+
+    fn submit(entry: &mut LeaderboardEntry, player: &Player, now: i64) -> Result<()> {
+        let authority = player.authority.unwrap();
+        apply_submission(entry, player, &config(), &authority, &args(player), now)
+    }
+
+    #[test]
+    fn submission_after_the_interval_takes_the_players_stats() {
+        let player = player();
+        let mut entry = LeaderboardEntry {
+            updated_at: NOW - 60,
+            ..Default::default()
+        };
+        submit(&mut entry, &player, NOW).unwrap();
+        assert_eq!(entry.player, player.authority);
+        assert_eq!((entry.best_time, entry.best_wave), (300, 9));
+        assert_eq!((entry.total_gold, entry.games_played), (1_500, 12));
+        assert_eq!(entry.character_index, character_index("antonio"));
+        assert_eq!((entry.environment_id, entry.updated_at), (2, NOW));
+    }
+
+    #[test]
+    fn submission_too_soon_is_rejected() {
+        let player = player();
+        let mut entry = LeaderboardEntry {
+            updated_at: NOW - 59,
+            ..Default::default()
+        };
+        assert_eq!(
+            submit(&mut entry, &player, NOW).unwrap_err(),
+            SubmitScoreError::SubmitTooSoon.into()
+        );
+        assert_eq!(entry.updated_at, NOW - 59);
+    }
+
+    #[test]
+    fn only_the_players_wallet_submits() {
+        let player = player();
+        let mut entry = LeaderboardEntry::default();
+        let err = apply_submission(
+            &mut entry,
+            &player,
+            &config(),
+            &Pubkey::new_unique(),
+            &args(&player),
+            NOW,
+        )
+        .unwrap_err();
+        assert_eq!(err, SubmitScoreError::NotAuthority.into());
+    }
+
+    #[test]
+    fn submission_for_another_player_is_rejected() {
+        let player = player();
+        let mut args = args(&player);
+        args[..32].copy_from_slice(&Pubkey::new_unique().to_bytes());
+        let err = apply_submission(
+            &mut LeaderboardEntry::default(),
+            &player,
+            &config(),
+            &player.authority.unwrap(),
+            &args,
+            NOW,
+        )
+        .unwrap_err();
+        assert_eq!(err, SubmitScoreError::PlayerMismatch.into());
     }
 }
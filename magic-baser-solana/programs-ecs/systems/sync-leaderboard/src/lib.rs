@@ -0,0 +1,85 @@
+use bolt_lang::*;
+use game_rules::characters::character_index;
+use leaderboard::LeaderboardEntry;
+use player::Player;
+
+declare_id!("AYzK3YkZ8bpKvMxBEb9NHULAzF9xYxLcvGtdLKmsFBjs");
+
+#[error_code]
+pub enum SyncLeaderboardError {
+    #[msg("Leaderboard entry belongs to a different player")]
+    WrongLeaderboard,
+}
+
+#[system]
+pub mod sync_leaderboard {
+    /// Repair a leaderboard entry whose cached fields drifted from the player
+    /// Only copies the player's own values, so anyone may run it. It leaves `updated_at` alone:
+    /// that is the submission time `submit-score` throttles on and ranks recency by
+    pub fn execute(ctx: Context<Components>, _args: Vec<u8>) -> Result<Components> {
+        require!(
+            ctx.accounts.player.authority.is_some()
+                && ctx.accounts.leaderboard.player == ctx.accounts.player.authority,
+            SyncLeaderboardError::WrongLeaderboard
+        );
+
+        sync_leaderboard(&ctx.accounts.player, &mut ctx.accounts.leaderboard);
+
+        Ok(ctx.accounts)
+    }
+
+    #[system_input]
+    pub struct Components {
+        pub player: Player,
+        pub leaderboard: LeaderboardEntry,
+    }
+}
+
+/// Copy the fields cached on the leaderboard entry from the player, which is canonical
+/// Shared with `end-game-and-submit` and `rename-player` so the cache can't drift
+pub fn sync_leaderboard(player: &Player, entry: &mut LeaderboardEntry) {
+    entry.name = player.name.clone();
+    entry.best_time = player.best_time;
+    entry.best_wave = player.best_wave;
+    entry.total_gold = player.total_gold;
+    entry.games_played = player.games_played;
+    entry.character_index = character_index(&player.last_character_id);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn drifted_entry_is_corrected_from_the_player() {
+        let player = Player {
+            authority: Some(Pubkey::new_unique()),
+            name: String::from("ada"),
+            best_time: 300,
+            best_wave: 9,
+            total_gold: 1_500,
+            games_played: 12,
+            last_character_id: String::from("vitalis"),
+            ..Default::default()
+        };
+        let mut entry = LeaderboardEntry {
+            player: player.authority,
+            name: String::from("old name"),
+            best_time: 10,
+            best_wave: 1,
+            total_gold: 99_999,
+            games_played: 1,
+            updated_at: 42,
+            best_score: 7_000,
+            ..Default::default()
+        };
+        sync_leaderboard(&player, &mut entry);
+
+        assert_eq!(entry.name, "ada");
+        assert_eq!((entry.best_time, entry.best_wave), (300, 9));
+        assert_eq!((entry.total_gold, entry.games_played), (1_500, 12));
+        assert_eq!(entry.character_index, character_index("vitalis"));
+        // Fields the player doesn't hold are left as they were
+        assert_eq!((entry.updated_at, entry.best_score), (42, 7_000));
+    }
+}
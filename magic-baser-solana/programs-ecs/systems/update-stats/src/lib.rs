@@ -1,5 +1,19 @@
 use bolt_lang::*;
-use game_session::GameSession;
+use game_rules::anticheat::{
+    max_gold_gain, max_kills_gain, max_kills_through_wave, max_xp_gain, resolve_is_dead,
+    xp_within_level,
+};
+use game_rules::features::{
+    FEATURE_ANTI_CHEAT, FEATURE_AUTO_XP, FEATURE_GOLD_PICKUPS, FEATURE_SERVER_TIME,
+};
+use game_rules::progression::{
+    calculate_level, combo_scaled, kill_xp, LEVEL_UP_MAX_HP_BONUS, XP_TOLERANCE,
+};
+use game_session::{
+    GameSession, ANOMALY_LEVEL_ABOVE_XP, SUSPICIOUS_GOLD_RATE, SUSPICIOUS_HP_OVER_MAX,
+    SUSPICIOUS_KILL_RATE, SUSPICIOUS_WAVE_DECREASE, SUSPICIOUS_WAVE_RATE, SUSPICIOUS_XP_RATE,
+};
+use wave_history::WaveHistory;
 
 declare_id!("7FeyB4hz8LCrBYJusgEzKReT9rbgkrqdbB2L6aoMPv88");
 
@@ -9,6 +23,34 @@ pub enum UpdateStatsError {
     InvalidArguments,
     #[msg("Session not active")]
     SessionNotActive,
+    #[msg("HP increased faster than regeneration allows")]
+    HpRegenTooHigh,
+    #[msg("XP is below the threshold of the current level")]
+    XpBelowLevel,
+    #[msg("XP exceeds what the run's kills have earned")]
+    XpMismatch,
+    #[msg("Update tick is not the session's next tick")]
+    StaleTick,
+    #[msg("Session is paused")]
+    SessionPaused,
+    #[msg("Kills fell below the session's count")]
+    KillsDecreased,
+    #[msg("Gold fell below the session's total")]
+    GoldDecreased,
+}
+
+/// Gold earned during one completed wave, for economy tuning
+#[event]
+pub struct WaveGold {
+    pub wave: u8,
+    pub gold_delta: u32,
+}
+
+/// Session state that contradicts itself, for monitoring; the state is left as-is
+#[event]
+pub struct StateAnomaly {
+    /// `game_session::ANOMALY_*` code of the inconsistent field
+    pub field_code: u8,
 }
 
 /// Arguments for updating game stats (called from ER every 200ms)
@@ -17,11 +59,192 @@ pub struct UpdateStatsArgs {
     pub hp: u16,
     pub xp: u32,
     pub gold_earned: u32,
+    /// Seconds since the run started, paused time included; the session's `paused_secs` is
+    /// taken off on-chain
     pub time_survived: u32,
     pub wave: u8,
     pub kills: u32,
     pub level: u8,
     pub is_dead: bool,
+    /// Client combo counter at this tick (0 for v1 payloads); informational only, XP and gold
+    /// use the session's on-chain combo
+    pub combo: u32,
+    /// Sequence number of this update, the session's `tick + 1` (None for v1/v2 payloads,
+    /// which carry no tick and simply advance the session's)
+    pub tick: Option<u64>,
+    /// Run total of damage dealt (the session's counter for payloads before v5)
+    pub damage_dealt: u32,
+    /// Run total of damage taken
+    pub damage_taken: u32,
+    /// Run total of HP healed
+    pub damage_healed: u32,
+}
+
+impl UpdateStatsArgs {
+    /// Layout without combo
+    pub const VERSION_V1: u8 = 1;
+    /// v1 plus a trailing combo
+    pub const VERSION_V2: u8 = 2;
+    /// `mask(2)` + `tick(8)` followed by only the fields whose `FIELD_*` bit is set, in field order
+    pub const VERSION_DELTA: u8 = 3;
+    /// v2 plus a trailing tick
+    pub const VERSION_V4: u8 = 4;
+    /// v4 plus trailing damage counters
+    pub const VERSION_V5: u8 = 5;
+
+    pub const FIELD_HP: u16 = 1 << 0;
+    pub const FIELD_XP: u16 = 1 << 1;
+    pub const FIELD_GOLD_EARNED: u16 = 1 << 2;
+    pub const FIELD_TIME_SURVIVED: u16 = 1 << 3;
+    pub const FIELD_WAVE: u16 = 1 << 4;
+    pub const FIELD_KILLS: u16 = 1 << 5;
+    pub const FIELD_LEVEL: u16 = 1 << 6;
+    pub const FIELD_IS_DEAD: u16 = 1 << 7;
+    pub const FIELD_COMBO: u16 = 1 << 8;
+    pub const FIELD_DAMAGE_DEALT: u16 = 1 << 9;
+    pub const FIELD_DAMAGE_TAKEN: u16 = 1 << 10;
+    pub const FIELD_DAMAGE_HEALED: u16 = 1 << 11;
+    const FIELDS_ALL: u16 = (1 << 12) - 1;
+
+    /// v1 body: hp(2) + xp(4) + gold_earned(4) + time_survived(4) + wave(1) + kills(4) + level(1) + is_dead(1)
+    pub const PACKED_LEN: usize = 21;
+    /// v2 body: v1 plus combo(4)
+    pub const PACKED_LEN_WITH_COMBO: usize = Self::PACKED_LEN + 4;
+    /// v4 body: v2 plus tick(8)
+    pub const PACKED_LEN_WITH_TICK: usize = Self::PACKED_LEN_WITH_COMBO + 8;
+    /// v5 body: v4 plus damage_dealt(4) + damage_taken(4) + damage_healed(4)
+    pub const PACKED_LEN_WITH_DAMAGE: usize = Self::PACKED_LEN_WITH_TICK + 12;
+
+    /// Decode `version(1)` followed by that version's little-endian body
+    /// v1/v2 bodies are fixed-width and byte-compatible with the Borsh encoding, but skip the
+    /// generic reader on the hot path; delta bodies, and versions before damage counters, take
+    /// unsent fields from `current`. Unknown versions and bodies of the wrong length are rejected
+    pub fn unpack(data: &[u8], current: &GameSession) -> Option<Self> {
+        let (&version, data) = data.split_first()?;
+        if version == Self::VERSION_DELTA {
+            return Self::unpack_delta(data, current);
+        }
+        let (has_combo, has_tick, has_damage) = match (version, data.len()) {
+            (Self::VERSION_V1, Self::PACKED_LEN) => (false, false, false),
+            (Self::VERSION_V2, Self::PACKED_LEN_WITH_COMBO) => (true, false, false),
+            (Self::VERSION_V4, Self::PACKED_LEN_WITH_TICK) => (true, true, false),
+            (Self::VERSION_V5, Self::PACKED_LEN_WITH_DAMAGE) => (true, true, true),
+            _ => return None,
+        };
+        let tick = if has_tick {
+            Some(u64::from_le_bytes(data[25..33].try_into().ok()?))
+        } else {
+            None
+        };
+        let u32_at = |at: usize| {
+            u32::from_le_bytes([data[at], data[at + 1], data[at + 2], data[at + 3]])
+        };
+        let (damage_dealt, damage_taken, damage_healed) = if has_damage {
+            (u32_at(33), u32_at(37), u32_at(41))
+        } else {
+            (current.damage_dealt, current.damage_taken, current.damage_healed)
+        };
+        let combo = if has_combo {
+            u32::from_le_bytes([data[21], data[22], data[23], data[24]])
+        } else {
+            0
+        };
+        let is_dead = match data[20] {
+            0 => false,
+            1 => true,
+            _ => return None,
+        };
+        Some(Self {
+            hp: u16::from_le_bytes([data[0], data[1]]),
+            xp: u32::from_le_bytes([data[2], data[3], data[4], data[5]]),
+            gold_earned: u32::from_le_bytes([data[6], data[7], data[8], data[9]]),
+            time_survived: u32::from_le_bytes([data[10], data[11], data[12], data[13]]),
+            wave: data[14],
+            kills: u32::from_le_bytes([data[15], data[16], data[17], data[18]]),
+            level: data[19],
+            is_dead,
+            combo,
+            tick,
+            damage_dealt,
+            damage_taken,
+            damage_healed,
+        })
+    }
+
+    /// Decode a delta body: most ticks only move `time_survived`, so they send 2 + 8 + 4 bytes
+    /// Combo is the streak at this tick rather than stored state, so an unsent combo is 0
+    fn unpack_delta(data: &[u8], current: &GameSession) -> Option<Self> {
+        let (mask, mut data) = data.split_first_chunk::<2>()?;
+        let mask = u16::from_le_bytes(*mask);
+        let tick = u64::from_le_bytes(take(&mut data)?);
+        if mask & !Self::FIELDS_ALL != 0 {
+            return None;
+        }
+
+        let mut args = Self {
+            hp: current.hp,
+            xp: current.xp,
+            gold_earned: current.raw_gold(),
+            time_survived: current.time_survived.saturating_add(current.paused_secs),
+            wave: current.wave,
+            kills: current.kills,
+            level: current.level,
+            is_dead: current.is_dead,
+            combo: 0,
+            tick: Some(tick),
+            damage_dealt: current.damage_dealt,
+            damage_taken: current.damage_taken,
+            damage_healed: current.damage_healed,
+        };
+        if mask & Self::FIELD_HP != 0 {
+            args.hp = u16::from_le_bytes(take(&mut data)?);
+        }
+        if mask & Self::FIELD_XP != 0 {
+            args.xp = u32::from_le_bytes(take(&mut data)?);
+        }
+        if mask & Self::FIELD_GOLD_EARNED != 0 {
+            args.gold_earned = u32::from_le_bytes(take(&mut data)?);
+        }
+        if mask & Self::FIELD_TIME_SURVIVED != 0 {
+            args.time_survived = u32::from_le_bytes(take(&mut data)?);
+        }
+        if mask & Self::FIELD_WAVE != 0 {
+            args.wave = take::<1>(&mut data)?[0];
+        }
+        if mask & Self::FIELD_KILLS != 0 {
+            args.kills = u32::from_le_bytes(take(&mut data)?);
+        }
+        if mask & Self::FIELD_LEVEL != 0 {
+            args.level = take::<1>(&mut data)?[0];
+        }
+        if mask & Self::FIELD_IS_DEAD != 0 {
+            args.is_dead = match take::<1>(&mut data)?[0] {
+                0 => false,
+                1 => true,
+                _ => return None,
+            };
+        }
+        if mask & Self::FIELD_COMBO != 0 {
+            args.combo = u32::from_le_bytes(take(&mut data)?);
+        }
+        if mask & Self::FIELD_DAMAGE_DEALT != 0 {
+            args.damage_dealt = u32::from_le_bytes(take(&mut data)?);
+        }
+        if mask & Self::FIELD_DAMAGE_TAKEN != 0 {
+            args.damage_taken = u32::from_le_bytes(take(&mut data)?);
+        }
+        if mask & Self::FIELD_DAMAGE_HEALED != 0 {
+            args.damage_healed = u32::from_le_bytes(take(&mut data)?);
+        }
+        data.is_empty().then_some(args)
+    }
+}
+
+/// Split the next `N` bytes off `data`
+fn take<const N: usize>(data: &mut &[u8]) -> Option<[u8; N]> {
+    let (head, rest) = data.split_first_chunk::<N>()?;
+    *data = rest;
+    Some(*head)
 }
 
 #[system]
@@ -30,29 +253,109 @@ pub mod update_stats {
     /// This is called frequently from ER (every 200ms) during gameplay
     /// Updates are gasless when running in Ephemeral Rollup
     pub fn execute(ctx: Context<Components>, args: Vec<u8>) -> Result<Components> {
-        let args: UpdateStatsArgs = UpdateStatsArgs::try_from_slice(&args)
-            .map_err(|_| UpdateStatsError::InvalidArguments)?;
+        let mut args = UpdateStatsArgs::unpack(&args, &ctx.accounts.game_session)
+            .ok_or(UpdateStatsError::InvalidArguments)?;
 
+        let session_key = ctx.accounts.game_session.key();
+        let signer = ctx.accounts.authority.key();
         let session = &mut ctx.accounts.game_session;
 
         // Verify session is active
         require!(session.is_active, UpdateStatsError::SessionNotActive);
+        require!(!session.is_paused(), UpdateStatsError::SessionPaused);
+
+        // The player's wallet may update directly; anyone else needs the player's session token
+        session.authorize_signer(
+            &session_key,
+            &signer,
+            ctx.remaining_accounts.first(),
+            &crate::ID,
+        )?;
+
+        // Updates apply strictly in order: stale, replayed or skipped ticks are rejected
+        session.tick = next_tick(args.tick, session.tick)?;
+
+        // Counters are run totals: a lower report is refused, not clamped
+        require_monotonic(&args, session)?;
+
+        let anti_cheat = session.is_enabled(FEATURE_ANTI_CHEAT);
+
+        // Clamp implausible values and flag them, rather than failing the tick
+        let now = Clock::get()?.unix_timestamp;
+        if anti_cheat {
+            let flags = apply_plausibility_bounds(&mut args, session, now);
+            session.suspicious_flags |= flags;
+        }
+
+        // Bound HP gains by the character's regen rate; revives already raised the stored HP
+        if anti_cheat {
+            require_hp_regen(&args, session, now.saturating_sub(session.last_update_at))?;
+        }
+
+        // The combo is tracked on-chain: new kills extend it, and this update's gains use the
+        // multiplier that was live before them
+        let new_kills = args.kills - session.kills;
+        let combo = session.advance_combo(new_kills, now);
+
+        // Credit XP for new kills at the combo
+        session.xp_allowance = session
+            .xp_allowance
+            .saturating_add(kill_xp(new_kills, combo, args.wave));
+
+        // Gold pickups: gold only moves through `collect_gold`, whatever the client reports
+        // Otherwise gold gains earn the combo multiplier too; the bonus is kept apart so the
+        // client's raw totals keep diffing against the session. `require_monotonic` refused
+        // lower totals, so only new gold is ever scaled
+        let gold_earned = if session.is_enabled(FEATURE_GOLD_PICKUPS) {
+            session.gold_earned
+        } else {
+            let gold_gain = args.gold_earned - session.raw_gold();
+            let bonus = combo_scaled(gold_gain, combo).saturating_sub(gold_gain);
+            session.combo_bonus_gold = session.combo_bonus_gold.saturating_add(bonus);
+            args.gold_earned.saturating_add(session.combo_bonus_gold)
+        };
+
+        // Auto-XP: the allowance is the XP; otherwise take the client's, held to the allowance
+        let xp = if session.is_enabled(FEATURE_AUTO_XP) {
+            session.xp_allowance.max(session.xp)
+        } else {
+            if anti_cheat {
+                require_client_xp(args.xp, session)?;
+            }
+            args.xp
+        };
+
+        // Server time: measure the run from `started_at` instead of trusting the client
+        // Paused time is left out either way
+        let time_survived = if session.is_enabled(FEATURE_SERVER_TIME) {
+            session.played_secs(now)
+        } else {
+            args.time_survived.saturating_sub(session.paused_secs)
+        };
+
+        // Report the finished wave's gold
+        let history = &mut ctx.accounts.wave_history;
+        if let Some(wave_gold) =
+            finish_wave(session, history, &args, gold_earned, time_survived, now)
+        {
+            emit!(wave_gold);
+        }
+
+        // Level only moves with XP, so skip the threshold walk on ticks where XP is unchanged
+        let xp_changed = xp != session.xp;
 
         // Update session stats
-        session.hp = args.hp;
-        session.xp = args.xp;
-        session.gold_earned = args.gold_earned;
-        session.time_survived = args.time_survived;
-        session.wave = args.wave;
-        session.kills = args.kills;
-        session.is_dead = args.is_dead;
+        write_stats(session, &args, xp, gold_earned, time_survived);
+        session.last_update_at = now;
+
+        // Drop buffs that ran out since the last update
+        session.expire_buffs(now);
 
         // Handle level up (XP thresholds: 100, 250, 500, 1000, etc.)
-        let new_level = calculate_level(args.xp);
-        if new_level > session.level {
-            session.level = new_level;
-            // Increase max HP on level up
-            session.max_hp = session.max_hp.saturating_add(10);
+        if xp_changed {
+            if let Some(anomaly) = apply_level(session, xp) {
+                emit!(anomaly);
+            }
         }
 
         Ok(ctx.accounts)
@@ -61,19 +364,577 @@ pub mod update_stats {
     #[system_input]
     pub struct Components {
         pub game_session: GameSession,
+        pub wave_history: WaveHistory,
+    }
+}
+
+/// Write a tick's stats to the session, skipping the fields it left unchanged
+/// Most ER ticks only move `time_survived` and HP, so the other stores are skipped. Returns
+/// the number of fields written
+pub fn write_stats(
+    session: &mut GameSession,
+    args: &UpdateStatsArgs,
+    xp: u32,
+    gold_earned: u32,
+    time_survived: u32,
+) -> u8 {
+    fn set<T: PartialEq>(field: &mut T, value: T) -> u8 {
+        if *field == value {
+            return 0;
+        }
+        *field = value;
+        1
+    }
+
+    let is_dead =
+        resolve_is_dead(session.is_dead, args.is_dead, args.hp, session.death_hp_threshold);
+    // Damage counters are run totals, so a stale or reordered report never lowers them
+    let damage_dealt = session.damage_dealt.max(args.damage_dealt);
+    let damage_taken = session.damage_taken.max(args.damage_taken);
+    let damage_healed = session.damage_healed.max(args.damage_healed);
+
+    set(&mut session.hp, args.hp)
+        + set(&mut session.xp, xp)
+        + set(&mut session.gold_earned, gold_earned)
+        + set(&mut session.time_survived, time_survived)
+        + set(&mut session.wave, args.wave)
+        + set(&mut session.kills, args.kills)
+        + set(&mut session.damage_dealt, damage_dealt)
+        + set(&mut session.damage_taken, damage_taken)
+        + set(&mut session.damage_healed, damage_healed)
+        + set(&mut session.is_dead, is_dead)
+}
+
+/// Close out the session's wave once `args` has moved past it
+/// Records the run's totals at the wave's end in `history`, starts the next wave's gold count
+/// and spawns any boss due. Returns the finished wave's gold; coin revives can spend gold
+/// mid-wave, so it never goes negative
+pub fn finish_wave(
+    session: &mut GameSession,
+    history: &mut WaveHistory,
+    args: &UpdateStatsArgs,
+    gold_earned: u32,
+    time_survived: u32,
+    now: i64,
+) -> Option<WaveGold> {
+    if args.wave <= session.wave {
+        return None;
+    }
+    let wave_gold = WaveGold {
+        wave: session.wave,
+        gold_delta: gold_earned.saturating_sub(session.wave_start_gold),
+    };
+    // Keep the run's totals at the wave's end for post-game analysis
+    history.push(session.started_at, session.wave, args.kills, gold_earned, time_survived);
+    session.wave_start_gold = gold_earned;
+    session.spawn_boss(args.wave, now);
+    Some(wave_gold)
+}
+
+/// Raise the session's level to the one `xp` reaches, adding max HP on a level up
+/// Level is never lowered, but a level above the XP curve means corrupted state: returns the
+/// anomaly to emit the first time the run hits it
+pub fn apply_level(session: &mut GameSession, xp: u32) -> Option<StateAnomaly> {
+    let new_level = calculate_level(xp);
+    if new_level > session.level {
+        session.level = new_level;
+        // Increase max HP on level up
+        session.max_hp = session.max_hp.saturating_add(LEVEL_UP_MAX_HP_BONUS);
+    } else if new_level < session.level && session.report_anomaly(ANOMALY_LEVEL_ABOVE_XP) {
+        return Some(StateAnomaly { field_code: ANOMALY_LEVEL_ABOVE_XP });
+    }
+    None
+}
+
+/// Session tick after an update carrying `tick`: a tick must be the session's next one, and
+/// payloads without one (v1/v2) advance it by one
+pub fn next_tick(tick: Option<u64>, current: u64) -> Result<u64> {
+    let next = current.saturating_add(1);
+    require!(tick.unwrap_or(next) == next, UpdateStatsError::StaleTick);
+    Ok(next)
+}
+
+/// Reject reports that lower a run-total counter: kills, and gold unless pickups credit it
+/// Accepting them would let a lower-then-raise pair credit the same kills, or the combo bonus
+/// on the same gold, twice. Coin revives lower the session's gold themselves, so the client's
+/// total after one still matches `raw_gold`
+pub fn require_monotonic(args: &UpdateStatsArgs, session: &GameSession) -> Result<()> {
+    require!(args.kills >= session.kills, UpdateStatsError::KillsDecreased);
+    require!(
+        session.is_enabled(FEATURE_GOLD_PICKUPS) || args.gold_earned >= session.raw_gold(),
+        UpdateStatsError::GoldDecreased
+    );
+    Ok(())
+}
+
+/// Reject an HP increase above the character's regen over `elapsed` seconds
+/// Revives write their HP to the session directly, so only gains since then are bounded
+pub fn require_hp_regen(
+    args: &UpdateStatsArgs,
+    session: &GameSession,
+    elapsed: i64,
+) -> Result<()> {
+    let gain = args.hp.saturating_sub(session.hp);
+    require!(
+        gain <= session.max_hp_gain(elapsed),
+        UpdateStatsError::HpRegenTooHigh
+    );
+    Ok(())
+}
+
+/// Reject client-reported XP under the current level's threshold or above the kill allowance
+/// Level never goes down, so XP may drop within a level but not back under it
+pub fn require_client_xp(xp: u32, session: &GameSession) -> Result<()> {
+    require!(
+        xp_within_level(xp, session.level),
+        UpdateStatsError::XpBelowLevel
+    );
+    require!(
+        xp <= session.xp_allowance.saturating_add(XP_TOLERANCE),
+        UpdateStatsError::XpMismatch
+    );
+    Ok(())
+}
+
+/// Clamp `args` to what the run's play until `now` allows
+/// The wave is bounded first, by the played time, and every other cap uses the bounded wave.
+/// Returns the `SUSPICIOUS_*` bits of every value that had to be clamped
+pub fn apply_plausibility_bounds(
+    args: &mut UpdateStatsArgs,
+    session: &GameSession,
+    now: i64,
+) -> u8 {
+    let elapsed = now.saturating_sub(session.last_update_at);
+    let mut flags = 0;
+
+    if args.hp > session.max_hp {
+        args.hp = session.max_hp;
+        flags |= SUSPICIOUS_HP_OVER_MAX;
+    }
+
+    if args.wave < session.wave {
+        args.wave = session.wave;
+        flags |= SUSPICIOUS_WAVE_DECREASE;
+    }
+
+    // Waves advance on the run's timer; the wave already accepted always stays allowed
+    let max_wave = session.max_wave(now).max(session.wave);
+    if args.wave > max_wave {
+        args.wave = max_wave;
+        flags |= SUSPICIOUS_WAVE_RATE;
+    }
+
+    // Kills are held to the time elapsed and to what the waves so far spawned, but never
+    // below the kills already accepted
+    let max_kills = session
+        .kills
+        .saturating_add(max_kills_gain(elapsed))
+        .min(max_kills_through_wave(args.wave, session.wave_seed()).max(session.kills));
+    if args.kills > max_kills {
+        args.kills = max_kills;
+        flags |= SUSPICIOUS_KILL_RATE;
+    }
+
+    let max_gold = session
+        .raw_gold()
+        .saturating_add(max_gold_gain(args.wave, elapsed));
+    if !session.is_enabled(FEATURE_GOLD_PICKUPS) && args.gold_earned > max_gold {
+        args.gold_earned = max_gold;
+        flags |= SUSPICIOUS_GOLD_RATE;
     }
+
+    let max_xp = session
+        .xp
+        .saturating_add(max_xp_gain(elapsed, session.combo_count, args.wave));
+    if args.xp > max_xp {
+        args.xp = max_xp;
+        flags |= SUSPICIOUS_XP_RATE;
+    }
+
+    flags
 }
 
-/// Calculate level from XP using exponential curve
-fn calculate_level(xp: u32) -> u8 {
-    if xp < 100 { 1 }
-    else if xp < 250 { 2 }
-    else if xp < 500 { 3 }
-    else if xp < 1000 { 4 }
-    else if xp < 2000 { 5 }
-    else if xp < 4000 { 6 }
-    else if xp < 8000 { 7 }
-    else if xp < 16000 { 8 }
-    else if xp < 32000 { 9 }
-    else { 10 }
-}
\ No newline at end of file
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use game_rules::progression::level_min_xp;
+
+    fn session() -> GameSession {
+        GameSession {
+            is_active: true,
+            hp: 100,
+            max_hp: 100,
+            level: 1,
+            kills: 10,
+            gold_earned: 50,
+            ..Default::default()
+        }
+    }
+
+    /// Args reporting `session`'s own totals
+    fn args_for(session: &GameSession) -> UpdateStatsArgs {
+        UpdateStatsArgs {
+            hp: session.hp,
+            xp: session.xp,
+            gold_earned: session.raw_gold(),
+            time_survived: session.time_survived + session.paused_secs,
+            wave: session.wave,
+            kills: session.kills,
+            level: session.level,
+            is_dead: session.is_dead,
+            combo: 0,
+            tick: Some(session.tick + 1),
+            damage_dealt: session.damage_dealt,
+            damage_taken: session.damage_taken,
+            damage_healed: session.damage_healed,
+        }
+    }
+
+    /// v1 body for `args`: hp, xp, gold, time, wave, kills, level, is_dead
+    fn v1_body(args: &UpdateStatsArgs) -> Vec<u8> {
+        let mut data = vec![UpdateStatsArgs::VERSION_V1];
+        data.extend_from_slice(&args.hp.to_le_bytes());
+        data.extend_from_slice(&args.xp.to_le_bytes());
+        data.extend_from_slice(&args.gold_earned.to_le_bytes());
+        data.extend_from_slice(&args.time_survived.to_le_bytes());
+        data.push(args.wave);
+        data.extend_from_slice(&args.kills.to_le_bytes());
+        data.push(args.level);
+        data.push(args.is_dead as u8);
+        data
+    }
+
+    fn with_version(mut data: Vec<u8>, version: u8) -> Vec<u8> {
+        data[0] = version;
+        data
+    }
+
+    #[test]
+    fn v1_decodes_without_tick_and_is_accepted() {
+        let mut session = session();
+        session.tick = 7;
+        let mut expected = args_for(&session);
+        expected.kills += 3;
+        let args = UpdateStatsArgs::unpack(&v1_body(&expected), &session).unwrap();
+        assert_eq!(args.kills, session.kills + 3);
+        assert_eq!(args.combo, 0);
+        assert_eq!(args.tick, None);
+        assert_eq!(args.damage_dealt, session.damage_dealt);
+        assert_eq!(next_tick(args.tick, session.tick).unwrap(), 8);
+        assert!(require_monotonic(&args, &session).is_ok());
+    }
+
+    #[test]
+    fn v2_adds_combo_without_tick() {
+        let session = session();
+        let mut data = with_version(v1_body(&args_for(&session)), UpdateStatsArgs::VERSION_V2);
+        data.extend_from_slice(&4u32.to_le_bytes());
+        let args = UpdateStatsArgs::unpack(&data, &session).unwrap();
+        assert_eq!(args.combo, 4);
+        assert_eq!(args.tick, None);
+    }
+
+    #[test]
+    fn v4_and_v5_carry_tick_and_damage() {
+        let session = session();
+        let mut data = with_version(v1_body(&args_for(&session)), UpdateStatsArgs::VERSION_V4);
+        data.extend_from_slice(&0u32.to_le_bytes());
+        data.extend_from_slice(&9u64.to_le_bytes());
+        let args = UpdateStatsArgs::unpack(&data, &session).unwrap();
+        assert_eq!(args.tick, Some(9));
+
+        data[0] = UpdateStatsArgs::VERSION_V5;
+        for damage in [11u32, 12, 13] {
+            data.extend_from_slice(&damage.to_le_bytes());
+        }
+        let args = UpdateStatsArgs::unpack(&data, &session).unwrap();
+        assert_eq!(args.tick, Some(9));
+        assert_eq!(
+            (args.damage_dealt, args.damage_taken, args.damage_healed),
+            (11, 12, 13)
+        );
+    }
+
+    #[test]
+    fn delta_fills_unsent_fields_from_session() {
+        let session = session();
+        let mut data = vec![UpdateStatsArgs::VERSION_DELTA];
+        data.extend_from_slice(&UpdateStatsArgs::FIELD_KILLS.to_le_bytes());
+        data.extend_from_slice(&1u64.to_le_bytes());
+        data.extend_from_slice(&12u32.to_le_bytes());
+        let args = UpdateStatsArgs::unpack(&data, &session).unwrap();
+        assert_eq!(args.tick, Some(1));
+        assert_eq!(args.kills, 12);
+        assert_eq!(args.hp, session.hp);
+        assert_eq!(args.gold_earned, session.raw_gold());
+    }
+
+    #[test]
+    fn delta_without_time_keeps_played_time_after_pauses() {
+        let mut session = session();
+        session.time_survived = 100;
+        session.paused_secs = 20;
+        let mut data = vec![UpdateStatsArgs::VERSION_DELTA];
+        data.extend_from_slice(&0u16.to_le_bytes());
+        data.extend_from_slice(&1u64.to_le_bytes());
+        let args = UpdateStatsArgs::unpack(&data, &session).unwrap();
+        assert_eq!(args.time_survived, 120);
+        assert_eq!(args.time_survived.saturating_sub(session.paused_secs), 100);
+    }
+
+    #[test]
+    fn malformed_payloads_are_rejected() {
+        let session = session();
+        let v1 = v1_body(&args_for(&session));
+        assert!(UpdateStatsArgs::unpack(&[], &session).is_none());
+        assert!(UpdateStatsArgs::unpack(&with_version(v1.clone(), 99), &session).is_none());
+        assert!(UpdateStatsArgs::unpack(&v1[..v1.len() - 1], &session).is_none());
+        assert!(
+            UpdateStatsArgs::unpack(&with_version(v1.clone(), UpdateStatsArgs::VERSION_V2), &session)
+                .is_none()
+        );
+        let mut dead = v1;
+        *dead.last_mut().unwrap() = 2;
+        assert!(UpdateStatsArgs::unpack(&dead, &session).is_none());
+
+        let mut delta = vec![UpdateStatsArgs::VERSION_DELTA];
+        delta.extend_from_slice(&(1u16 << 15).to_le_bytes());
+        delta.extend_from_slice(&1u64.to_le_bytes());
+        assert!(UpdateStatsArgs::unpack(&delta, &session).is_none());
+    }
+
+    #[test]
+    fn ticks_must_be_the_next_one() {
+        assert_eq!(next_tick(Some(1), 0).unwrap(), 1);
+        assert_eq!(next_tick(None, 0).unwrap(), 1);
+        for stale in [Some(0), Some(2), Some(u64::MAX)] {
+            assert_eq!(
+                next_tick(stale, 0).unwrap_err(),
+                UpdateStatsError::StaleTick.into()
+            );
+        }
+    }
+
+    #[test]
+    fn unchanged_or_higher_kills_pass() {
+        let session = session();
+        let mut args = args_for(&session);
+        assert!(require_monotonic(&args, &session).is_ok());
+        args.kills += 1;
+        assert!(require_monotonic(&args, &session).is_ok());
+    }
+
+    #[test]
+    fn lower_kills_are_rejected() {
+        let session = session();
+        let mut args = args_for(&session);
+        args.kills -= 1;
+        assert_eq!(
+            require_monotonic(&args, &session).unwrap_err(),
+            UpdateStatsError::KillsDecreased.into()
+        );
+    }
+
+    #[test]
+    fn lower_gold_is_rejected() {
+        let session = session();
+        let mut args = args_for(&session);
+        args.gold_earned -= 1;
+        assert_eq!(
+            require_monotonic(&args, &session).unwrap_err(),
+            UpdateStatsError::GoldDecreased.into()
+        );
+    }
+
+    #[test]
+    fn gold_is_compared_without_the_combo_bonus() {
+        let mut session = session();
+        session.combo_bonus_gold = 20;
+        let mut args = args_for(&session);
+        assert_eq!(args.gold_earned, 30);
+        assert!(require_monotonic(&args, &session).is_ok());
+        args.gold_earned = 29;
+        assert!(require_monotonic(&args, &session).is_err());
+    }
+
+    #[test]
+    fn gold_pickups_ignore_reported_gold() {
+        let mut session = session();
+        session.feature_flags = FEATURE_GOLD_PICKUPS;
+        let mut args = args_for(&session);
+        args.gold_earned = 0;
+        assert!(require_monotonic(&args, &session).is_ok());
+    }
+
+    #[test]
+    fn regen_within_the_character_rate_passes() {
+        let mut session = session();
+        session.hp = 40;
+        let mut args = args_for(&session);
+        args.hp = 40 + session.max_hp_gain(4);
+        assert!(require_hp_regen(&args, &session, 4).is_ok());
+        args.hp = 10;
+        assert!(require_hp_regen(&args, &session, 0).is_ok());
+    }
+
+    #[test]
+    fn regen_above_the_character_rate_is_rejected() {
+        let mut session = session();
+        session.hp = 40;
+        let mut args = args_for(&session);
+        args.hp = 41 + session.max_hp_gain(4);
+        assert_eq!(
+            require_hp_regen(&args, &session, 4).unwrap_err(),
+            UpdateStatsError::HpRegenTooHigh.into()
+        );
+    }
+
+    #[test]
+    fn revive_raised_hp_is_not_counted_as_regen() {
+        // Died at 0 HP, then `use_revive` restored the session's HP
+        let mut session = session();
+        session.hp = session.revive_hp();
+        let mut args = args_for(&session);
+        args.hp += session.max_hp_gain(0);
+        assert!(args.hp > session.max_hp_gain(0));
+        assert!(require_hp_regen(&args, &session, 0).is_ok());
+    }
+
+    /// Level 2 run with 150 XP and room for 300
+    fn leveled_session() -> GameSession {
+        GameSession {
+            level: 2,
+            xp: 150,
+            xp_allowance: 300,
+            ..session()
+        }
+    }
+
+    #[test]
+    fn xp_growth_and_drops_within_the_level_pass() {
+        let session = leveled_session();
+        assert!(require_client_xp(200, &session).is_ok());
+        assert!(require_client_xp(300 + XP_TOLERANCE, &session).is_ok());
+        assert!(require_client_xp(120, &session).is_ok());
+    }
+
+    #[test]
+    fn xp_under_the_current_level_is_rejected() {
+        let session = leveled_session();
+        assert_eq!(
+            require_client_xp(99, &session).unwrap_err(),
+            UpdateStatsError::XpBelowLevel.into()
+        );
+    }
+
+    #[test]
+    fn xp_above_the_allowance_is_rejected() {
+        let session = leveled_session();
+        assert_eq!(
+            require_client_xp(301 + XP_TOLERANCE, &session).unwrap_err(),
+            UpdateStatsError::XpMismatch.into()
+        );
+    }
+
+    const NOW: i64 = 1_700_000_000;
+
+    #[test]
+    fn wave_jump_is_clamped_to_the_played_time() {
+        let mut session = session();
+        session.started_at = NOW - 60;
+        session.last_update_at = NOW - 1;
+        session.wave = 2;
+        let mut args = args_for(&session);
+        args.wave = u8::MAX;
+        args.gold_earned = u32::MAX;
+
+        let flags = apply_plausibility_bounds(&mut args, &session, NOW);
+        assert_eq!(args.wave, session.max_wave(NOW));
+        assert_eq!(args.wave, 3);
+        assert_ne!(flags & SUSPICIOUS_WAVE_RATE, 0);
+        // Gold is capped at the bounded wave, not the reported one
+        assert_eq!(args.gold_earned, session.raw_gold() + max_gold_gain(3, 1));
+    }
+
+    #[test]
+    fn wave_on_schedule_is_accepted() {
+        let mut session = session();
+        session.started_at = NOW - 60;
+        session.last_update_at = NOW - 1;
+        session.wave = 2;
+        let mut args = args_for(&session);
+        args.wave = 3;
+        let flags = apply_plausibility_bounds(&mut args, &session, NOW);
+        assert_eq!(args.wave, 3);
+        assert_eq!(flags & SUSPICIOUS_WAVE_RATE, 0);
+    }
+
+    #[test]
+    fn idle_tick_writes_no_fields() {
+        let mut session = session();
+        let args = args_for(&session);
+        let (xp, gold, time) = (session.xp, session.gold_earned, session.time_survived);
+        assert_eq!(write_stats(&mut session, &args, xp, gold, time), 0);
+        assert_eq!((session.hp, session.kills, session.gold_earned), (100, 10, 50));
+    }
+
+    #[test]
+    fn only_changed_fields_are_written() {
+        let mut session = session();
+        session.damage_dealt = 40;
+        let mut args = args_for(&session);
+        args.hp = 90;
+        args.damage_dealt = 30;
+
+        let (xp, gold) = (session.xp, session.gold_earned);
+        let written = write_stats(&mut session, &args, xp, gold, 6);
+        // HP and time moved; the lower damage report leaves the run total alone
+        assert_eq!(written, 2);
+        assert_eq!((session.hp, session.time_survived, session.damage_dealt), (90, 6, 40));
+    }
+
+    #[test]
+    fn level_above_the_xp_curve_is_reported_once() {
+        let mut session = session();
+        session.level = 5;
+
+        let anomaly = apply_level(&mut session, level_min_xp(2)).unwrap();
+        assert_eq!(anomaly.field_code, ANOMALY_LEVEL_ABOVE_XP);
+        assert_eq!((session.level, session.max_hp), (5, 100));
+
+        // Already reported this run
+        assert!(apply_level(&mut session, level_min_xp(2) + 1).is_none());
+    }
+
+    #[test]
+    fn level_up_raises_max_hp_without_an_anomaly() {
+        let mut session = session();
+        assert!(apply_level(&mut session, level_min_xp(3)).is_none());
+        assert_eq!((session.level, session.max_hp), (3, 100 + LEVEL_UP_MAX_HP_BONUS));
+        assert_eq!(session.anomalies_reported, 0);
+    }
+
+    #[test]
+    fn wave_gold_deltas_sum_to_the_session_total() {
+        let mut session = session();
+        session.wave = 1;
+        session.gold_earned = 0;
+        let mut history = WaveHistory::default();
+        let mut deltas = 0;
+
+        for (wave, gold) in [(1, 30), (2, 80), (2, 100), (3, 150), (5, 240), (5, 260)] {
+            let mut args = args_for(&session);
+            args.wave = wave;
+            if let Some(wave_gold) = finish_wave(&mut session, &mut history, &args, gold, 0, NOW) {
+                deltas += wave_gold.gold_delta;
+            }
+            session.wave = wave;
+            session.gold_earned = gold;
+        }
+
+        // The wave still in progress is reported when it ends
+        assert_eq!(deltas, session.wave_start_gold);
+        assert_eq!(deltas + session.gold_earned - session.wave_start_gold, 260);
+        assert_eq!(history.entries().map(|(_, _, gold, _)| gold).last(), Some(240));
+    }
+}
@@ -1,17 +1,19 @@
 use bolt_lang::*;
 use player::Player;
-use game_session::GameSession;
+use game_session::{GameSession, LUCKY_REVIVE_CHANCE_PCT, ROLL_REVIVE};
 
 declare_id!("GwmXPNJE1MWXBgWaMyYZiemEdboAYFceanBZUkEmBA7H");
 
 #[error_code]
 pub enum UseReviveError {
-    #[msg("Session not active")]
-    SessionNotActive,
+    #[msg("Session has not been started")]
+    SessionNotStarted,
     #[msg("Player is not dead")]
     NotDead,
     #[msg("No revives available")]
     NoRevives,
+    #[msg("Session has already ended")]
+    SessionEnded,
 }
 
 #[system]
@@ -19,23 +21,10 @@ pub mod use_revive {
     /// Use a revive to continue playing after death
     /// This is an L1 transaction (costs SOL for gas)
     /// The player must have available revives
+    /// With fulfilled `ROLL_REVIVE` randomness waiting, the revive has a
+    /// `LUCKY_REVIVE_CHANCE_PCT` chance of not being used up
     pub fn execute(ctx: Context<Components>, _args: Vec<u8>) -> Result<Components> {
-        let player = &mut ctx.accounts.player;
-        let session = &mut ctx.accounts.game_session;
-
-        // Verify session is active but player is dead
-        require!(session.is_active, UseReviveError::SessionNotActive);
-        require!(session.is_dead, UseReviveError::NotDead);
-
-        // Verify player has revives available
-        require!(player.revives > 0, UseReviveError::NoRevives);
-
-        // Use one revive
-        player.revives = player.revives.saturating_sub(1);
-
-        // Restore player to alive state with 50% HP
-        session.is_dead = false;
-        session.hp = session.max_hp / 2;
+        spend_revive(&mut ctx.accounts.player, &mut ctx.accounts.game_session)?;
 
         Ok(ctx.accounts)
     }
@@ -45,4 +34,101 @@ pub mod use_revive {
         pub player: Player,
         pub game_session: GameSession,
     }
+}
+
+/// Spend one of the player's revives (or none, on a lucky roll) to bring a dead run back
+pub fn spend_revive(player: &mut Player, session: &mut GameSession) -> Result<()> {
+    // Verify session is active but player is dead
+    // An inactive session is either one that never started or one `end_game` already closed
+    // (e.g. earlier in the same bundle); report which, so clients don't retry a finished run
+    require!(session.has_started(), UseReviveError::SessionNotStarted);
+    require!(session.is_active, UseReviveError::SessionEnded);
+    require!(session.is_dead, UseReviveError::NotDead);
+
+    // Verify player has revives available
+    require!(player.revives > 0, UseReviveError::NoRevives);
+
+    // Use one revive, unless the oracle-backed revive roll comes up lucky
+    if !is_lucky_revive(session)? {
+        player.revives = player.revives.saturating_sub(1);
+    }
+
+    // Restore player to alive state with 50% HP (never at or below the death threshold)
+    session.revive();
+    Ok(())
+}
+
+/// Roll the session's `ROLL_REVIVE` randomness, if any is waiting; false without one
+pub fn is_lucky_revive(session: &mut GameSession) -> Result<bool> {
+    if !session.has_roll(ROLL_REVIVE) {
+        return Ok(false);
+    }
+    Ok(session.take_roll(ROLL_REVIVE, 100)? < LUCKY_REVIVE_CHANCE_PCT)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use game_session::ROLL_CHEST;
+
+    fn revive_attempt(started_at: i64, is_active: bool) -> (Player, GameSession, Result<()>) {
+        let mut player = Player { revives: 1, ..Default::default() };
+        let mut session = GameSession {
+            started_at,
+            is_active,
+            is_dead: true,
+            max_hp: 100,
+            ..Default::default()
+        };
+        let result = spend_revive(&mut player, &mut session);
+        (player, session, result)
+    }
+
+    #[test]
+    fn session_that_never_started_is_refused() {
+        let (player, session, result) = revive_attempt(0, false);
+        assert_eq!(result.unwrap_err(), UseReviveError::SessionNotStarted.into());
+        assert_eq!(player.revives, 1);
+        assert!(session.is_dead);
+    }
+
+    #[test]
+    fn session_that_already_ended_is_refused() {
+        let (player, session, result) = revive_attempt(1_700_000_000, false);
+        assert_eq!(result.unwrap_err(), UseReviveError::SessionEnded.into());
+        assert_eq!(player.revives, 1);
+        assert!(session.is_dead);
+    }
+
+    #[test]
+    fn active_session_spends_a_revive() {
+        let (player, session, result) = revive_attempt(1_700_000_000, true);
+        result.unwrap();
+        assert_eq!(player.revives, 0);
+        assert!(!session.is_dead);
+    }
+
+    #[test]
+    fn no_waiting_randomness_is_never_lucky() {
+        let mut session = GameSession::default();
+        assert!(!is_lucky_revive(&mut session).unwrap());
+    }
+
+    #[test]
+    fn revive_roll_is_used_up() {
+        let mut session = GameSession::default();
+        session.pending_randomness = [7; 32];
+        session.randomness_event = ROLL_REVIVE;
+        is_lucky_revive(&mut session).unwrap();
+        assert_eq!(session.pending_randomness, [0; 32]);
+    }
+
+    #[test]
+    fn randomness_for_another_event_is_left_alone() {
+        let mut session = GameSession::default();
+        session.pending_randomness = [7; 32];
+        session.randomness_event = ROLL_CHEST;
+        assert!(!is_lucky_revive(&mut session).unwrap());
+        assert_eq!(session.pending_randomness, [7; 32]);
+    }
 }
\ No newline at end of file
@@ -1,3 +1,4 @@
+use bolt_lang::anchor_lang::solana_program::hash::hash;
 use bolt_lang::prelude::*;
 
 declare_id!("PMCNPTdTaQi8zSayLLAth3Y1wSG3EpGqSRYJNZiSpax");
@@ -9,7 +10,221 @@ pub mod magic_baser_solana {
     pub fn initialize(_ctx: Context<Initialize>) -> Result<()> {
         Ok(())
     }
+
+    /// Claim a player name; fails if the name's record already exists
+    /// `name_hash` must be `name_hash(&name)`, so a record always stands for a real name
+    pub fn claim_name(ctx: Context<ClaimName>, name_hash: [u8; 32], name: String) -> Result<()> {
+        require!(crate::name_hash(&name) == name_hash, NameRegistryError::NameHashMismatch);
+
+        let record = &mut ctx.accounts.name_record;
+        record.authority = ctx.accounts.authority.key();
+        record.name_hash = name_hash;
+        Ok(())
+    }
+
+    /// Release a claimed name (e.g. after a rename), returning the rent
+    pub fn release_name(_ctx: Context<ReleaseName>) -> Result<()> {
+        Ok(())
+    }
+
+    /// Release a claimed name into the caller's new record; `rename_player` calls this, where
+    /// the authority signs but isn't writable
+    pub fn release_name_into(_ctx: Context<ReleaseNameInto>) -> Result<()> {
+        Ok(())
+    }
 }
 
 #[derive(Accounts)]
 pub struct Initialize {}
+
+#[derive(Accounts)]
+#[instruction(name_hash: [u8; 32])]
+pub struct ClaimName<'info> {
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + NameRecord::INIT_SPACE,
+        seeds = [NameRecord::SEED_PREFIX, name_hash.as_ref()],
+        bump
+    )]
+    pub name_record: Account<'info, NameRecord>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ReleaseName<'info> {
+    #[account(
+        mut,
+        seeds = [NameRecord::SEED_PREFIX, name_record.name_hash.as_ref()],
+        bump,
+        has_one = authority,
+        close = authority
+    )]
+    pub name_record: Account<'info, NameRecord>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ReleaseNameInto<'info> {
+    #[account(
+        mut,
+        seeds = [NameRecord::SEED_PREFIX, name_record.name_hash.as_ref()],
+        bump,
+        has_one = authority,
+        constraint = name_record.key() != new_record.key() @ NameRegistryError::WrongNameRecord,
+        close = new_record
+    )]
+    pub name_record: Account<'info, NameRecord>,
+    #[account(mut, has_one = authority)]
+    pub new_record: Account<'info, NameRecord>,
+    pub authority: Signer<'info>,
+}
+
+/// Ownership record for a player name, one PDA per lowercased name
+#[account]
+#[derive(InitSpace)]
+pub struct NameRecord {
+    pub authority: Pubkey,
+    pub name_hash: [u8; 32],
+}
+
+impl NameRecord {
+    pub const SEED_PREFIX: &'static [u8] = b"name";
+}
+
+#[error_code]
+pub enum NameRegistryError {
+    #[msg("Account is not the record for this name")]
+    WrongNameRecord,
+    #[msg("Name is claimed by another player")]
+    NameTaken,
+    #[msg("Name has not been claimed")]
+    NameNotClaimed,
+    #[msg("Name hash does not match the name")]
+    NameHashMismatch,
+}
+
+/// Registry key for a name: case-insensitive, so "Bob" and "bob" collide
+pub fn name_hash(name: &str) -> [u8; 32] {
+    hash(name.to_lowercase().as_bytes()).to_bytes()
+}
+
+/// Address of the registry record for `name`
+pub fn name_record_address(name: &str) -> Pubkey {
+    Pubkey::find_program_address(&[NameRecord::SEED_PREFIX, &name_hash(name)], &ID).0
+}
+
+/// Check that `record_info` is the registry record for `name` and belongs to `authority`
+/// Used by the Bolt systems that set a player name
+pub fn verify_name_record(record_info: &AccountInfo, name: &str, authority: &Pubkey) -> Result<()> {
+    require_keys_eq!(*record_info.key, name_record_address(name), NameRegistryError::WrongNameRecord);
+
+    require_keys_eq!(*record_info.owner, ID, NameRegistryError::NameNotClaimed);
+    let record = NameRecord::try_deserialize(&mut &record_info.data.borrow()[..])?;
+    require_keys_eq!(record.authority, *authority, NameRegistryError::NameTaken);
+    Ok(())
+}
+
+/// Whether `record_info`, the registry record for `name`, is currently claimed by `authority`
+/// Names set before the registry existed have no record, so there is nothing to release
+pub fn is_own_name_record(record_info: &AccountInfo, name: &str, authority: &Pubkey) -> Result<bool> {
+    require_keys_eq!(*record_info.key, name_record_address(name), NameRegistryError::WrongNameRecord);
+
+    if *record_info.owner != ID || record_info.data_is_empty() {
+        return Ok(false);
+    }
+    let record = NameRecord::try_deserialize(&mut &record_info.data.borrow()[..])?;
+    Ok(record.authority == *authority)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record_data(authority: Pubkey, name: &str) -> Vec<u8> {
+        let mut data = Vec::new();
+        NameRecord { authority, name_hash: name_hash(name) }
+            .try_serialize(&mut data)
+            .unwrap();
+        data
+    }
+
+    /// Run `check` against an account at `key` owned by `owner` holding `data`
+    fn with_account<T>(key: Pubkey, owner: Pubkey, mut data: Vec<u8>, check: impl FnOnce(&AccountInfo) -> T) -> T {
+        let mut lamports = 1_000_000;
+        let info = AccountInfo::new(&key, false, true, &mut lamports, &mut data, &owner, false, 0);
+        check(&info)
+    }
+
+    #[test]
+    fn name_hash_ignores_case() {
+        assert_eq!(name_hash("Bob"), name_hash("bob"));
+        assert_ne!(name_hash("bob"), name_hash("bobby"));
+        assert_eq!(name_record_address("BOB"), name_record_address("bob"));
+    }
+
+    #[test]
+    fn own_record_verifies() {
+        let authority = Pubkey::new_unique();
+        let result = with_account(name_record_address("Bob"), ID, record_data(authority, "bob"), |info| {
+            verify_name_record(info, "Bob", &authority)
+        });
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn record_for_another_name_is_rejected() {
+        let authority = Pubkey::new_unique();
+        let result = with_account(name_record_address("alice"), ID, record_data(authority, "alice"), |info| {
+            verify_name_record(info, "bob", &authority)
+        });
+        assert_eq!(result.unwrap_err(), NameRegistryError::WrongNameRecord.into());
+    }
+
+    #[test]
+    fn unclaimed_name_is_rejected() {
+        let authority = Pubkey::new_unique();
+        let result = with_account(name_record_address("bob"), Pubkey::default(), Vec::new(), |info| {
+            verify_name_record(info, "bob", &authority)
+        });
+        assert_eq!(result.unwrap_err(), NameRegistryError::NameNotClaimed.into());
+    }
+
+    #[test]
+    fn name_claimed_by_someone_else_is_rejected() {
+        let owner = Pubkey::new_unique();
+        let result = with_account(name_record_address("bob"), ID, record_data(owner, "bob"), |info| {
+            verify_name_record(info, "bob", &Pubkey::new_unique())
+        });
+        assert_eq!(result.unwrap_err(), NameRegistryError::NameTaken.into());
+    }
+
+    #[test]
+    fn only_own_claimed_records_are_released() {
+        let authority = Pubkey::new_unique();
+        let address = name_record_address("bob");
+        assert!(with_account(address, ID, record_data(authority, "bob"), |info| {
+            is_own_name_record(info, "bob", &authority)
+        })
+        .unwrap());
+        assert!(!with_account(address, ID, record_data(Pubkey::new_unique(), "bob"), |info| {
+            is_own_name_record(info, "bob", &authority)
+        })
+        .unwrap());
+        // Pre-registry name: the record was never created
+        assert!(!with_account(address, Pubkey::default(), Vec::new(), |info| {
+            is_own_name_record(info, "bob", &authority)
+        })
+        .unwrap());
+        assert_eq!(
+            with_account(address, ID, record_data(authority, "bob"), |info| {
+                is_own_name_record(info, "alice", &authority)
+            })
+            .unwrap_err(),
+            NameRegistryError::WrongNameRecord.into()
+        );
+    }
+}
@@ -1,86 +1,434 @@
 import { PublicKey } from "@solana/web3.js";
-import { Position } from "../target/types/position";
-import { Movement } from "../target/types/movement";
+import { Player } from "../target/types/player";
+import { GameSession } from "../target/types/game_session";
+import { Leaderboard } from "../target/types/leaderboard";
+import { Config } from "../target/types/config";
+import { WaveHistory } from "../target/types/wave_history";
+import { PlayerExtras } from "../target/types/player_extras";
+import { InitPlayer } from "../target/types/init_player";
+import { SetConfig } from "../target/types/set_config";
+import { StartGame } from "../target/types/start_game";
+import { UpdateStats } from "../target/types/update_stats";
+import { UseRevive } from "../target/types/use_revive";
+import { EndGame } from "../target/types/end_game";
+import { SubmitScore } from "../target/types/submit_score";
+import { RenamePlayer } from "../target/types/rename_player";
+import { MagicBaserSolana } from "../target/types/magic_baser_solana";
 import {
     InitializeNewWorld,
     AddEntity,
     InitializeComponent,
     ApplySystem,
+    FindComponentPda,
     Program
 } from "@magicblock-labs/bolt-sdk"
 import {expect} from "chai";
 import * as anchor from "@coral-xyz/anchor";
 
+// Full run lifecycle against a local validator with the Bolt world:
+// init_player -> start_game -> update_stats xN (level up, death) -> use_revive -> end_game -> submit_score
 describe("magic-baser-solana", () => {
   // Configure the client to use the local cluster.
   const provider = anchor.AnchorProvider.env();
   anchor.setProvider(provider);
+  const authority = provider.wallet.publicKey;
+
+  const playerComponent = anchor.workspace.Player as Program<Player>;
+  const sessionComponent = anchor.workspace.GameSession as Program<GameSession>;
+  const leaderboardComponent = anchor.workspace.Leaderboard as Program<Leaderboard>;
+  const configComponent = anchor.workspace.Config as Program<Config>;
+  const waveHistoryComponent = anchor.workspace.WaveHistory as Program<WaveHistory>;
+  const playerExtrasComponent = anchor.workspace.PlayerExtras as Program<PlayerExtras>;
+
+  const initPlayer = anchor.workspace.InitPlayer as Program<InitPlayer>;
+  const setConfig = anchor.workspace.SetConfig as Program<SetConfig>;
+  const startGame = anchor.workspace.StartGame as Program<StartGame>;
+  const updateStats = anchor.workspace.UpdateStats as Program<UpdateStats>;
+  const useRevive = anchor.workspace.UseRevive as Program<UseRevive>;
+  const endGame = anchor.workspace.EndGame as Program<EndGame>;
+  const submitScore = anchor.workspace.SubmitScore as Program<SubmitScore>;
+  const renamePlayer = anchor.workspace.RenamePlayer as Program<RenamePlayer>;
+  const nameRegistry = anchor.workspace.MagicBaserSolana as Program<MagicBaserSolana>;
 
-  // Constants used to test the program.
   let worldPda: PublicKey;
-  let entityPda: PublicKey;
-  let componentPda: PublicKey;
+  const entities: Record<string, PublicKey> = {};
+  const components: Record<string, PublicKey> = {};
+
+  // Same derivation as the app: `${wallet.slice(0, 20)}-{suffix}`, config has no wallet prefix;
+  // the session entity is save slot 0 ("session")
+  const entitySeed = (suffix: string) =>
+    new TextEncoder().encode(
+      suffix === "config" ? "config" : `${authority.toBase58().slice(0, 20)}-${suffix}`
+    );
+
+  const entity = (name: string, componentId: PublicKey) => ({
+    entity: entities[name],
+    components: [{ componentId }],
+  });
+
+  // update_stats takes the session entity's GameSession and WaveHistory
+  const statsEntity = () => ({
+    entity: entities.session,
+    components: [{ componentId: sessionComponent.programId }, { componentId: waveHistoryComponent.programId }],
+  });
+
+  // start_game / end_game take the player entity's Player and PlayerExtras, plus the entity
+  // itself as an extra account so the system can check they belong together
+  const playerWithExtras = () => ({
+    entity: entities.player,
+    components: [{ componentId: playerComponent.programId }, { componentId: playerExtrasComponent.programId }],
+  });
+  const playerEntityAccount = () => [{ pubkey: entities.player, isSigner: false, isWritable: false }];
+
+  const apply = async (
+    systemId: PublicKey,
+    list: { entity: PublicKey; components: { componentId: PublicKey }[] }[],
+    args: Buffer,
+    extraAccounts: { pubkey: PublicKey; isSigner: boolean; isWritable: boolean }[] = []
+  ) => {
+    const applySystem = await ApplySystem({
+      authority,
+      systemId,
+      world: worldPda,
+      entities: list,
+      extraAccounts,
+      args,
+    });
+    return provider.sendAndConfirm(applySystem.transaction);
+  };
+
+  // UpdateStatsArgs v4: version(1) + hp(2) + xp(4) + gold(4) + time(4) + wave(1) + kills(4)
+  // + level(1) + is_dead(1) + combo(4) + tick(8); ticks count up from 1 after start_game
+  let tick = 0;
+  const statsArgs = (s: {
+    hp: number; xp: number; gold: number; time: number; wave: number; kills: number; level: number; isDead: boolean;
+  }) => {
+    const buf = Buffer.alloc(34);
+    buf.writeUInt8(4, 0);
+    buf.writeUInt16LE(s.hp, 1);
+    buf.writeUInt32LE(s.xp, 3);
+    buf.writeUInt32LE(s.gold, 7);
+    buf.writeUInt32LE(s.time, 11);
+    buf.writeUInt8(s.wave, 15);
+    buf.writeUInt32LE(s.kills, 16);
+    buf.writeUInt8(s.level, 20);
+    buf.writeUInt8(s.isDead ? 1 : 0, 21);
+    buf.writeUInt32LE(0, 22);
+    buf.writeBigUInt64LE(BigInt(++tick), 26);
+    return buf;
+  };
 
-  const positionComponent = anchor.workspace.Position as Program<Position>;
-  const systemMovement = anchor.workspace.Movement as Program<Movement>;
+  // Registry record for a name: ["name", sha256(lowercase(name))]
+  const nameHash = (name: string) => Buffer.from(anchor.utils.sha256.hash(name.toLowerCase()), "hex");
+  const nameRecord = (name: string) =>
+    PublicKey.findProgramAddressSync([Buffer.from("name"), nameHash(name)], nameRegistry.programId)[0];
+  const claimName = (name: string) =>
+    nameRegistry.methods
+      .claimName([...nameHash(name)], name)
+      .accountsPartial({ nameRecord: nameRecord(name), authority })
+      .rpc();
+  const nameArgs = (name: string) => {
+    const bytes = Buffer.from(name);
+    const args = Buffer.alloc(4 + bytes.length);
+    args.writeUInt32LE(bytes.length, 0);
+    bytes.copy(args, 4);
+    return args;
+  };
+  const readOnly = (pubkey: PublicKey) => ({ pubkey, isSigner: false, isWritable: false });
+  const writable = (pubkey: PublicKey) => ({ pubkey, isSigner: false, isWritable: true });
 
   it("InitializeNewWorld", async () => {
     const initNewWorld = await InitializeNewWorld({
-      payer: provider.wallet.publicKey,
+      payer: authority,
       connection: provider.connection,
     });
-    const txSign = await provider.sendAndConfirm(initNewWorld.transaction);
+    await provider.sendAndConfirm(initNewWorld.transaction);
     worldPda = initNewWorld.worldPda;
-    console.log(`Initialized a new world (ID=${worldPda}). Initialization signature: ${txSign}`);
   });
 
-  it("Add an entity", async () => {
+  it("Creates the player (with extras), session (with wave history), leaderboard and config entities", async () => {
+    const layout: [string, PublicKey][] = [
+      ["player", playerComponent.programId],
+      ["session", sessionComponent.programId],
+      ["leaderboard", leaderboardComponent.programId],
+      ["config", configComponent.programId],
+    ];
+    for (const [name, componentId] of layout) {
+      const addEntity = await AddEntity({
+        payer: authority,
+        world: worldPda,
+        seed: entitySeed(name),
+        connection: provider.connection,
+      });
+      await provider.sendAndConfirm(addEntity.transaction);
+      entities[name] = addEntity.entityPda;
+
+      const initializeComponent = await InitializeComponent({
+        payer: authority,
+        entity: entities[name],
+        componentId,
+      });
+      await provider.sendAndConfirm(initializeComponent.transaction);
+      components[name] = initializeComponent.componentPda;
+    }
+
+    const initializeWaveHistory = await InitializeComponent({
+      payer: authority,
+      entity: entities.session,
+      componentId: waveHistoryComponent.programId,
+    });
+    await provider.sendAndConfirm(initializeWaveHistory.transaction);
+    components.waveHistory = initializeWaveHistory.componentPda;
+
+    const initializePlayerExtras = await InitializeComponent({
+      payer: authority,
+      entity: entities.player,
+      componentId: playerExtrasComponent.programId,
+    });
+    await provider.sendAndConfirm(initializePlayerExtras.transaction);
+    components.playerExtras = initializePlayerExtras.componentPda;
+  });
+
+  // Same steps as the app's `initPlayerExtrasIfMissing`: players created before PlayerExtras
+  // existed have only a Player and get zeroed extras; entities that have them are left alone
+  const migratePlayerExtras = async (playerEntity: PublicKey) => {
+    const extras = FindComponentPda({ componentId: playerExtrasComponent.programId, entity: playerEntity });
+    if (await provider.connection.getAccountInfo(extras)) {
+      return false;
+    }
+    const initializeExtras = await InitializeComponent({
+      payer: authority,
+      entity: playerEntity,
+      componentId: playerExtrasComponent.programId,
+    });
+    await provider.sendAndConfirm(initializeExtras.transaction);
+    return true;
+  };
+
+  it("migrates a Player-only entity by adding zeroed PlayerExtras once", async () => {
     const addEntity = await AddEntity({
-      payer: provider.wallet.publicKey,
+      payer: authority,
       world: worldPda,
+      seed: entitySeed("legacy-player"),
       connection: provider.connection,
     });
-    const txSign = await provider.sendAndConfirm(addEntity.transaction);
-    entityPda = addEntity.entityPda;
-    console.log(`Initialized a new Entity (ID=${addEntity.entityId}). Initialization signature: ${txSign}`);
+    await provider.sendAndConfirm(addEntity.transaction);
+    const initializePlayer = await InitializeComponent({
+      payer: authority,
+      entity: addEntity.entityPda,
+      componentId: playerComponent.programId,
+    });
+    await provider.sendAndConfirm(initializePlayer.transaction);
+
+    expect(await migratePlayerExtras(addEntity.entityPda)).to.equal(true);
+    const extrasPda = FindComponentPda({ componentId: playerExtrasComponent.programId, entity: addEntity.entityPda });
+    const extras = await playerExtrasComponent.account.playerExtras.fetch(extrasPda);
+    expect(extras.settings.every((b: number) => b === 0)).to.equal(true);
+    expect(extras.bestUpdatedAt.toNumber()).to.equal(0);
+    expect(extras.starterRevivesGranted).to.equal(false);
+
+    // Migrating again, or migrating a current-layout player, is a no-op
+    expect(await migratePlayerExtras(addEntity.entityPda)).to.equal(false);
+    expect(await migratePlayerExtras(entities.player)).to.equal(false);
   });
 
-  it("Add a component", async () => {
-    const initializeComponent = await InitializeComponent({
-      payer: provider.wallet.publicKey,
-      entity: entityPda,
-      componentId: positionComponent.programId,
-    });
-    const txSign = await provider.sendAndConfirm(initializeComponent.transaction);
-    componentPda = initializeComponent.componentPda;
-    console.log(`Initialized the grid component. Initialization signature: ${txSign}`);
+  it("Configures the world with one starter revive", async () => {
+    // treasury(32) + submit_interval_secs(4) + death_hp_threshold(2) + starter_revives(1)
+    const args = Buffer.alloc(39);
+    authority.toBuffer().copy(args, 0);
+    args.writeUInt8(1, 38);
+    await apply(setConfig.programId, [entity("config", configComponent.programId)], args);
+
+    const config = await configComponent.account.config.fetch(components.config);
+    expect(config.admin.toBase58()).to.equal(authority.toBase58());
+    expect(config.starterRevives).to.equal(1);
   });
 
-  it("Apply a system", async () => {
-    // Check that the component has been initialized and x is 0
-    const positionBefore = await positionComponent.account.position.fetch(
-      componentPda
+  it("claim_name rejects a hash of another name", async () => {
+    try {
+      await nameRegistry.methods
+        .claimName([...nameHash("tester")], "someone-else")
+        .accountsPartial({ nameRecord: nameRecord("tester"), authority })
+        .rpc();
+      expect.fail("claim_name accepted a mismatched hash");
+    } catch (e) {
+      expect(String(e)).to.contain("NameHashMismatch");
+    }
+  });
+
+  it("init_player requires the claimed name record", async () => {
+    const players = [entity("player", playerComponent.programId), entity("leaderboard", leaderboardComponent.programId)];
+    try {
+      await apply(initPlayer.programId, players, nameArgs("tester"));
+      expect.fail("init_player accepted a name without its record");
+    } catch (e) {
+      expect(String(e)).to.contain("MissingNameRecord");
+    }
+
+    await claimName("tester");
+    await apply(initPlayer.programId, players, nameArgs("tester"), [readOnly(nameRecord("tester"))]);
+
+    const player = await playerComponent.account.player.fetch(components.player);
+    expect(player.name).to.equal("tester");
+    expect(player.createdAt.toNumber()).to.be.gt(0);
+  });
+
+  it("start_game grants the starter revive", async () => {
+    const id = Buffer.from("Antonio"); // mixed case resolves to antonio
+    const args = Buffer.alloc(4 + id.length + 1);
+    args.writeUInt32LE(id.length, 0);
+    id.copy(args, 4);
+    await apply(
+      startGame.programId,
+      [
+        entity("session", sessionComponent.programId),
+        entity("config", configComponent.programId),
+        playerWithExtras(),
+      ],
+      args,
+      playerEntityAccount()
+    );
+
+    const session = await sessionComponent.account.gameSession.fetch(components.session);
+    expect(session.characterId).to.equal("antonio");
+    expect(session.maxHp).to.equal(120);
+    expect(session.isActive).to.equal(true);
+    expect(session.tick.toNumber()).to.equal(0);
+    const player = await playerComponent.account.player.fetch(components.player);
+    expect(player.revives).to.equal(1);
+    const extras = await playerExtrasComponent.account.playerExtras.fetch(components.playerExtras);
+    expect(extras.starterRevivesGranted).to.equal(true);
+  });
+
+  it("update_stats levels up and then dies", async () => {
+    const session = [statsEntity()];
+    await apply(updateStats.programId, session,
+      statsArgs({ hp: 100, xp: 0, gold: 5, time: 5, wave: 1, kills: 0, level: 1, isDead: false }));
+    // 4 kills on wave 1 earn 132 XP, enough for level 2 (100 XP)
+    await apply(updateStats.programId, session,
+      statsArgs({ hp: 60, xp: 120, gold: 20, time: 10, wave: 1, kills: 4, level: 2, isDead: false }));
+
+    let state = await sessionComponent.account.gameSession.fetch(components.session);
+    expect(state.level).to.equal(2);
+    expect(state.maxHp).to.equal(130);
+
+    await apply(updateStats.programId, session,
+      statsArgs({ hp: 0, xp: 180, gold: 40, time: 35, wave: 2, kills: 6, level: 2, isDead: false }));
+    state = await sessionComponent.account.gameSession.fetch(components.session);
+    expect(state.isDead).to.equal(true);
+    expect(state.tick.toNumber()).to.equal(3);
+
+    // Reaching wave 2 recorded wave 1's end
+    const history = await waveHistoryComponent.account.waveHistory.fetch(components.waveHistory);
+    expect(history.len).to.equal(1);
+    expect(history.waves[0]).to.equal(1);
+    expect(history.kills[0]).to.equal(6);
+  });
+
+  it("use_revive brings the player back at half HP", async () => {
+    await apply(
+      useRevive.programId,
+      [entity("player", playerComponent.programId), entity("session", sessionComponent.programId)],
+      Buffer.alloc(0)
     );
-    expect(positionBefore.x.toNumber()).to.equal(0);
 
-    // Run the movement system
+    const state = await sessionComponent.account.gameSession.fetch(components.session);
+    expect(state.isDead).to.equal(false);
+    expect(state.hp).to.equal(65);
+    const player = await playerComponent.account.player.fetch(components.player);
+    expect(player.revives).to.equal(0);
+
+    await apply(updateStats.programId, [statsEntity()],
+      statsArgs({ hp: 65, xp: 180, gold: 50, time: 40, wave: 2, kills: 6, level: 2, isDead: false }));
+  });
+
+  // Compute units of an update_stats tick, simulated so the session's tick does not advance
+  const UPDATE_STATS_CU_BUDGET = 60_000;
+  const simulateStats = async (s: Parameters<typeof statsArgs>[0]) => {
     const applySystem = await ApplySystem({
-      authority: provider.wallet.publicKey,
-      systemId: systemMovement.programId,
+      authority,
+      systemId: updateStats.programId,
       world: worldPda,
-      entities: [{
-        entity: entityPda,
-        components: [{ componentId: positionComponent.programId }],
-      }]
+      entities: [statsEntity()],
+      args: statsArgs(s),
     });
-    const txSign = await provider.sendAndConfirm(applySystem.transaction);
-    console.log(`Applied a system. Signature: ${txSign}`);
+    tick--;
+    const result = await provider.simulate(applySystem.transaction);
+    return result.unitsConsumed;
+  };
+
+  it("update_stats skips the writes of an idle tick", async () => {
+    // The last tick was hp 65, xp 180, gold 50, time 40, wave 2, kills 6
+    const idle = await simulateStats({ hp: 65, xp: 180, gold: 50, time: 40, wave: 2, kills: 6, level: 2, isDead: false });
+    const busy = await simulateStats({ hp: 60, xp: 180, gold: 55, time: 41, wave: 2, kills: 7, level: 2, isDead: false });
+    console.log(`update_stats CU: idle ${idle}, busy ${busy}`);
+    expect(idle).to.be.lessThan(busy);
+    expect(busy).to.be.lessThan(UPDATE_STATS_CU_BUDGET);
+  });
+
+  it("end_game promotes the run to the player", async () => {
+    await apply(
+      endGame.programId,
+      [entity("session", sessionComponent.programId), playerWithExtras()],
+      Buffer.alloc(0),
+      playerEntityAccount()
+    );
 
-    // Check that the system has been applied and x is > 0
-    const positionAfter = await positionComponent.account.position.fetch(
-      componentPda
+    const state = await sessionComponent.account.gameSession.fetch(components.session);
+    expect(state.isActive).to.equal(false);
+    const player = await playerComponent.account.player.fetch(components.player);
+    expect(player.gamesPlayed).to.equal(1);
+    expect(player.bestWave).to.equal(2);
+    expect(player.bestTime).to.equal(40);
+    expect(player.lastCharacterId).to.equal("antonio");
+    expect(player.totalGold.toNumber()).to.be.gte(50);
+    const extras = await playerExtrasComponent.account.playerExtras.fetch(components.playerExtras);
+    expect(extras.bestUpdatedAt.toNumber()).to.be.gt(0);
+  });
+
+  it("submit_score mirrors the player on the leaderboard", async () => {
+    const player = await playerComponent.account.player.fetch(components.player);
+
+    // player(32) + best_time(4) + best_wave(1) + total_gold(8) + games_played(4)
+    // + environment_id(1); the character index comes from the player's last character
+    const args = Buffer.alloc(50);
+    authority.toBuffer().copy(args, 0);
+    args.writeUInt32LE(player.bestTime, 32);
+    args.writeUInt8(player.bestWave, 36);
+    args.writeBigUInt64LE(BigInt(player.totalGold.toString()), 37);
+    args.writeUInt32LE(player.gamesPlayed, 45);
+    args.writeUInt8(0, 49);
+    await apply(
+      submitScore.programId,
+      [
+        entity("leaderboard", leaderboardComponent.programId),
+        entity("config", configComponent.programId),
+        entity("player", playerComponent.programId),
+      ],
+      args
     );
-    expect(positionAfter.x.toNumber()).to.gt(0);
+
+    const entry = await leaderboardComponent.account.leaderboardEntry.fetch(components.leaderboard);
+    expect(entry.player.toBase58()).to.equal(authority.toBase58());
+    expect(entry.bestWave).to.equal(2);
+    expect(entry.bestTime).to.equal(40);
+    expect(entry.gamesPlayed).to.equal(1);
+    expect(entry.characterIndex).to.equal(1); // antonio
   });
 
+  it("rename_player moves the name and releases the old record", async () => {
+    await claimName("Renamed");
+    await apply(
+      renamePlayer.programId,
+      [entity("player", playerComponent.programId), entity("leaderboard", leaderboardComponent.programId)],
+      nameArgs("Renamed"),
+      [writable(nameRecord("Renamed")), writable(nameRecord("tester")), readOnly(nameRegistry.programId)]
+    );
+
+    const player = await playerComponent.account.player.fetch(components.player);
+    expect(player.name).to.equal("Renamed");
+    const entry = await leaderboardComponent.account.leaderboardEntry.fetch(components.leaderboard);
+    expect(entry.name).to.equal("Renamed");
+    expect(await provider.connection.getAccountInfo(nameRecord("tester"))).to.equal(null);
+  });
 });